@@ -0,0 +1,167 @@
+/*
+   dumpster, a cycle-tracking garbage collector for Rust.
+   Copyright (C) 2023 Clayton Ramsey.
+
+   This program is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   This program is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Throughput benchmarks for allocation, reference counting, and collection.
+
+mod common;
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use dumpster::unsync::{
+    collect, set_collect_condition, set_free_list_capacity, with_scope, CollectInfo, Gc,
+};
+
+use common::{build_graph, CYCLE_DENSITIES, GRAPH_SIZES};
+
+/// A collection condition which never triggers, so that only explicit collections are measured.
+fn never_collect(_: &CollectInfo) -> bool {
+    false
+}
+
+/// Measure the time to allocate a new `Gc`.
+fn new(c: &mut Criterion) {
+    c.bench_function("new", |b| {
+        b.iter_with_large_drop(|| Gc::new(black_box(0u64)));
+    });
+}
+
+/// Measure the time to allocate a batch of `Gc`s, one at a time and all at once.
+fn new_many(c: &mut Criterion) {
+    let mut group = c.benchmark_group("new_many");
+    group.bench_function("one_at_a_time", |b| {
+        b.iter_with_large_drop(|| {
+            (0..1000u64)
+                .map(|i| Gc::new(black_box(i)))
+                .collect::<Vec<_>>()
+        });
+    });
+    group.bench_function("batch", |b| {
+        b.iter_with_large_drop(|| Gc::new_many((0..1000u64).map(black_box)));
+    });
+    group.finish();
+}
+
+/// Measure the time to allocate, clone, and free a batch of `Gc`s, with and without a `GcScope`.
+fn scope(c: &mut Criterion) {
+    let mut group = c.benchmark_group("scope");
+    group.bench_function("unscoped", |b| {
+        b.iter(|| {
+            for i in 0..1000u64 {
+                let gc = Gc::new(black_box(i));
+                drop(black_box(gc.clone()));
+            }
+        });
+    });
+    group.bench_function("scoped", |b| {
+        b.iter(|| {
+            with_scope(|scope| {
+                for i in 0..1000u64 {
+                    let gc = scope.new_gc(black_box(i));
+                    drop(black_box(scope.clone_gc(&gc)));
+                }
+            });
+        });
+    });
+    group.finish();
+}
+
+/// Measure the time to allocate a new `Gc` and immediately free it, with and without a free list.
+fn new_drop(c: &mut Criterion) {
+    let mut group = c.benchmark_group("new_drop");
+    for capacity in [0, 64] {
+        set_free_list_capacity(capacity);
+        group.bench_function(BenchmarkId::new("free_list", capacity), |b| {
+            b.iter(|| drop(Gc::new(black_box([0u64; 4]))));
+        });
+    }
+    set_free_list_capacity(0);
+    group.finish();
+}
+
+/// Measure the time to clone and then drop a `Gc`.
+fn clone_drop(c: &mut Criterion) {
+    let gc = Gc::new(0u64);
+    c.bench_function("clone_drop", |b| {
+        b.iter(|| drop(black_box(gc.clone())));
+    });
+}
+
+/// Measure the time to collect a graph which has just become garbage.
+fn collect_graph(c: &mut Criterion) {
+    set_collect_condition(never_collect);
+    let mut group = c.benchmark_group("collect");
+    for density in CYCLE_DENSITIES {
+        for size in GRAPH_SIZES {
+            group.bench_with_input(
+                BenchmarkId::new(format!("density_{density}"), size),
+                &size,
+                |b, &size| {
+                    b.iter_batched(
+                        || drop(build_graph(size, density)),
+                        |()| collect(),
+                        BatchSize::PerIteration,
+                    );
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+/// Measure the time to collect a small cycle on a large heap which is otherwise untouched, compared
+/// with a collection after every allocation in the heap has had a reference dropped.
+fn collect_stable_heap(c: &mut Criterion) {
+    set_collect_condition(never_collect);
+    let heap: Vec<Gc<[u64; 4]>> = (0..10_000).map(|_| Gc::new([0; 4])).collect();
+    collect();
+
+    let mut group = c.benchmark_group("collect_stable_heap");
+    group.bench_function("recent", |b| {
+        b.iter_batched(
+            || drop(build_graph(10, 1.0)),
+            |()| collect(),
+            BatchSize::PerIteration,
+        );
+    });
+    group.bench_function("full", |b| {
+        b.iter_batched(
+            || {
+                drop(build_graph(10, 1.0));
+                for gc in &heap {
+                    drop(gc.clone());
+                }
+            },
+            |()| collect(),
+            BatchSize::PerIteration,
+        );
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    new,
+    new_many,
+    scope,
+    new_drop,
+    clone_drop,
+    collect_graph,
+    collect_stable_heap
+);
+criterion_main!(benches);
@@ -0,0 +1,66 @@
+/*
+   dumpster, a cycle-tracking garbage collector for Rust.
+   Copyright (C) 2023 Clayton Ramsey.
+
+   This program is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   This program is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Graph construction shared between the benchmarks.
+
+use std::cell::RefCell;
+
+use dumpster::{unsync::Gc, Collectable, Visitor};
+
+/// The number of nodes in each benchmarked graph.
+pub const GRAPH_SIZES: [usize; 3] = [100, 1_000, 10_000];
+
+/// The fraction of nodes in each benchmarked graph which have an extra edge back to an earlier
+/// node, creating a cycle.
+pub const CYCLE_DENSITIES: [f64; 3] = [0.0, 0.1, 1.0];
+
+/// A node in a benchmarked graph.
+pub struct Node {
+    /// The outgoing edges of this node.
+    edges: RefCell<Vec<Gc<Node>>>,
+}
+
+unsafe impl Collectable for Node {
+    fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+        self.edges.accept(visitor)
+    }
+}
+
+/// Build a graph of `size` nodes, returning its root.
+///
+/// The graph is a random tree in which a `cycle_density` fraction of the nodes also have an edge
+/// back to their parent.
+/// The same arguments always produce the same graph.
+pub fn build_graph(size: usize, cycle_density: f64) -> Gc<Node> {
+    let mut rng = fastrand::Rng::with_seed(size as u64);
+    let nodes: Vec<Gc<Node>> = (0..size)
+        .map(|_| {
+            Gc::new(Node {
+                edges: RefCell::new(Vec::new()),
+            })
+        })
+        .collect();
+    for (i, node) in nodes.iter().enumerate().skip(1) {
+        let parent = &nodes[rng.usize(..i)];
+        parent.edges.borrow_mut().push(node.clone());
+        if rng.f64() < cycle_density {
+            node.edges.borrow_mut().push(parent.clone());
+        }
+    }
+    nodes[0].clone()
+}
@@ -0,0 +1,81 @@
+/*
+   dumpster, a cycle-tracking garbage collector for Rust.
+   Copyright (C) 2023 Clayton Ramsey.
+
+   This program is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   This program is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Benchmarks for the individual phases of a collection.
+//!
+//! These require the `bench` feature, which records how long each phase of a collection takes.
+
+mod common;
+
+use std::time::Duration;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use dumpster::unsync::{
+    collect, last_collect_phase_times, set_collect_condition, CollectInfo, PhaseTimes,
+};
+
+use common::{build_graph, CYCLE_DENSITIES, GRAPH_SIZES};
+
+/// A collection condition which never triggers, so that only explicit collections are measured.
+fn never_collect(_: &CollectInfo) -> bool {
+    false
+}
+
+/// Measure one phase of collecting graphs of every size and density.
+fn bench_phase(c: &mut Criterion, name: &str, phase: fn(&PhaseTimes) -> Duration) {
+    set_collect_condition(never_collect);
+    let mut group = c.benchmark_group(name);
+    for density in CYCLE_DENSITIES {
+        for size in GRAPH_SIZES {
+            group.bench_with_input(
+                BenchmarkId::new(format!("density_{density}"), size),
+                &size,
+                |b, &size| {
+                    b.iter_custom(|iters| {
+                        (0..iters)
+                            .map(|_| {
+                                drop(build_graph(size, density));
+                                collect();
+                                phase(&last_collect_phase_times())
+                            })
+                            .sum()
+                    });
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+/// Measure the time spent building the reference graph.
+fn trace(c: &mut Criterion) {
+    bench_phase(c, "trace", |t| t.trace);
+}
+
+/// Measure the time spent marking reachable allocations.
+fn mark(c: &mut Criterion) {
+    bench_phase(c, "mark", |t| t.mark);
+}
+
+/// Measure the time spent dropping and freeing garbage.
+fn sweep(c: &mut Criterion) {
+    bench_phase(c, "sweep", |t| t.sweep);
+}
+
+criterion_group!(benches, trace, mark, sweep);
+criterion_main!(benches);
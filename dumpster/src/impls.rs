@@ -20,33 +20,46 @@
 
 #![allow(deprecated)]
 
-use std::{
-    borrow::Cow,
-    cell::{Cell, RefCell},
-    collections::{
-        hash_map::{DefaultHasher, RandomState},
-        BTreeSet, BinaryHeap, HashSet, LinkedList, VecDeque,
-    },
-    ffi::{OsStr, OsString},
+use alloc::{
+    borrow::{Cow, ToOwned},
+    boxed::Box,
+    collections::{BTreeMap, BTreeSet, BinaryHeap, LinkedList, VecDeque},
+    rc::Rc,
+    string::String,
+    vec::Vec,
+};
+#[cfg(feature = "lazy-cell")]
+use core::cell::LazyCell;
+use core::{
+    cell::{Cell, OnceCell, RefCell},
+    convert::Infallible,
     hash::{BuildHasherDefault, SipHasher},
     marker::PhantomData,
+    mem::ManuallyDrop,
     num::{
         NonZeroI128, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroIsize, NonZeroU128,
         NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8, NonZeroUsize,
     },
+    sync::atomic::{
+        AtomicI16, AtomicI32, AtomicI64, AtomicI8, AtomicIsize, AtomicU16, AtomicU32, AtomicU64,
+        AtomicU8, AtomicUsize,
+    },
+};
+#[cfg(feature = "std")]
+use std::{
+    collections::{
+        hash_map::{DefaultHasher, RandomState},
+        HashMap, HashSet,
+    },
+    ffi::{OsStr, OsString},
     ops::Deref,
     path::{Path, PathBuf},
-    rc::Rc,
-    sync::{
-        atomic::{
-            AtomicI16, AtomicI32, AtomicI64, AtomicI8, AtomicIsize, AtomicU16, AtomicU32,
-            AtomicU64, AtomicU8, AtomicUsize,
-        },
-        Mutex, MutexGuard, RwLock, RwLockReadGuard, TryLockError,
-    },
+    sync::{Arc, Mutex, MutexGuard, RwLock, RwLockReadGuard, TryLockError},
 };
 
-use crate::{Collectable, Visitor};
+use crate::{
+    __private::AcyclicSealed, Acyclic, AnyCollectable, Collectable, DynPartialEq, Visitor,
+};
 
 /// Implement `Collectable` trivially for some parametric `?Sized` type.
 macro_rules! param_trivial_impl_unsized {
@@ -60,24 +73,52 @@ macro_rules! param_trivial_impl_unsized {
     };
 }
 
+#[cfg(feature = "std")]
 param_trivial_impl_unsized!(MutexGuard<'static, T>);
+#[cfg(feature = "std")]
 param_trivial_impl_unsized!(RwLockReadGuard<'static, T>);
 param_trivial_impl_unsized!(&'static T);
 param_trivial_impl_unsized!(PhantomData<T>);
 
+impl<T: Acyclic + ?Sized> AcyclicSealed for &'static T {}
+impl<T: Acyclic + ?Sized> Acyclic for &'static T {}
+impl<T: ?Sized> AcyclicSealed for PhantomData<T> {}
+impl<T: ?Sized> Acyclic for PhantomData<T> {}
+
+/// A `dyn AnyCollectable` forwards the garbage collector's own visitors to the concrete type.
+/// Any other visitor cannot be forwarded, so it gets an error instead.
+unsafe impl Collectable for dyn AnyCollectable {
+    fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+        self.erased_accept(visitor.as_any_mut().ok_or(())?)
+    }
+}
+
+/// A `dyn DynPartialEq` is traced just like a `dyn AnyCollectable`.
+unsafe impl Collectable for dyn DynPartialEq {
+    fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+        self.erased_accept(visitor.as_any_mut().ok_or(())?)
+    }
+}
+
 unsafe impl<T: Collectable + ?Sized> Collectable for Box<T> {
     fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
         (**self).accept(visitor)
     }
 }
 
+impl<T: Acyclic + ?Sized> AcyclicSealed for Box<T> {}
+impl<T: Acyclic + ?Sized> Acyclic for Box<T> {}
+
 unsafe impl<T> Collectable for BuildHasherDefault<T> {
     fn accept<V: Visitor>(&self, _: &mut V) -> Result<(), ()> {
         Ok(())
     }
 }
 
-unsafe impl<'a, T: ToOwned> Collectable for Cow<'a, T>
+impl<T> AcyclicSealed for BuildHasherDefault<T> {}
+impl<T> Acyclic for BuildHasherDefault<T> {}
+
+unsafe impl<T: ToOwned> Collectable for Cow<'_, T>
 where
     T::Owned: Collectable,
 {
@@ -89,6 +130,26 @@ where
     }
 }
 
+impl<T: ToOwned> AcyclicSealed for Cow<'_, T> where T::Owned: Acyclic {}
+impl<T: ToOwned> Acyclic for Cow<'_, T> where T::Owned: Acyclic {}
+
+/// A `ManuallyDrop` is visited just like the value it wraps.
+/// If the value is dropped with [`ManuallyDrop::drop`], its `ManuallyDrop` must never be visited
+/// again.
+unsafe impl<T: Collectable + ?Sized> Collectable for ManuallyDrop<T> {
+    #[inline]
+    fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+        (**self).accept(visitor)
+    }
+}
+
+impl<T: Acyclic + ?Sized> AcyclicSealed for ManuallyDrop<T> {}
+impl<T: Acyclic + ?Sized> Acyclic for ManuallyDrop<T> {}
+
+/// A `RefCell` which is mutably borrowed cannot be inspected, since its value may be partway
+/// through being changed, so visiting it returns `Err`.
+/// The garbage collector then treats every `Gc` inside it as a root, keeping all that it can reach
+/// alive.
 unsafe impl<T: Collectable + ?Sized> Collectable for RefCell<T> {
     #[inline]
     fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
@@ -96,6 +157,10 @@ unsafe impl<T: Collectable + ?Sized> Collectable for RefCell<T> {
     }
 }
 
+impl<T: Acyclic + ?Sized> AcyclicSealed for RefCell<T> {}
+impl<T: Acyclic + ?Sized> Acyclic for RefCell<T> {}
+
+#[cfg(feature = "std")]
 unsafe impl<T: Collectable + ?Sized> Collectable for Mutex<T> {
     #[inline]
     fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
@@ -109,6 +174,37 @@ unsafe impl<T: Collectable + ?Sized> Collectable for Mutex<T> {
     }
 }
 
+#[cfg(feature = "std")]
+impl<T: Acyclic + ?Sized> AcyclicSealed for Mutex<T> {}
+#[cfg(feature = "std")]
+impl<T: Acyclic + ?Sized> Acyclic for Mutex<T> {}
+
+#[cfg(feature = "std")]
+/// An `Arc<Mutex<T>>` is only traced when it is the sole owner of its contents, i.e. when there
+/// are no other strong or weak references to the same `Arc`.
+/// In that case it behaves just like a `Box<Mutex<T>>`, and any `Gc`s inside it are visible to the
+/// garbage collector.
+///
+/// If the `Arc` is shared, its contents could be reachable from outside the garbage-collected heap,
+/// so they are conservatively treated as reachable.
+/// Likewise, if the mutex is locked, its contents are treated as reachable.
+/// Note that cycles made of `Arc`s themselves are never broken.
+unsafe impl<T: Collectable + ?Sized> Collectable for Arc<Mutex<T>> {
+    #[inline]
+    fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+        if Arc::strong_count(self) != 1 || Arc::weak_count(self) != 0 {
+            return Err(());
+        }
+        (**self).accept(visitor)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: Acyclic + ?Sized> AcyclicSealed for Arc<Mutex<T>> {}
+#[cfg(feature = "std")]
+impl<T: Acyclic + ?Sized> Acyclic for Arc<Mutex<T>> {}
+
+#[cfg(feature = "std")]
 unsafe impl<T: Collectable + ?Sized> Collectable for RwLock<T> {
     #[inline]
     fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
@@ -122,6 +218,11 @@ unsafe impl<T: Collectable + ?Sized> Collectable for RwLock<T> {
     }
 }
 
+#[cfg(feature = "std")]
+impl<T: Acyclic + ?Sized> AcyclicSealed for RwLock<T> {}
+#[cfg(feature = "std")]
+impl<T: Acyclic + ?Sized> Acyclic for RwLock<T> {}
+
 unsafe impl<T: Collectable> Collectable for Option<T> {
     #[inline]
     fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
@@ -132,6 +233,9 @@ unsafe impl<T: Collectable> Collectable for Option<T> {
     }
 }
 
+impl<T: Acyclic> AcyclicSealed for Option<T> {}
+impl<T: Acyclic> Acyclic for Option<T> {}
+
 unsafe impl<T: Collectable, E: Collectable> Collectable for Result<T, E> {
     #[inline]
     fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
@@ -142,12 +246,54 @@ unsafe impl<T: Collectable, E: Collectable> Collectable for Result<T, E> {
     }
 }
 
+impl<T: Acyclic, E: Acyclic> AcyclicSealed for Result<T, E> {}
+impl<T: Acyclic, E: Acyclic> Acyclic for Result<T, E> {}
+
+/// A `Cell` is visited by copying its value out, so it is only `Collectable` when its contents are
+/// `Copy`.
+/// There is no safe way to look inside a `Cell` of a non-`Copy` type (such as a `Cell<Gc<T>>`)
+/// without taking its value, so such cells must be replaced by a `RefCell`.
 unsafe impl<T: Copy + Collectable> Collectable for Cell<T> {
     fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
         self.get().accept(visitor)
     }
 }
 
+impl<T: Copy + Acyclic> AcyclicSealed for Cell<T> {}
+impl<T: Copy + Acyclic> Acyclic for Cell<T> {}
+
+unsafe impl<T: Collectable> Collectable for OnceCell<T> {
+    #[inline]
+    fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+        match self.get() {
+            Some(value) => value.accept(visitor),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T: Acyclic> AcyclicSealed for OnceCell<T> {}
+impl<T: Acyclic> Acyclic for OnceCell<T> {}
+
+#[cfg(feature = "lazy-cell")]
+#[clippy::msrv = "1.94"]
+/// Only the value of a `LazyCell` which has already been forced is visited.
+/// The initializer of a `LazyCell` which has not been forced yet is opaque, just like any other
+/// closure, so any `Gc`s it captures are treated as roots: they will never be freed early, but
+/// cycles passing through them will not be collected.
+///
+/// This implementation is only available with the `lazy-cell` feature enabled, since it needs
+/// `LazyCell::get`, which was stabilized in Rust 1.94.
+unsafe impl<T: Collectable, F: FnOnce() -> T> Collectable for LazyCell<T, F> {
+    #[inline]
+    fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+        match LazyCell::get(self) {
+            Some(value) => value.accept(visitor),
+            None => Ok(()),
+        }
+    }
+}
+
 /// Implement [`Collectable`] for a collection data structure which has some method `iter()` that
 /// iterates over all elements of the data structure and `iter_mut()` which does the same over
 /// mutable references.
@@ -162,6 +308,9 @@ macro_rules! collectable_collection_impl {
                 Ok(())
             }
         }
+
+        impl<T: Acyclic> AcyclicSealed for $x {}
+        impl<T: Acyclic> Acyclic for $x {}
     };
 }
 
@@ -169,10 +318,60 @@ collectable_collection_impl!(Vec<T>);
 collectable_collection_impl!(VecDeque<T>);
 collectable_collection_impl!(LinkedList<T>);
 collectable_collection_impl!([T]);
-collectable_collection_impl!(HashSet<T>);
 collectable_collection_impl!(BinaryHeap<T>);
 collectable_collection_impl!(BTreeSet<T>); // awaiting stabilization of `drain` on `BTreeSet`
 
+unsafe impl<K: Collectable, V: Collectable> Collectable for BTreeMap<K, V> {
+    #[inline]
+    fn accept<Z: Visitor>(&self, visitor: &mut Z) -> Result<(), ()> {
+        for (k, v) in self {
+            k.accept(visitor)?;
+            v.accept(visitor)?;
+        }
+        Ok(())
+    }
+}
+
+impl<K: Acyclic, V: Acyclic> AcyclicSealed for BTreeMap<K, V> {}
+impl<K: Acyclic, V: Acyclic> Acyclic for BTreeMap<K, V> {}
+
+// The hasher of a map or set is visited too, since a custom `BuildHasher` may itself hold
+// garbage-collected pointers (such as to shared salt data).
+// The price is that a map or set can only be collected if its hasher is `Collectable`; hashers
+// without any garbage-collected pointers in them can implement it trivially.
+#[cfg(feature = "std")]
+unsafe impl<K: Collectable, V: Collectable, S: Collectable> Collectable for HashMap<K, V, S> {
+    #[inline]
+    fn accept<Z: Visitor>(&self, visitor: &mut Z) -> Result<(), ()> {
+        for (k, v) in self {
+            k.accept(visitor)?;
+            v.accept(visitor)?;
+        }
+        self.hasher().accept(visitor)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K: Acyclic, V: Acyclic, S: Acyclic> AcyclicSealed for HashMap<K, V, S> {}
+#[cfg(feature = "std")]
+impl<K: Acyclic, V: Acyclic, S: Acyclic> Acyclic for HashMap<K, V, S> {}
+
+#[cfg(feature = "std")]
+unsafe impl<T: Collectable, S: Collectable> Collectable for HashSet<T, S> {
+    #[inline]
+    fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+        for elem in self {
+            elem.accept(visitor)?;
+        }
+        self.hasher().accept(visitor)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: Acyclic, S: Acyclic> AcyclicSealed for HashSet<T, S> {}
+#[cfg(feature = "std")]
+impl<T: Acyclic, S: Acyclic> Acyclic for HashSet<T, S> {}
+
 unsafe impl<T: Collectable, const N: usize> Collectable for [T; N] {
     #[inline]
     fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
@@ -183,8 +382,11 @@ unsafe impl<T: Collectable, const N: usize> Collectable for [T; N] {
     }
 }
 
-/// Implement [`Collectable`] for a trivially-collected type which contains no  [`Gc`]s in its
-/// fields.
+impl<T: Acyclic, const N: usize> AcyclicSealed for [T; N] {}
+impl<T: Acyclic, const N: usize> Acyclic for [T; N] {}
+
+/// Implement [`Collectable`] and [`Acyclic`] for a trivially-collected type which contains no
+/// [`Gc`]s in its fields.
 macro_rules! collectable_trivial_impl {
     ($x: ty) => {
         unsafe impl Collectable for $x {
@@ -193,10 +395,14 @@ macro_rules! collectable_trivial_impl {
                 Ok(())
             }
         }
+
+        impl AcyclicSealed for $x {}
+        impl Acyclic for $x {}
     };
 }
 
 collectable_trivial_impl!(());
+collectable_trivial_impl!(Infallible);
 
 collectable_trivial_impl!(u8);
 collectable_trivial_impl!(u16);
@@ -243,12 +449,18 @@ collectable_trivial_impl!(NonZeroIsize);
 
 collectable_trivial_impl!(String);
 collectable_trivial_impl!(str);
+#[cfg(feature = "std")]
 collectable_trivial_impl!(PathBuf);
+#[cfg(feature = "std")]
 collectable_trivial_impl!(Path);
+#[cfg(feature = "std")]
 collectable_trivial_impl!(OsString);
+#[cfg(feature = "std")]
 collectable_trivial_impl!(OsStr);
 
+#[cfg(feature = "std")]
 collectable_trivial_impl!(DefaultHasher);
+#[cfg(feature = "std")]
 collectable_trivial_impl!(RandomState);
 collectable_trivial_impl!(Rc<str>);
 collectable_trivial_impl!(SipHasher);
@@ -265,6 +477,9 @@ macro_rules! collectable_tuple {
                 Ok(())
             }
         }
+
+        impl<$($args: Acyclic),*> AcyclicSealed for ($($args,)*) {}
+        impl<$($args: Acyclic),*> Acyclic for ($($args,)*) {}
     }
 }
 
@@ -279,13 +494,18 @@ collectable_tuple!(A, B, C, D, E, F, G);
 collectable_tuple!(A, B, C, D, E, F, G, H);
 collectable_tuple!(A, B, C, D, E, F, G, H, I);
 collectable_tuple!(A, B, C, D, E, F, G, H, I, J);
+collectable_tuple!(A, B, C, D, E, F, G, H, I, J, K);
+collectable_tuple!(A, B, C, D, E, F, G, H, I, J, K, L);
 
-/// Implement `Collectable` for one function type.
+/// Implement `Collectable` and `Acyclic` for one function type.
 macro_rules! collectable_fn {
     ($ty:ty $(,$args:ident)*) => {
         unsafe impl<Ret $(,$args)*> Collectable for $ty {
             fn accept<V: Visitor>(&self, _: &mut V) -> Result<(), ()> { Ok(()) }
         }
+
+        impl<Ret $(,$args)*> AcyclicSealed for $ty {}
+        impl<Ret $(,$args)*> Acyclic for $ty {}
     }
 }
 
@@ -318,3 +538,43 @@ collectable_fn_group!(A, B, C, D, E, F, G);
 collectable_fn_group!(A, B, C, D, E, F, G, H);
 collectable_fn_group!(A, B, C, D, E, F, G, H, I);
 collectable_fn_group!(A, B, C, D, E, F, G, H, I, J);
+
+#[cfg(feature = "petgraph")]
+unsafe impl<N, E, Ty, Ix> Collectable for petgraph::Graph<N, E, Ty, Ix>
+where
+    N: Collectable,
+    E: Collectable,
+    Ty: petgraph::EdgeType,
+    Ix: petgraph::graph::IndexType,
+{
+    #[inline]
+    fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+        for weight in self.node_weights() {
+            weight.accept(visitor)?;
+        }
+        for weight in self.edge_weights() {
+            weight.accept(visitor)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "petgraph")]
+unsafe impl<N, E, Ty, Ix> Collectable for petgraph::stable_graph::StableGraph<N, E, Ty, Ix>
+where
+    N: Collectable,
+    E: Collectable,
+    Ty: petgraph::EdgeType,
+    Ix: petgraph::graph::IndexType,
+{
+    #[inline]
+    fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+        for weight in self.node_weights() {
+            weight.accept(visitor)?;
+        }
+        for weight in self.edge_weights() {
+            weight.accept(visitor)?;
+        }
+        Ok(())
+    }
+}
@@ -129,7 +129,23 @@
 //!
 //! # Optional features
 //!
-//! `dumpster` has two optional features: `derive` and `coerce-unsized`.
+//! `dumpster` has thirteen optional features: `std`, `derive`, `coerce-unsized`, `allocator_api`,
+//! `lazy-cell`, `debug-allocations`, `backtrace`, `track-allocations`, `bytemuck`, `serde`,
+//! `petgraph`, `metrics`, and `bench`.
+//!
+//! `std` is enabled by default.
+//! Without it, `dumpster` only depends on `core` and `alloc`, so it can be used on embedded targets
+//! which have a global allocator but no operating system.
+//! In that case, the [`sync`] module is unavailable, and the state of the [`unsync`] garbage
+//! collector is kept in a single static instead of in thread-local storage.
+//! Since that state cannot be shared between threads, the program must call
+//! `unsync::assume_single_threaded` before creating its first `Gc`.
+//! Collections use ordered maps instead of hash maps without `std`, so they are somewhat slower.
+//!
+//! ```toml
+//! [dependencies]
+//! dumpster = { version = "0.1.0", default-features = false, features = ["derive"] }
+//! ```
 //!
 //! `derive` is enabled by default.
 //! It enables the derive macro for `Collectable`, which makes it easy for users to implement their
@@ -169,6 +185,56 @@ let gc1: Gc<[u8]> = Gc::new([1, 2, 3]);
 //! dumpster = { version = "0.1.0", features = ["coerce-unsized"]}
 //! ```
 //!
+//! `allocator_api` is disabled by default, and also requires nightly Rust.
+//! It enables `unsync::Gc::new_in`, which makes an allocation in a user-supplied
+//! `Allocator` (such as an arena) instead of the global allocator.
+//!
+//! `lazy-cell` is disabled by default, and requires Rust 1.94 or newer.
+//! It implements `Collectable` for `LazyCell`, which needs `LazyCell::get` to look inside the cell
+//! without forcing it.
+//!
+//! `debug-allocations` is disabled by default.
+//! It keeps a registry of every living allocation made by [`unsync::Gc`], which enables debugging
+//! queries such as `unsync::allocations_of_type`.
+//! This registry adds overhead to every allocation and deallocation, so it should only be used when
+//! hunting for leaks.
+//!
+//! `backtrace` is disabled by default, and implies `debug-allocations`.
+//! It makes it possible to capture a backtrace whenever an [`unsync::Gc`] allocation is created,
+//! so that leaked allocations can be attributed to the code which created them.
+//! Capture must additionally be switched on at runtime with `unsync::set_capture_backtraces`.
+//!
+//! `track-allocations` is disabled by default.
+//! It reports every allocation made or freed by [`unsync::Gc`], along with its size and type, to a
+//! function set with `unsync::set_alloc_event_sink`, so that the memory used by `Gc`s can be
+//! attributed in an allocation profiler.
+//!
+//! `bytemuck` is disabled by default.
+//! It enables `unsync::Gc::as_bytes`, which views a garbage-collected plain-old-data value as raw
+//! bytes using the [`bytemuck`](https://docs.rs/bytemuck) crate.
+//!
+//! `serde` is disabled by default.
+//! It implements [`serde`](https://docs.rs/serde)'s `Serialize` and `Deserialize` for
+//! [`unsync::Gc`], which are (de)serialized as the value they point to.
+//! Since each `Gc` is serialized separately, only acyclic graphs can be serialized, and
+//! allocations shared by several `Gc`s are duplicated by a round trip.
+//!
+//! `petgraph` is disabled by default.
+//! It implements `Collectable` for [`petgraph`](https://docs.rs/petgraph)'s `Graph` and
+//! `StableGraph`, visiting the weights of every node and edge, so that graphs whose weights hold
+//! `Gc`s can take part in cycle collection.
+//!
+//! `metrics` is disabled by default, and implies `std`.
+//! It times every collection made by [`unsync::Gc`], reporting each pause to a function set with
+//! `unsync::set_collect_latency_hook` so that it can be recorded in a latency histogram.
+//! In debug builds, the pauses can also be read back with `unsync::collection_latencies`.
+//!
+//! `bench` is disabled by default.
+//! It makes the collector record how long each phase of a collection takes, which can be read back
+//! with `unsync::last_collect_phase_times`.
+//! It is intended for benchmarking the garbage collector itself, and adds a small amount of
+//! overhead to every collection.
+//!
 //! # License
 //!
 //! `dumpster` is licensed under the GNU GPLv3 or later.
@@ -180,17 +246,24 @@ let gc1: Gc<[u8]> = Gc::new([1, 2, 3]);
 #![warn(missing_docs)]
 #![warn(clippy::missing_docs_in_private_items)]
 #![allow(clippy::multiple_crate_versions, clippy::result_unit_err)]
+#![cfg_attr(not(feature = "std"), no_std)]
 #![cfg_attr(feature = "coerce-unsized", feature(coerce_unsized))]
 #![cfg_attr(feature = "coerce-unsized", feature(unsize))]
+#![cfg_attr(feature = "allocator_api", feature(allocator_api))]
+
+extern crate alloc;
 
-use std::{
+use core::{
+    any::Any,
     fmt,
     mem::{size_of, MaybeUninit},
     ptr::{addr_of, addr_of_mut, copy_nonoverlapping, NonNull},
 };
 
 mod impls;
+mod map;
 
+#[cfg(feature = "std")]
 pub mod sync;
 pub mod unsync;
 
@@ -259,6 +332,13 @@ pub mod unsync;
 ///     }
 /// }
 /// ```
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be traced by the garbage collector",
+    label = "`{Self}` does not implement `Collectable`",
+    note = "use `#[derive(Collectable)]` to make a type collectable",
+    note = "closures hide the `Gc`s they capture from the garbage collector; use `dumpster::GcFn` \
+            to store captured `Gc`s where they can be traced"
+)]
 pub unsafe trait Collectable {
     /// Accept a visitor to this garbage-collected value.
     ///
@@ -276,9 +356,242 @@ pub unsafe trait Collectable {
     /// after delegating acceptance to it, or if this value's data is inaccessible (such as
     /// attempting to borrow from a [`RefCell`](std::cell::RefCell) which has already been
     /// mutably borrowed).
+    ///
+    /// The garbage collector treats a value which returns an error as reachable, along with
+    /// everything it points to, so nothing which might still be in use is ever freed.
+    /// An unreachable cycle through such a value is collected by a later collection once the value
+    /// can be traced again.
     fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()>;
 }
 
+/// A marker for collectable types which can never contain a garbage-collected pointer.
+///
+/// A value of an `Acyclic` type owns no [`unsync::Gc`] or [`sync::Gc`], so an allocation holding
+/// one can never be part of a reference cycle.
+/// [`unsync::Gc::new_acyclic`] uses this to skip the bookkeeping which the garbage collector would
+/// otherwise need to find cycles through the allocation.
+///
+/// This trait is sealed: it can only be implemented with `#[derive(Acyclic)]`, which checks at
+/// compile time that the type of every field is `Acyclic`, and by `dumpster` itself for the types
+/// it supports.
+/// This guarantees that no `Acyclic` type contains a `Gc`, so `new_acyclic` can never leak a cycle.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::{unsync::Gc, Acyclic, Collectable};
+///
+/// #[derive(Collectable, Acyclic)]
+/// struct Point {
+///     x: f64,
+///     y: f64,
+///     label: String,
+/// }
+///
+/// let gc = Gc::new_acyclic(Point {
+///     x: 1.0,
+///     y: 2.0,
+///     label: String::from("origin"),
+/// });
+/// ```
+pub trait Acyclic: Collectable + __private::AcyclicSealed {}
+
+#[doc(hidden)]
+/// Items which are only public so that the code generated by `dumpster_derive` can use them.
+pub mod __private {
+    /// The trait which seals [`Acyclic`](crate::Acyclic).
+    ///
+    /// It is implemented alongside `Acyclic` by `#[derive(Acyclic)]` and in `dumpster` itself, and
+    /// must not be implemented anywhere else.
+    pub trait AcyclicSealed {}
+}
+
+/// A [`Collectable`] value whose type has been erased, but which can still be traced.
+///
+/// Every `'static` type which implements `Collectable` also implements `AnyCollectable`.
+/// Unlike a `dyn Any`, a `dyn AnyCollectable` passes the garbage collector on to the concrete
+/// type's [`Collectable::accept`], so a [`unsync::Gc<dyn AnyCollectable>`](unsync::Gc) takes part
+/// in cycle collection just like a `Gc` to the concrete type.
+/// It is mostly useful through [`unsync::Gc::into_any`] and [`unsync::Gc::downcast`].
+///
+/// The garbage collector's own visitors are the only ones which can see inside a
+/// `dyn AnyCollectable`; any other [`Visitor`] gets an error from `accept`.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::AnyCollectable;
+///
+/// let value: &dyn AnyCollectable = &5u8;
+/// assert!(value.is::<u8>());
+/// assert_eq!(value.downcast_ref::<u8>(), Some(&5));
+/// assert_eq!(value.downcast_ref::<u16>(), None);
+/// ```
+pub trait AnyCollectable: Any {
+    #[doc(hidden)]
+    /// View `self` as a `dyn Any`.
+    ///
+    /// Use [`<dyn AnyCollectable>::as_any`](#method.as_any) instead, which cannot be confused with
+    /// this method on a `Gc` which also implements `AnyCollectable`.
+    fn erased_as_any(&self) -> &dyn Any;
+
+    #[doc(hidden)]
+    /// Accept `visitor`, which is one of the garbage collector's own visitors viewed through
+    /// [`Visitor::as_any_mut`].
+    ///
+    /// # Errors
+    ///
+    /// Errors are returned if `visitor` is not one of the garbage collector's own visitors, or if
+    /// the value's [`Collectable::accept`] returns an error.
+    fn erased_accept(&self, visitor: &mut dyn Any) -> Result<(), ()>;
+}
+
+impl<T: Collectable + Any> AnyCollectable for T {
+    fn erased_as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn erased_accept(&self, visitor: &mut dyn Any) -> Result<(), ()> {
+        unsync::accept_any(self, visitor)
+    }
+}
+
+impl dyn AnyCollectable {
+    #[must_use]
+    /// View `self` as a [`dyn Any`](Any).
+    pub fn as_any(&self) -> &dyn Any {
+        self.erased_as_any()
+    }
+
+    #[must_use]
+    /// Determine whether the erased value is a `T`.
+    pub fn is<T: Any>(&self) -> bool {
+        self.as_any().is::<T>()
+    }
+
+    #[must_use]
+    /// Get a reference to the erased value if it is a `T`, or `None` if it isn't.
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.as_any().downcast_ref()
+    }
+}
+
+impl fmt::Debug for dyn AnyCollectable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AnyCollectable").finish_non_exhaustive()
+    }
+}
+
+/// An object-safe form of [`PartialEq`], for comparing values whose types have been erased.
+///
+/// Every `'static` [`Collectable`] type which implements `PartialEq` also implements
+/// `DynPartialEq`.
+/// Like a [`dyn AnyCollectable`](AnyCollectable), a `dyn DynPartialEq` can still be traced by the
+/// garbage collector.
+/// Two values compare equal through this trait only if they have the same concrete type and are
+/// equal according to that type's `PartialEq` implementation.
+/// This is mostly useful through [`unsync::Gc::dyn_eq`], which compares two
+/// `Gc<dyn DynPartialEq>`s.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::DynPartialEq;
+///
+/// let a: &dyn DynPartialEq = &1u8;
+/// let b: &dyn DynPartialEq = &1u8;
+/// let c: &dyn DynPartialEq = &1u16;
+/// assert!(a.dyn_eq(b));
+/// assert!(!a.dyn_eq(c));
+/// ```
+pub trait DynPartialEq: AnyCollectable {
+    /// Determine whether `self` and `other` have the same type and are equal.
+    fn dyn_eq(&self, other: &dyn DynPartialEq) -> bool;
+}
+
+impl<T: PartialEq + Collectable + Any> DynPartialEq for T {
+    fn dyn_eq(&self, other: &dyn DynPartialEq) -> bool {
+        other.as_any().downcast_ref::<T>() == Some(self)
+    }
+}
+
+impl dyn DynPartialEq {
+    #[must_use]
+    /// View `self` as a [`dyn Any`](Any), so that it can be downcast.
+    pub fn as_any(&self) -> &dyn Any {
+        self.erased_as_any()
+    }
+}
+
+/// A closure whose captured garbage-collected pointers are visible to the garbage collector.
+///
+/// The body of a closure is opaque, so the garbage collector cannot find the `Gc`s it captures,
+/// and there is deliberately no `Collectable` implementation for `Box<dyn Fn()>` and the like.
+/// Instead, a `GcFn` keeps everything that its function needs in a separate, collectable
+/// `captures` value, and passes it to the function by reference on every call.
+/// Cycles which pass through the captures are then collected like any other.
+///
+/// Any `Gc` captured by the function itself is still hidden, and is treated as a root which keeps
+/// everything reachable from it alive, so the function should capture nothing else.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::{unsync::Gc, Collectable, GcFn};
+/// use std::cell::{Cell, RefCell};
+///
+/// #[derive(Collectable)]
+/// struct Button {
+///     clicks: Cell<usize>,
+///     on_click: RefCell<Option<GcFn<Gc<Button>, fn(&Gc<Button>, usize)>>>,
+/// }
+///
+/// let button = Gc::new(Button {
+///     clicks: Cell::new(0),
+///     on_click: RefCell::new(None),
+/// });
+/// *button.on_click.borrow_mut() = Some(GcFn::new(button.clone(), |button, n| {
+///     button.clicks.set(button.clicks.get() + n);
+/// }));
+///
+/// button.on_click.borrow().as_ref().unwrap().call(2);
+/// assert_eq!(button.clicks.get(), 2);
+/// ```
+pub struct GcFn<C, F> {
+    /// The values which the function needs, passed to it on every call.
+    captures: C,
+    /// The function.
+    f: F,
+}
+
+impl<C: Collectable, F> GcFn<C, F> {
+    /// Construct a new `GcFn` which calls `f` with `captures`.
+    pub fn new(captures: C, f: F) -> GcFn<C, F> {
+        GcFn { captures, f }
+    }
+
+    /// Get a reference to the values which are passed to the function.
+    pub fn captures(&self) -> &C {
+        &self.captures
+    }
+
+    /// Call the function with the captured values and `args`.
+    ///
+    /// To pass several arguments, bundle them into a tuple.
+    pub fn call<A, R>(&self, args: A) -> R
+    where
+        F: Fn(&C, A) -> R,
+    {
+        (self.f)(&self.captures, args)
+    }
+}
+
+unsafe impl<C: Collectable, F> Collectable for GcFn<C, F> {
+    fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+        self.captures.accept(visitor)
+    }
+}
+
 /// A visitor for a garbage collected value.
 ///
 /// This visitor allows us to hide details of the implementation of the garbage-collection procedure
@@ -295,9 +608,17 @@ pub trait Visitor {
     ///
     /// This function is called for every [`sync::Gc`] owned by the value that accepted this
     /// visitor.
+    ///
+    /// This function is only part of the trait with the `std` feature enabled.
+    /// By default, it does nothing, so that a visitor written without `std` still compiles when
+    /// another crate enables the feature.
+    #[cfg(feature = "std")]
     fn visit_sync<T>(&mut self, gc: &sync::Gc<T>)
     where
-        T: Collectable + Send + Sync + ?Sized;
+        T: Collectable + Send + Sync + ?Sized,
+    {
+        let _ = gc;
+    }
 
     /// Visit a thread-local garbage-collected pointer.
     ///
@@ -306,6 +627,14 @@ pub trait Visitor {
     fn visit_unsync<T>(&mut self, gc: &unsync::Gc<T>)
     where
         T: Collectable + ?Sized;
+
+    #[doc(hidden)]
+    /// View this visitor as a `dyn Any` if it is one of the garbage collector's own visitors, so
+    /// that a [`dyn AnyCollectable`](AnyCollectable) can hand it to the concrete type's
+    /// [`Collectable::accept`].
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        None
+    }
 }
 
 // Re-export #[derive(Collectable)].
@@ -332,8 +661,152 @@ extern crate dumpster_derive;
 ///     bar: Option<Box<Foo>>,
 /// }
 /// ```
+///
+/// # Generic types
+///
+/// The derived implementation requires every type parameter which appears in the type of a
+/// field to be `Collectable`.
+/// If that is not the right bound, such as when a type parameter only appears inside a
+/// `PhantomData`, the bounds can be given by hand with `#[collectable(bound = "...")]`, which
+/// replaces the inferred bounds entirely.
+///
+/// ```
+/// use dumpster::{unsync::Gc, Collectable};
+/// use std::marker::PhantomData;
+///
+/// #[derive(Collectable)]
+/// enum Either<L, R> {
+///     Left(L),
+///     Right(R),
+/// }
+///
+/// /// A type which does not implement `Collectable`.
+/// struct Meters;
+///
+/// #[derive(Collectable)]
+/// #[collectable(bound = "")]
+/// struct Quantity<U> {
+///     value: f64,
+///     unit: PhantomData<U>,
+/// }
+///
+/// let gc = Gc::new(Either::<u8, Quantity<Meters>>::Left(3));
+/// ```
+///
+/// # Skipping fields
+///
+/// A field marked with `#[collectable(unsafe_skip)]` is not visited by the derived
+/// implementation, so its type does not need to implement `Collectable`.
+/// This is useful for fields which can never contain a `Gc`, such as handles from a foreign
+/// library.
+///
+/// Skipping a field from which a `Gc` can be reached is not memory-unsafe, but the garbage
+/// collector will be unable to see that `Gc`.
+/// Any cycle passing through a skipped field will never be collected.
+/// Only skip fields which are certain never to hold a `Gc`.
+///
+/// ```
+/// use dumpster::Collectable;
+///
+/// /// A handle from some library which does not know about `dumpster`.
+/// struct Handle(u32);
+///
+/// #[derive(Collectable)]
+/// struct Foo {
+///     #[collectable(unsafe_skip)]
+///     handle: Handle,
+///     bar: Option<Box<Foo>>,
+/// }
+/// ```
+///
+/// # Tracing fields by hand
+///
+/// A field marked with `#[collectable(trace_with = "path")]` is visited by calling the
+/// function at `path` instead of its own `Collectable` implementation, much like `serde`'s
+/// `serialize_with`.
+/// This is useful for containers from other libraries, which cannot implement `Collectable`
+/// themselves.
+/// The function must have the signature `fn<V: Visitor>(&FieldType, &mut V) -> Result<(),
+/// ()>`, and is bound by the same rules as [`Collectable::accept`]: it must visit every `Gc`
+/// owned by the field exactly once, and must never visit a `Gc` which the field does not own.
+/// The type of such a field adds no bounds to the derived implementation, so any bounds its
+/// function needs must be given with `#[collectable(bound = "...")]`.
+///
+/// ```
+/// use dumpster::{unsync::Gc, Collectable, Visitor};
+///
+/// /// A list from some library which does not know about `dumpster`.
+/// struct List<T>(Vec<T>);
+///
+/// fn trace_list<V: Visitor>(list: &List<Gc<Foo>>, visitor: &mut V) -> Result<(), ()> {
+///     list.0.iter().try_for_each(|gc| gc.accept(visitor))
+/// }
+///
+/// #[derive(Collectable)]
+/// struct Foo {
+///     #[collectable(trace_with = "trace_list")]
+///     children: List<Gc<Foo>>,
+/// }
+/// ```
+///
+/// # Unions
+///
+/// The derive macro cannot know which field of a union is active, so a union must say so
+/// itself. Marking a union with `#[collectable(unsafe_union_field = "field")]` makes the
+/// derived implementation visit `field`, which must then be the active field whenever the
+/// union might be visited; visiting an inactive field is undefined behavior.
+/// Alternatively, a union which never contains a `Gc` may be marked with
+/// `#[collectable(unsafe_skip)]`, so that none of its fields are visited.
+///
+/// ```
+/// use dumpster::{unsync::Gc, Collectable};
+/// use std::mem::ManuallyDrop;
+///
+/// #[derive(Collectable)]
+/// #[collectable(unsafe_union_field = "gc")]
+/// union Slot {
+///     gc: ManuallyDrop<Gc<u8>>,
+///     raw: usize,
+/// }
+///
+/// #[derive(Collectable)]
+/// #[collectable(unsafe_skip)]
+/// union Bits {
+///     int: u32,
+///     float: f32,
+/// }
+/// ```
 pub use dumpster_derive::Collectable;
 
+#[cfg(feature = "derive")]
+/// The derive macro for implementing `Acyclic`.
+///
+/// The derived implementation requires the type of every field to be [`Acyclic`], including
+/// fields marked with `#[collectable(unsafe_skip)]`, so a type which could contain a `Gc` is
+/// rejected at compile time.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::{Acyclic, Collectable};
+///
+/// #[derive(Collectable, Acyclic)]
+/// enum Shape<T> {
+///     Circle { radius: T },
+///     Polygon(Vec<(T, T)>),
+/// }
+/// ```
+///
+/// ```compile_fail
+/// use dumpster::{unsync::Gc, Acyclic, Collectable};
+///
+/// #[derive(Collectable, Acyclic)]
+/// struct Node {
+///     next: Option<Gc<Node>>,
+/// }
+/// ```
+pub use dumpster_derive::Acyclic;
+
 #[repr(align(16))]
 #[repr(C)]
 #[derive(Clone, Copy)]
@@ -403,10 +876,11 @@ impl fmt::Debug for ErasedPtr {
 
 #[cfg(test)]
 mod tests {
-    use std::{
+    use alloc::{
         alloc::{dealloc, Layout},
-        mem::align_of,
+        boxed::Box,
     };
+    use core::mem::align_of;
 
     use super::*;
 
@@ -425,4 +899,23 @@ mod tests {
             dealloc(remade_ptr.as_ptr(), Layout::for_value(remade_ptr.as_ref()));
         }
     }
+    #[test]
+    /// Check that a visitor which only visits thread-local pointers compiles with or without
+    /// `std`.
+    fn visitor_without_visit_sync() {
+        struct CountGcs(usize);
+
+        impl Visitor for CountGcs {
+            fn visit_unsync<T>(&mut self, _: &unsync::Gc<T>)
+            where
+                T: Collectable + ?Sized,
+            {
+                self.0 += 1;
+            }
+        }
+
+        let mut counter = CountGcs(0);
+        assert!((5u8, [1u16, 2]).accept(&mut counter).is_ok());
+        assert_eq!(counter.0, 0);
+    }
 }
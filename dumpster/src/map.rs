@@ -0,0 +1,48 @@
+/*
+   dumpster, a cycle-tracking garbage collector for Rust.
+   Copyright (C) 2023 Clayton Ramsey.
+
+   This program is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   This program is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! The maps and sets used internally by the garbage collectors.
+//!
+//! With the `std` feature, these are hash maps.
+//! Without it, `alloc` has no hash maps, so ordered maps are used instead.
+
+#[cfg(feature = "std")]
+pub use std::collections::{hash_map::Entry, HashMap as Map, HashSet as Set};
+
+#[cfg(not(feature = "std"))]
+pub use alloc::collections::{btree_map::Entry, BTreeMap as Map, BTreeSet as Set};
+
+/// Construct an empty map with room for at least `capacity` entries, if the map supports
+/// preallocation.
+#[cfg_attr(not(feature = "std"), allow(unused_variables))]
+pub fn map_with_capacity<K, V>(capacity: usize) -> Map<K, V> {
+    #[cfg(feature = "std")]
+    return Map::with_capacity(capacity);
+    #[cfg(not(feature = "std"))]
+    return Map::new();
+}
+
+/// Construct an empty set with room for at least `capacity` elements, if the set supports
+/// preallocation.
+#[cfg_attr(not(feature = "std"), allow(unused_variables))]
+pub fn set_with_capacity<T>(capacity: usize) -> Set<T> {
+    #[cfg(feature = "std")]
+    return Set::with_capacity(capacity);
+    #[cfg(not(feature = "std"))]
+    return Set::new();
+}
@@ -26,12 +26,10 @@ use std::{
     ptr::{drop_in_place, NonNull},
     sync::{
         atomic::{AtomicPtr, AtomicUsize, Ordering},
-        Mutex, RwLock,
+        LazyLock, Mutex, RwLock,
     },
 };
 
-use once_cell::sync::Lazy;
-
 use crate::{Collectable, ErasedPtr, Visitor};
 
 use super::{default_collect_condition, CollectCondition, CollectInfo, Gc, GcBox, CURRENT_TAG};
@@ -113,7 +111,7 @@ enum Reachability {
 
 /// The global garbage truck.
 /// All [`TrashCans`] should eventually end up in here.
-static GARBAGE_TRUCK: Lazy<GarbageTruck> = Lazy::new(|| GarbageTruck {
+static GARBAGE_TRUCK: LazyLock<GarbageTruck> = LazyLock::new(|| GarbageTruck {
     contents: Mutex::new(HashMap::new()),
     collecting_lock: RwLock::new(()),
     n_gcs_dropped: AtomicUsize::new(0),
@@ -162,7 +160,9 @@ pub fn notify_dropped_gc() {
     });
 
     if (unsafe {
-        transmute::<_, CollectCondition>(GARBAGE_TRUCK.collect_condition.load(Ordering::Relaxed))
+        transmute::<*mut (), CollectCondition>(
+            GARBAGE_TRUCK.collect_condition.load(Ordering::Relaxed),
+        )
     })(&CollectInfo { _private: () })
     {
         GARBAGE_TRUCK.collect_all();
@@ -329,7 +329,7 @@ impl GarbageTruck {
                         weak_destroys.push((node.weak_drop_fn, node.ptr));
                     }
                 }
-            };
+            }
         }
         CLEANING.with(|c| c.set(false));
         for (drop_fn, ptr) in weak_destroys {
@@ -405,7 +405,7 @@ struct Dfs<'a> {
     current_id: AllocationId,
 }
 
-impl<'a> Visitor for Dfs<'a> {
+impl Visitor for Dfs<'_> {
     fn visit_sync<T>(&mut self, gc: &Gc<T>)
     where
         T: Collectable + Send + Sync + ?Sized,
@@ -474,7 +474,7 @@ impl<'a> Visitor for Dfs<'a> {
                 // Restore current_id and carry on
                 swap(&mut new_id, &mut self.current_id);
             }
-        };
+        }
     }
 
     fn visit_unsync<T>(&mut self, _: &crate::unsync::Gc<T>)
@@ -542,7 +542,7 @@ unsafe fn destroy_erased<T: Collectable + Send + Sync + ?Sized>(
         .expect("allocation assumed to be unreachable but somehow was accessed");
     let layout = Layout::for_value(specified);
     drop_in_place(specified);
-    dealloc((specified as *mut GcBox<T>).cast(), layout);
+    dealloc(std::ptr::from_mut(specified).cast(), layout);
 }
 
 /// Function for handling dropping an allocation when its weak and strong reference count reach
@@ -23,6 +23,27 @@
 //! Those with more particular needs (such as benchmarking) should turn toward
 //! [`set_collect_condition`] in order to tune exactly when the garbage collector does cleanups.
 //!
+//! # Synchronization
+//!
+//! Collection does not stop the world: other threads keep running (and mutating their `Gc`s) while
+//! a collection is underway.
+//! Instead, the collector is kept correct in the following ways.
+//!
+//! - When a `Gc` is dropped, its allocation is marked as possibly unreachable in a thread-local
+//!   dumpster. Each thread's dumpster is delivered to a single global, mutex-protected garbage
+//!   truck when it fills up, when that thread calls [`collect`], or when that thread exits.
+//! - Only one collection runs at a time. The collecting thread holds a global lock for the whole
+//!   collection, and [`collect`] waits on that lock so that it only returns once any collection
+//!   already in progress has finished.
+//! - Each collection starts a new sweep by incrementing a global tag. Creating, cloning, or
+//!   dereferencing a `Gc` stamps the `Gc` and its allocation with the current tag, so if the
+//!   collector finds an allocation which was touched during the sweep, it conservatively treats
+//!   that allocation as reachable instead of reasoning about a graph which may have changed under
+//!   it.
+//! - Values are inspected through their [`Collectable`] implementations, which fail when they would
+//!   block (such as on a locked [`Mutex`](std::sync::Mutex)). An allocation which cannot be
+//!   inspected is also treated as reachable.
+//!
 //! # Examples
 //!
 //! ```
@@ -378,8 +399,8 @@ impl<T: Collectable + Send + Sync + ?Sized> Deref for Gc<T> {
     ///
     /// ```should_panic
     /// // This is wrong!
-    /// use std::sync::Mutex;
     /// use dumpster::{sync::Gc, Collectable};
+    /// use std::sync::Mutex;
     ///
     /// #[derive(Collectable)]
     /// struct Bad {
@@ -449,3 +470,18 @@ impl<T: Collectable + Send + Sync + ?Sized> Debug for Gc<T> {
         )
     }
 }
+
+/// Implement formatting traits for `Gc` by forwarding them to the value it points to.
+macro_rules! fmt_forward_impl {
+    ($($tr: ident),*) => {
+        $(
+            impl<T: Collectable + Send + Sync + std::fmt::$tr + ?Sized> std::fmt::$tr for Gc<T> {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    std::fmt::$tr::fmt(&**self, f)
+                }
+            }
+        )*
+    };
+}
+
+fmt_forward_impl!(Display, LowerHex, UpperHex, Binary, Octal, LowerExp, UpperExp);
@@ -32,7 +32,7 @@ use super::*;
 
 struct DropCount<'a>(&'a AtomicUsize);
 
-impl<'a> Drop for DropCount<'a> {
+impl Drop for DropCount<'_> {
     fn drop(&mut self) {
         self.0.fetch_add(1, Ordering::Release);
     }
@@ -221,6 +221,46 @@ fn parallel_loop() {
     assert_eq!(COUNT_4.load(Ordering::Acquire), 1);
 }
 
+#[test]
+fn multithreaded_cycle() {
+    const N_THREADS: usize = 8;
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    // each thread creates one node of a ring
+    let nodes: Vec<Gc<MultiRef>> = std::thread::scope(|s| {
+        let handles: Vec<_> = (0..N_THREADS)
+            .map(|_| {
+                s.spawn(|| {
+                    Gc::new(MultiRef {
+                        refs: Mutex::new(Vec::new()),
+                        count: DropCount(&COUNT),
+                    })
+                })
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+
+    // then each thread links its node to the next one, drops its handle, and collects while the
+    // others are still working on the ring
+    std::thread::scope(|s| {
+        for (i, node) in nodes.iter().enumerate() {
+            let node = node.clone();
+            let next = nodes[(i + 1) % N_THREADS].clone();
+            s.spawn(move || {
+                node.refs.lock().unwrap().push(next);
+                drop(node);
+                collect();
+            });
+        }
+    });
+    assert_eq!(COUNT.load(Ordering::Acquire), 0);
+
+    drop(nodes);
+    collect();
+    assert_eq!(COUNT.load(Ordering::Acquire), N_THREADS);
+}
+
 #[test]
 /// Test that we can drop a Gc which points to some allocation with a locked Mutex inside it
 // note: I tried using `ntest::timeout` but for some reason that caused this test to trivially pass.
@@ -613,3 +653,63 @@ fn root_canal() {
 
     assert_eq!(B_DROP_DETECT.load(Ordering::Relaxed), 1);
 }
+
+#[test]
+fn arc_mutex() {
+    struct Node {
+        next: std::sync::Arc<Mutex<Option<Gc<Node>>>>,
+        #[allow(unused)]
+        count: DropCount<'static>,
+    }
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.next.accept(visitor)
+        }
+    }
+
+    static DROP_COUNT: AtomicUsize = AtomicUsize::new(0);
+    static SHARED_DROP_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    // a cycle through a uniquely-owned `Arc<Mutex<_>>`, built on another thread
+    let gc = std::thread::spawn(|| {
+        let gc = Gc::new(Node {
+            next: std::sync::Arc::new(Mutex::new(None)),
+            count: DropCount(&DROP_COUNT),
+        });
+        *gc.next.lock().unwrap() = Some(gc.clone());
+        gc
+    })
+    .join()
+    .unwrap();
+
+    collect();
+    assert_eq!(DROP_COUNT.load(Ordering::Acquire), 0);
+    drop(gc);
+    collect();
+    assert_eq!(DROP_COUNT.load(Ordering::Acquire), 1);
+
+    // if the `Arc` is shared outside the heap, the cycle must be kept alive
+    let shared = Gc::new(Node {
+        next: std::sync::Arc::new(Mutex::new(None)),
+        count: DropCount(&SHARED_DROP_COUNT),
+    });
+    *shared.next.lock().unwrap() = Some(shared.clone());
+    let arc = shared.next.clone();
+    drop(shared);
+    collect();
+    assert_eq!(SHARED_DROP_COUNT.load(Ordering::Acquire), 0);
+    assert!(arc.lock().unwrap().is_some());
+
+    // breaking the cycle by hand frees it
+    drop(arc.lock().unwrap().take());
+    drop(arc);
+    collect();
+    assert_eq!(SHARED_DROP_COUNT.load(Ordering::Acquire), 1);
+}
+
+#[test]
+fn fmt_forwarding() {
+    assert_eq!(format!("{}", Gc::new(42)), "42");
+    assert_eq!(format!("{:x}", Gc::new(255u8)), "ff");
+}
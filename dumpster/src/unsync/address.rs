@@ -0,0 +1,113 @@
+/*
+   dumpster, a cycle-tracking garbage collector for Rust.
+   Copyright (C) 2023 Clayton Ramsey.
+
+   This program is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   This program is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Comparing garbage-collected pointers by the identity of their allocations.
+
+use core::{
+    fmt::{self, Debug},
+    hash::{Hash, Hasher},
+    ops::Deref,
+};
+
+use crate::{Collectable, Visitor};
+
+use super::Gc;
+
+/// A wrapper around a [`Gc`] which is hashed and compared by the allocation it points to, rather
+/// than by its value.
+///
+/// [`Gc`]'s own implementations of [`Hash`] and [`PartialEq`] both look at the value it points to.
+/// `ByAddress`'s implementations both look only at the address of the allocation, so two
+/// `ByAddress`es are equal exactly when they point to the same allocation, and equal `ByAddress`es
+/// always have equal hashes.
+/// Since `ByAddress<T>` and `Gc<T>` are different types, a map is keyed either by value or by
+/// identity, and the two behaviors can never be mixed within one map.
+///
+/// Unlike a `Gc`, a `ByAddress` can be hashed and compared even if its value does not implement
+/// `Hash` or `Eq`, and even while the garbage collector is running.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::unsync::{ByAddress, Gc};
+/// use std::collections::HashSet;
+///
+/// let gc1 = Gc::new(1);
+/// let gc2 = Gc::new(1);
+/// assert!(gc1 == gc2);
+///
+/// let mut set = HashSet::new();
+/// set.insert(ByAddress(gc1.clone()));
+/// assert!(set.contains(&ByAddress(gc1)));
+/// assert!(!set.contains(&ByAddress(gc2)));
+/// ```
+pub struct ByAddress<T: Collectable + ?Sized + 'static>(pub Gc<T>);
+
+impl<T: Collectable + ?Sized> ByAddress<T> {
+    /// Get the address of the allocation that this `ByAddress` points to, ignoring any metadata.
+    fn addr(&self) -> *const () {
+        self.0.ptr.as_ptr().cast::<()>()
+    }
+}
+
+impl<T: Collectable + ?Sized> PartialEq for ByAddress<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.addr() == other.addr()
+    }
+}
+
+impl<T: Collectable + ?Sized> Eq for ByAddress<T> {}
+
+impl<T: Collectable + ?Sized> Hash for ByAddress<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.addr().hash(state);
+    }
+}
+
+impl<T: Collectable + ?Sized> Clone for ByAddress<T> {
+    fn clone(&self) -> Self {
+        ByAddress(self.0.clone())
+    }
+}
+
+impl<T: Collectable + ?Sized> Deref for ByAddress<T> {
+    type Target = Gc<T>;
+
+    fn deref(&self) -> &Gc<T> {
+        &self.0
+    }
+}
+
+impl<T: Collectable + ?Sized> From<Gc<T>> for ByAddress<T> {
+    fn from(gc: Gc<T>) -> Self {
+        ByAddress(gc)
+    }
+}
+
+impl<T: Collectable + ?Sized> Debug for ByAddress<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ByAddress({:?})", self.addr())
+    }
+}
+
+unsafe impl<T: Collectable + ?Sized> Collectable for ByAddress<T> {
+    #[inline]
+    fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+        self.0.accept(visitor)
+    }
+}
@@ -0,0 +1,129 @@
+/*
+   dumpster, a cycle-tracking garbage collector for Rust.
+   Copyright (C) 2023 Clayton Ramsey.
+
+   This program is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   This program is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Garbage-collected allocations in user-supplied allocators.
+//!
+//! An allocation made by [`Gc::new_in`] stores its allocator in a header placed just before its
+//! [`GcBox`], followed immediately by a function which knows the type of the allocator and can
+//! free the whole allocation.
+//! This keeps `Gc` itself free of an allocator type parameter: only the code which finally frees
+//! the allocation needs to know where it came from, and it finds out through the header.
+
+use alloc::alloc::{handle_alloc_error, Allocator, Layout};
+use core::{cell::Cell, ptr::NonNull};
+
+use crate::Collectable;
+
+use super::{collect::DUMPSTER, Gc, GcBox, IN_ALLOCATOR_FLAG};
+
+/// A function which frees an allocation made by [`Gc::new_in`], given a pointer to its `GcBox`
+/// and the layout of that `GcBox`.
+type ReleaseFn = unsafe fn(NonNull<u8>, Layout);
+
+impl<T: Collectable + ?Sized> Gc<T> {
+    /// Construct a new garbage-collected allocation in the allocator `alloc`, with `value` as its
+    /// value.
+    ///
+    /// The allocator is kept alongside the allocation, and is used to free it once the last `Gc`
+    /// and [`Weak`](super::Weak) to it are gone, whether that happens because they were dropped or
+    /// because the allocation was collected as part of an unreachable cycle.
+    /// Otherwise, the returned `Gc` behaves exactly like one made by [`Gc::new`].
+    ///
+    /// This function is only available with the `allocator_api` feature enabled, which requires
+    /// nightly Rust.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// #![feature(allocator_api)]
+    /// use dumpster::unsync::Gc;
+    /// use std::alloc::System;
+    ///
+    /// let gc = Gc::new_in(5, System);
+    /// assert_eq!(*gc, 5);
+    /// ```
+    pub fn new_in<A: Allocator + 'static>(value: T, alloc: A) -> Gc<T>
+    where
+        T: Sized,
+    {
+        let (layout, offset) = layout_in::<A>(Layout::new::<GcBox<T>>());
+        let base = alloc
+            .allocate(layout)
+            .unwrap_or_else(|_| handle_alloc_error(layout))
+            .cast::<u8>();
+        let ptr = unsafe {
+            base.cast::<A>().as_ptr().write(alloc);
+            let ptr = base.add(offset).cast::<GcBox<T>>();
+            ptr.cast::<ReleaseFn>()
+                .sub(1)
+                .as_ptr()
+                .write(release_in::<A>);
+            ptr.as_ptr().write(GcBox {
+                ref_count: Cell::new(1),
+                weak: Cell::new(1 | IN_ALLOCATOR_FLAG),
+                value,
+            });
+            ptr
+        };
+        DUMPSTER.with(|d| {
+            d.notify_created_gc();
+            #[cfg(any(feature = "debug-allocations", feature = "track-allocations"))]
+            d.notify_allocated(ptr);
+        });
+        Gc { ptr }
+    }
+}
+
+/// Compute the layout of an allocation made by [`Gc::new_in`] with an allocator of type `A`, given
+/// the layout of its `GcBox`.
+///
+/// Returns the layout of the whole allocation and the offset of the `GcBox` within it.
+/// The `GcBox` is always aligned at least as strictly as a [`ReleaseFn`], since it contains a
+/// `usize`, so the `ReleaseFn` can be stored immediately before it.
+fn layout_in<A>(box_layout: Layout) -> (Layout, usize) {
+    let (header, _) = Layout::new::<A>()
+        .extend(Layout::new::<ReleaseFn>())
+        .expect("allocation header too large");
+    let (layout, offset) = header.extend(box_layout).expect("allocation too large");
+    (layout.pad_to_align(), offset)
+}
+
+/// Free an allocation made by [`Gc::new_in`], whose `GcBox` has layout `box_layout`, using the
+/// allocator stored in its header.
+///
+/// # Safety
+///
+/// `ptr` must point to the `GcBox` of an allocation made by [`Gc::new_in`] with an allocator of
+/// type `A`, whose value has already been dropped, and which must not be used again.
+unsafe fn release_in<A: Allocator>(ptr: NonNull<u8>, box_layout: Layout) {
+    let (layout, offset) = layout_in::<A>(box_layout);
+    let base = ptr.sub(offset);
+    let alloc = base.cast::<A>().as_ptr().read();
+    alloc.deallocate(base, layout);
+}
+
+/// Free an allocation made by [`Gc::new_in`], whose `GcBox` has layout `box_layout`.
+///
+/// # Safety
+///
+/// `ptr` must point to the `GcBox` of an allocation made by [`Gc::new_in`], whose value has already
+/// been dropped, and which must not be used again.
+pub(super) unsafe fn deallocate_in(ptr: NonNull<u8>, box_layout: Layout) {
+    let release = ptr.cast::<ReleaseFn>().sub(1).as_ptr().read();
+    release(ptr, box_layout);
+}
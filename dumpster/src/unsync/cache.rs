@@ -0,0 +1,165 @@
+/*
+   dumpster, a cycle-tracking garbage collector for Rust.
+   Copyright (C) 2023 Clayton Ramsey.
+
+   This program is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   This program is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Caches which hold their values weakly.
+
+use std::{collections::HashMap, hash::Hash};
+
+use crate::Collectable;
+
+use super::{Gc, Weak};
+
+/// A map from keys to garbage-collected values, which only holds [`Weak`] references to its values.
+///
+/// A `WeakCache` never keeps a value alive by itself: once every [`Gc`] to a cached value is gone,
+/// the value is freed as usual, and the next lookup of its key builds a new one.
+/// This makes it useful for deduplicating values (such as interned strings) without leaking them.
+///
+/// Entries whose values have been freed are pruned from the map from time to time as new entries
+/// are inserted, so the cache does not grow without bound.
+///
+/// This type is only available with the `std` feature enabled.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::unsync::{Gc, WeakCache};
+///
+/// let mut cache: WeakCache<&str, String> = WeakCache::new();
+///
+/// let hello = cache.get_or_insert_with("hello", || String::from("hello"));
+/// let hello2 = cache.get_or_insert_with("hello", || unreachable!());
+/// assert!(std::ptr::eq(&*hello, &*hello2));
+/// ```
+pub struct WeakCache<K, V: Collectable + 'static> {
+    /// The cached entries, some of which may be dangling.
+    entries: HashMap<K, Weak<V>>,
+    /// The number of entries at which dangling entries will next be pruned.
+    prune_at: usize,
+}
+
+impl<K: Eq + Hash, V: Collectable> WeakCache<K, V> {
+    #[must_use]
+    /// Construct a new, empty `WeakCache`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::WeakCache;
+    ///
+    /// let cache: WeakCache<u32, String> = WeakCache::new();
+    /// assert!(cache.is_empty());
+    /// ```
+    pub fn new() -> WeakCache<K, V> {
+        WeakCache {
+            entries: HashMap::new(),
+            prune_at: 16,
+        }
+    }
+
+    /// Get the value cached for `key`, or insert a new one built by `make` if there is no cached
+    /// value or the cached value has been freed.
+    ///
+    /// The returned `Gc` keeps the value alive, so later calls with an equal key will return the
+    /// same allocation for as long as any `Gc` to it exists.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::WeakCache;
+    ///
+    /// let mut cache = WeakCache::new();
+    ///
+    /// let one = cache.get_or_insert_with(1, || String::from("one"));
+    /// assert_eq!(*one, "one");
+    /// drop(one);
+    ///
+    /// // the old value was freed, so a new one is built
+    /// let one = cache.get_or_insert_with(1, || String::from("uno"));
+    /// assert_eq!(*one, "uno");
+    /// ```
+    pub fn get_or_insert_with(&mut self, key: K, make: impl FnOnce() -> V) -> Gc<V> {
+        if let Some(gc) = self.entries.get(&key).and_then(Weak::upgrade) {
+            return gc;
+        }
+
+        let gc = Gc::new(make());
+        self.entries.insert(key, Gc::downgrade(&gc));
+        if self.entries.len() >= self.prune_at {
+            self.prune();
+            self.prune_at = (self.entries.len() * 2).max(16);
+        }
+        gc
+    }
+
+    #[must_use]
+    /// Get the value cached for `key`, if there is one and it has not been freed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::WeakCache;
+    ///
+    /// let mut cache = WeakCache::new();
+    /// assert!(cache.get(&1).is_none());
+    ///
+    /// let one = cache.get_or_insert_with(1, || 1u8);
+    /// assert_eq!(cache.get(&1).as_deref(), Some(&1));
+    /// ```
+    pub fn get(&self, key: &K) -> Option<Gc<V>> {
+        self.entries.get(key).and_then(Weak::upgrade)
+    }
+
+    /// Remove every entry whose value has been freed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::WeakCache;
+    ///
+    /// let mut cache = WeakCache::new();
+    /// drop(cache.get_or_insert_with(1, || 1u8));
+    /// assert_eq!(cache.len(), 1);
+    ///
+    /// cache.prune();
+    /// assert!(cache.is_empty());
+    /// ```
+    pub fn prune(&mut self) {
+        self.entries.retain(|_, weak| !weak.is_dangling());
+    }
+
+    #[must_use]
+    /// Get the number of entries in this cache, including any whose values have been freed but
+    /// which have not been pruned yet.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[must_use]
+    /// Determine whether this cache has no entries, including any whose values have been freed but
+    /// which have not been pruned yet.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl<K: Eq + Hash, V: Collectable> Default for WeakCache<K, V> {
+    fn default() -> Self {
+        WeakCache::new()
+    }
+}
@@ -18,52 +18,168 @@
 
 //! Implementations of the single-threaded garbage-collection logic.
 
-use std::{
+use alloc::{
     alloc::{dealloc, Layout},
+    boxed::Box,
+    rc::Rc,
+    vec::Vec,
+};
+#[cfg(feature = "debug-allocations")]
+use alloc::{fmt::Write, string::String};
+#[cfg(any(feature = "debug-allocations", feature = "track-allocations"))]
+use core::any::type_name;
+#[cfg(feature = "debug-allocations")]
+use core::any::TypeId;
+#[cfg(not(feature = "std"))]
+use core::convert::Infallible;
+use core::{
+    any::Any,
     cell::{Cell, RefCell},
-    collections::{hash_map::Entry, HashMap, HashSet},
-    num::NonZeroUsize,
-    ptr::{drop_in_place, NonNull},
+    mem::{forget, take},
+    ptr::{addr_of_mut, drop_in_place, NonNull},
 };
+#[cfg(feature = "backtrace")]
+use std::backtrace::Backtrace;
+#[cfg(feature = "std")]
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+#[cfg(feature = "std")]
+use std::time::{Duration, Instant};
 
 use crate::{
+    map::{map_with_capacity, set_with_capacity, Entry, Map, Set},
     unsync::{default_collect_condition, CollectInfo, Gc},
     Collectable, ErasedPtr, Visitor,
 };
 
-use super::{CollectCondition, GcBox};
+#[cfg(not(feature = "std"))]
+use super::global::Global;
+#[cfg(feature = "bench")]
+use super::PhaseTimes;
+use super::{structure::Children, CollectCondition, CollectStats, GcBox};
+#[cfg(feature = "track-allocations")]
+use super::{AllocEvent, AllocEventKind};
 
+#[cfg(feature = "std")]
 thread_local! {
     /// Whether the current thread is running a cleanup process.
     pub(super) static COLLECTING: Cell<bool> = const { Cell::new(false) };
     /// The global collection of allocation information for this thread.
-    pub(super) static DUMPSTER: Dumpster = Dumpster {
-        to_collect: RefCell::new(HashMap::new()),
-        n_ref_drops: Cell::new(0),
-        n_refs_living: Cell::new(0),
-        collect_condition: Cell::new(default_collect_condition),
-    };
+    pub(super) static DUMPSTER: Dumpster = Dumpster::new();
 }
 
+#[cfg(not(feature = "std"))]
+/// Whether the program is running a cleanup process.
+pub(super) static COLLECTING: Global<Cell<bool>> = Global::new(|| Cell::new(false));
+#[cfg(not(feature = "std"))]
+/// The global collection of allocation information for the program.
+pub(super) static DUMPSTER: Global<Dumpster> = Global::new(Dumpster::new);
+
 /// A dumpster is a collection of all the garbage that may or may not need to be cleaned up.
 /// It also contains information relevant to when a cleanup should be triggered.
 pub(super) struct Dumpster {
     /// A map from allocation IDs for allocations which may need to be collected to pointers to
     /// their allocations.
-    to_collect: RefCell<HashMap<AllocationId, Cleanup>>,
+    to_collect: RefCell<Map<AllocationId, Cleanup>>,
     /// The number of times a reference has been dropped since the last collection was triggered.
     pub n_ref_drops: Cell<usize>,
     /// The number of references that currently exist in the entire heap and stack.
     pub n_refs_living: Cell<usize>,
+    /// The number of references which have been created since the last collection was triggered.
+    pub n_refs_created: Cell<usize>,
+    #[cfg(feature = "std")]
+    /// How long the most recent collection took.
+    pub last_collect_duration: Cell<Duration>,
+    /// The number of allocations which have been added to `to_collect`, used to remember the order
+    /// in which they were added.
+    n_dirtied: Cell<usize>,
+    /// The total size, in bytes, of the allocations in `to_collect`.
+    n_bytes_dirty: Cell<usize>,
+    /// Whether collections should visit and drop allocations in a reproducible order.
+    pub deterministic: Cell<bool>,
     /// The function for determining whether a collection should be run.
-    pub collect_condition: Cell<CollectCondition>,
+    pub collect_condition: RefCell<StoredCondition>,
+    /// The maximum number of references which may be living after a user-requested collection.
+    /// If `None`, there is no maximum.
+    pub leak_threshold: Cell<Option<usize>>,
+    /// The function used to report roots which the collector cannot otherwise see, if any.
+    pub external_roots: Cell<Option<fn(&mut ExternalRoots<'_>)>>,
+    /// The function which is called right before each allocation is deallocated, if any.
+    pub on_free: Cell<Option<fn(AllocationId)>>,
+    #[cfg(feature = "track-allocations")]
+    /// The function which is told about every allocation which is created or freed, if any.
+    pub alloc_event_sink: Cell<Option<fn(AllocEvent)>>,
+    /// The finalizers registered for each allocation, in the order they were registered.
+    finalizers: RefCell<Map<AllocationId, Vec<Finalizer>>>,
+    /// Finalizers whose allocations were dropped during a collection, which will be run once the
+    /// collection is over.
+    pending_finalizers: RefCell<Vec<Finalizer>>,
+    /// Freed allocations which are kept around to be reused by new allocations of the same layout,
+    /// grouped by layout.
+    /// Programs usually only churn through a handful of layouts, so this is searched linearly.
+    free_list: RefCell<Vec<(Layout, Vec<NonNull<u8>>)>>,
+    /// The maximum number of freed allocations of each layout which may be kept in `free_list`.
+    pub free_list_capacity: Cell<usize>,
+    /// The number of allocations which have been created by reusing an allocation from
+    /// `free_list`.
+    pub n_free_list_hits: Cell<usize>,
+    #[cfg(feature = "debug-allocations")]
+    /// A registry of every living allocation managed by this dumpster.
+    allocations: RefCell<Map<AllocationId, AllocationRecord>>,
+    #[cfg(feature = "debug-allocations")]
+    /// The number of allocations which have ever been added to `allocations`.
+    n_allocations_recorded: Cell<usize>,
+    #[cfg(feature = "backtrace")]
+    /// Whether a backtrace should be captured every time an allocation is created.
+    pub capture_backtraces: Cell<bool>,
+    #[cfg(feature = "bench")]
+    /// The time spent in each phase of the most recent collection.
+    pub phase_times: Cell<PhaseTimes>,
+    #[cfg(feature = "metrics")]
+    /// The function which is called with the duration of every collection, if any.
+    pub latency_hook: Cell<Option<fn(Duration)>>,
+    #[cfg(all(feature = "metrics", debug_assertions))]
+    /// The duration of every collection which has run on this thread.
+    pub latencies: RefCell<Vec<Duration>>,
+}
+
+#[cfg(feature = "debug-allocations")]
+#[derive(Debug)]
+/// Debugging information about a single living allocation.
+struct AllocationRecord {
+    /// The type of the value that the allocation was created with.
+    type_id: TypeId,
+    /// The name of the type of the value that the allocation was created with.
+    type_name: &'static str,
+    /// The number of allocations which were recorded before this one.
+    serial: usize,
+    /// An erased pointer to the allocation.
+    ptr: ErasedPtr,
+    #[cfg(feature = "backtrace")]
+    /// The backtrace of the creation of the allocation, if backtraces were being captured at the
+    /// time.
+    backtrace: Option<Rc<Backtrace>>,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone)]
+/// A collection condition, as set by either `set_collect_condition` or
+/// `set_collect_condition_boxed`.
+pub(super) enum StoredCondition {
+    /// A condition given as a function pointer.
+    Fn(CollectCondition),
+    /// A condition given as a closure, which may have its own state.
+    Closure(Rc<dyn Fn(&CollectInfo) -> bool>),
+}
+
+/// A one-shot function which is run after the value in an allocation has been dropped.
+type Finalizer = Box<dyn FnOnce()>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 /// A unique identifier for an allocated garbage-collected block.
 ///
 /// It contains a pointer to the reference count of the allocation.
-struct AllocationId(pub NonNull<Cell<NonZeroUsize>>);
+/// An ID is only unique for as long as its allocation is alive: once an allocation is freed, a
+/// later allocation may reuse its ID.
+pub struct AllocationId(NonNull<Cell<usize>>);
 
 impl<T> From<NonNull<GcBox<T>>> for AllocationId
 where
@@ -75,7 +191,7 @@ where
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 /// The necessary information required to collect some garbage-collected data.
 /// This data is stored in a map from allocation IDs to the necessary cleanup operation.
 struct Cleanup {
@@ -84,20 +200,23 @@ struct Cleanup {
     dfs_fn: unsafe fn(ErasedPtr, &mut Dfs),
     /// The function which is called to mark descendants of this allocation as reachable.
     mark_fn: unsafe fn(ErasedPtr, &mut Mark),
-    /// A function used for dropping the allocation.
-    drop_fn: unsafe fn(ErasedPtr, &mut DropAlloc<'_>),
     /// An erased pointer to the allocation.
     ptr: ErasedPtr,
+    /// The position of this allocation in the order in which allocations were marked as dirty.
+    order: usize,
+    /// The size of the allocation, in bytes.
+    size: usize,
 }
 
 impl Cleanup {
-    /// Construct a new cleanup for an allocation.
-    fn new<T: Collectable + ?Sized>(box_ptr: NonNull<GcBox<T>>) -> Cleanup {
+    /// Construct a new cleanup for an allocation which was the `order`-th to be marked dirty.
+    fn new<T: Collectable + ?Sized>(box_ptr: NonNull<GcBox<T>>, order: usize) -> Cleanup {
         Cleanup {
-            dfs_fn: apply_visitor::<T, Dfs>,
+            dfs_fn: trace_erased::<T>,
             mark_fn: apply_visitor::<T, Mark>,
-            drop_fn: drop_assist::<T>,
             ptr: ErasedPtr::new(box_ptr),
+            order,
+            size: Layout::for_value(unsafe { box_ptr.as_ref() }).size(),
         }
     }
 }
@@ -112,25 +231,180 @@ unsafe fn apply_visitor<T: Collectable + ?Sized, V: Visitor>(ptr: ErasedPtr, vis
     let _ = specified.as_ref().value.accept(visitor);
 }
 
+/// Pass `visitor`, which should be one of the garbage collector's own visitors viewed as a
+/// `dyn Any`, on to `value`.
+///
+/// This is how a [`dyn AnyCollectable`](crate::AnyCollectable) is traced: the garbage collector
+/// only ever uses a handful of visitors, so the erased value can find out which one it was given
+/// and call its own [`Collectable::accept`] with it.
+///
+/// # Errors
+///
+/// This function returns an error if `visitor` is not one of the garbage collector's own visitors,
+/// or if `value` returns an error from `accept`.
+pub(crate) fn accept_any<T: Collectable + ?Sized>(
+    value: &T,
+    visitor: &mut dyn Any,
+) -> Result<(), ()> {
+    if let Some(dfs) = visitor.downcast_mut::<Dfs>() {
+        value.accept(dfs)
+    } else if let Some(mark) = visitor.downcast_mut::<Mark>() {
+        value.accept(mark)
+    } else if let Some(counter) = visitor.downcast_mut::<CycleCounter>() {
+        value.accept(counter)
+    } else if let Some(barrier) = visitor.downcast_mut::<WriteBarrier>() {
+        value.accept(barrier)
+    } else if let Some(children) = visitor.downcast_mut::<Children>() {
+        value.accept(children)
+    } else {
+        Err(())
+    }
+}
+
+/// Trace the allocation behind some erased pointer while building the reference graph.
+///
+/// # Safety
+///
+/// `T` must be the same type that `ptr` was created with via [`ErasedPtr::new`].
+unsafe fn trace_erased<T: Collectable + ?Sized>(ptr: ErasedPtr, dfs: &mut Dfs) {
+    dfs.trace::<T>(ptr.specify());
+}
+
 impl Dumpster {
+    /// Construct a new dumpster with nothing in it and the default settings.
+    fn new() -> Dumpster {
+        Dumpster {
+            to_collect: RefCell::new(Map::new()),
+            n_ref_drops: Cell::new(0),
+            n_refs_living: Cell::new(0),
+            n_refs_created: Cell::new(0),
+            #[cfg(feature = "std")]
+            last_collect_duration: Cell::new(Duration::ZERO),
+            n_dirtied: Cell::new(0),
+            n_bytes_dirty: Cell::new(0),
+            deterministic: Cell::new(false),
+            collect_condition: RefCell::new(StoredCondition::Fn(default_collect_condition)),
+            leak_threshold: Cell::new(None),
+            external_roots: Cell::new(None),
+            on_free: Cell::new(None),
+            #[cfg(feature = "track-allocations")]
+            alloc_event_sink: Cell::new(None),
+            finalizers: RefCell::new(Map::new()),
+            pending_finalizers: RefCell::new(Vec::new()),
+            free_list: RefCell::new(Vec::new()),
+            free_list_capacity: Cell::new(0),
+            n_free_list_hits: Cell::new(0),
+            #[cfg(feature = "debug-allocations")]
+            allocations: RefCell::new(Map::new()),
+            #[cfg(feature = "debug-allocations")]
+            n_allocations_recorded: Cell::new(0),
+            #[cfg(feature = "backtrace")]
+            capture_backtraces: Cell::new(false),
+            #[cfg(feature = "bench")]
+            phase_times: Cell::new(PhaseTimes::ZERO),
+            #[cfg(feature = "metrics")]
+            latency_hook: Cell::new(None),
+            #[cfg(all(feature = "metrics", debug_assertions))]
+            latencies: RefCell::new(Vec::new()),
+        }
+    }
+
     /// Collect all unreachable allocations that this dumpster is responsible for.
-    pub fn collect_all(&self) {
+    ///
+    /// Collection happens in three phases.
+    /// First, every unreachable allocation is turned into a "zombie" by setting its reference
+    /// count to zero.
+    /// Next, the values inside of every zombie are dropped.
+    /// Any `Gc` to a zombie which is dropped in this phase is ignored, while a `Gc` to a living
+    /// allocation is dropped as normal.
+    /// Finally, every zombie is deallocated.
+    /// Since no memory is freed until every value is dropped, a destructor can never observe a
+    /// freed allocation.
+    /// Likewise, every traversal of the heap happens before the first value is dropped, and no
+    /// collection or write barrier can start while values are being dropped, so a `Collectable`
+    /// implementation is never run on a value (such as a `Vec`) which is partway through being
+    /// dropped.
+    ///
+    /// Counting the cycles in the garbage takes an extra traversal of it, so it is only done if
+    /// `count_cycles` is `true`; otherwise, the returned number of cycles is zero.
+    pub fn collect_all(&self, count_cycles: bool) -> CollectStats {
+        self.collect_bounded(usize::MAX, count_cycles)
+    }
+
+    /// Collect all unreachable allocations like [`Dumpster::collect_all`], then panic if more
+    /// references are living than the leak threshold permits.
+    ///
+    /// If a collection is already running, this does nothing, since the garbage that collection
+    /// found has not all been freed yet.
+    pub fn collect_all_checked(&self, count_cycles: bool) -> CollectStats {
+        if COLLECTING.with(Cell::get) {
+            return CollectStats::default();
+        }
+        let stats = self.collect_all(count_cycles);
+        self.check_leak_threshold();
+        stats
+    }
+
+    #[allow(clippy::too_many_lines)]
+    /// Collect the unreachable allocations which can be found by tracing from at most
+    /// `max_allocations` allocations in the dirty set, leaving the rest of the dirty set for later.
+    ///
+    /// This works exactly like [`Dumpster::collect_all`] otherwise.
+    /// The allocations which were marked dirty earliest are traced first, so repeated calls
+    /// eventually trace every allocation in the dirty set.
+    pub fn collect_bounded(&self, max_allocations: usize, count_cycles: bool) -> CollectStats {
+        if COLLECTING.with(Cell::get) {
+            // a destructor run by an ongoing collection tried to start another one; the ongoing
+            // collection will take care of everything
+            return CollectStats::default();
+        }
         self.n_ref_drops.set(0);
+        self.n_refs_created.set(0);
+        #[cfg(feature = "std")]
+        let started = Instant::now();
 
-        unsafe {
+        // taking the set of dirty allocations lets destructors mark allocations as dirty again
+        // while we are dropping values.
+        // likewise, allocations created by destructors are never part of `garbage`, which is
+        // fixed before any value is dropped, so they are left for a later collection to inspect
+        let to_collect = self.take_dirty(max_allocations);
+        #[cfg(feature = "bench")]
+        let start = Instant::now();
+
+        let (stats, swept) = unsafe {
             let mut dfs = Dfs {
-                visited: HashSet::with_capacity(self.to_collect.borrow().len()),
-                ref_graph: HashMap::with_capacity(self.to_collect.borrow().len()),
+                visited: set_with_capacity(to_collect.len()),
+                ref_graph: map_with_capacity(to_collect.len()),
+                untraced: Vec::new(),
             };
 
-            for (k, v) in &*self.to_collect.borrow() {
+            let mut visit_dirty = |k: &AllocationId, v: &Cleanup| {
                 if dfs.visited.insert(*k) {
                     (v.dfs_fn)(v.ptr, &mut dfs);
                 }
+            };
+            if self.deterministic.get() {
+                let mut dirty: Vec<(&AllocationId, &Cleanup)> = to_collect.iter().collect();
+                dirty.sort_unstable_by_key(|(_, cleanup)| cleanup.order);
+                for (k, v) in dirty {
+                    visit_dirty(k, v);
+                }
+            } else {
+                for (k, v) in &to_collect {
+                    visit_dirty(k, v);
+                }
             }
+            #[cfg(feature = "bench")]
+            let traced = Instant::now();
+            // the garbage must not be left in the dirty set once it is freed, and the living
+            // allocations which were found need not be inspected again either.
+            // this happens before marking, since external roots are marked dirty again then
+            self.forget_dirty(&dfs.visited);
+            // an allocation which could not be traced must be looked at again once it can be
+            self.mark_untraced_dirty(&mut dfs.untraced);
 
             let mut mark = Mark {
-                visited: HashSet::with_capacity(dfs.visited.len()),
+                visited: set_with_capacity(dfs.visited.len()),
             };
             for (id, reachability) in dfs
                 .ref_graph
@@ -141,10 +415,20 @@ impl Dumpster {
                 (reachability.mark_fn)(reachability.ptr, &mut mark);
             }
 
+            if let Some(external_roots) = self.external_roots.get() {
+                external_roots(&mut ExternalRoots { mark: &mut mark });
+            }
+
+            // so must any allocation which could not be traced, since we don't know what it
+            // points to. every allocation it points to is already a root, since the references
+            // from it were never accounted for
+            for (id, cleanup) in &dfs.untraced {
+                mark.visited.insert(*id);
+                (cleanup.mark_fn)(cleanup.ptr, &mut mark);
+            }
+
             // any allocations which we didn't find must also be roots
-            for (id, cleanup) in self
-                .to_collect
-                .borrow()
+            for (id, cleanup) in to_collect
                 .iter()
                 .filter(|(id, _)| !dfs.ref_graph.contains_key(id))
             {
@@ -152,22 +436,186 @@ impl Dumpster {
                 (cleanup.mark_fn)(cleanup.ptr, &mut mark);
             }
 
-            dfs.visited.clear();
-            let mut decrementer = DropAlloc {
-                visited: dfs.visited,
-                reachable: &mark.visited,
+            let n_scanned = dfs.visited.len();
+            let mut garbage: Vec<(AllocationId, Reachability)> = dfs
+                .ref_graph
+                .into_iter()
+                .filter(|(id, _)| !mark.visited.contains(id))
+                .collect();
+            if self.deterministic.get() {
+                garbage.sort_unstable_by_key(|(_, reachability)| reachability.order);
+            }
+            #[cfg(feature = "bench")]
+            let marked = Instant::now();
+            let stats = CollectStats {
+                n_scanned,
+                n_freed: garbage.len(),
+                n_cycles: if count_cycles {
+                    count_components(&garbage)
+                } else {
+                    0
+                },
             };
 
-            COLLECTING.with(|c| c.set(true));
-            for cleanup in self
-                .to_collect
-                .borrow_mut()
-                .drain()
-                .filter_map(|(id, cleanup)| (!mark.visited.contains(&id)).then_some(cleanup))
-            {
-                (cleanup.drop_fn)(cleanup.ptr, &mut decrementer);
+            let swept = self.sweep(&garbage);
+
+            #[cfg(feature = "bench")]
+            self.phase_times.set(PhaseTimes {
+                trace: traced - start,
+                mark: marked - traced,
+                sweep: marked.elapsed(),
+            });
+            (stats, swept)
+        };
+
+        self.run_pending_finalizers();
+        #[cfg(feature = "std")]
+        {
+            let latency = started.elapsed();
+            self.last_collect_duration.set(latency);
+            #[cfg(feature = "metrics")]
+            self.record_latency(latency);
+        }
+        resume_panic(swept);
+        stats
+    }
+
+    /// Drop `root`, freeing it and everything reachable from it at once if `root` is the only
+    /// reference into them from outside.
+    ///
+    /// Returns `true` if they were freed.
+    /// Otherwise, including when the function registered with
+    /// [`set_external_roots`](super::set_external_roots) reports any of them as reachable, `root`
+    /// is dropped as normal, and the allocations are left for a later collection.
+    pub fn drop_subgraph<T: Collectable + ?Sized>(&self, root: Gc<T>) -> bool {
+        if COLLECTING.with(Cell::get) {
+            drop(root);
+            return false;
+        }
+        let mut dfs = Dfs {
+            visited: Set::new(),
+            ref_graph: Map::new(),
+            untraced: Vec::new(),
+        };
+        // this accounts for `root` itself as well as every reference inside the subgraph
+        dfs.visit_unsync(&root);
+        if !dfs.untraced.is_empty()
+            || dfs
+                .ref_graph
+                .values()
+                .any(|reachability| reachability.n_unaccounted != 0)
+        {
+            drop(root);
+            return false;
+        }
+        // the roots reported by the embedder have no reference count, so they must be asked for
+        // just as a collection would
+        if let Some(external_roots) = self.external_roots.get() {
+            let mut mark = Mark {
+                visited: Set::new(),
+            };
+            external_roots(&mut ExternalRoots { mark: &mut mark });
+            if mark.visited.iter().any(|id| dfs.ref_graph.contains_key(id)) {
+                drop(root);
+                return false;
             }
-            COLLECTING.with(|c| c.set(false));
+        }
+
+        // the sweep takes care of `root`'s reference along with all the others
+        forget(root);
+        self.forget_dirty(&dfs.visited);
+        let mut garbage: Vec<(AllocationId, Reachability)> = dfs.ref_graph.into_iter().collect();
+        if self.deterministic.get() {
+            garbage.sort_unstable_by_key(|(_, reachability)| reachability.order);
+        }
+        let swept = unsafe { self.sweep(&garbage) };
+        self.run_pending_finalizers();
+        self.notify_dropped_gc();
+        resume_panic(swept);
+        true
+    }
+
+    /// Free every unreachable allocation left over as the thread which owns this dumpster exits,
+    /// including after a panic, so that their destructors still run.
+    ///
+    /// A panic from one of those destructors cannot unwind out of a thread-local destructor without
+    /// aborting the process, so it is swallowed once every other value has been dropped; the panic
+    /// hook will already have reported it.
+    fn drain_on_thread_exit(&self) {
+        // the thread is exiting, so any external roots are gone (and their storage may already be
+        // destroyed)
+        self.external_roots.set(None);
+        #[cfg(feature = "std")]
+        let _ = catch_unwind(AssertUnwindSafe(|| self.collect_all(false)));
+        #[cfg(not(feature = "std"))]
+        self.collect_all(false);
+        self.set_free_list_capacity(0);
+    }
+
+    /// Free every allocation in `garbage`.
+    ///
+    /// Every allocation is first turned into a zombie, then every value is dropped, and finally
+    /// every allocation is released, so that no destructor can observe a freed allocation.
+    /// The finalizers of the allocations are left in the pending finalizers, to be run once the
+    /// caller is done.
+    ///
+    /// If a destructor panics, the remaining values are still dropped and every allocation is
+    /// still released, and the first panic is returned so that the caller can resume it once it
+    /// has finished cleaning up.
+    /// Without the `std` feature, panics cannot be caught, so the remaining allocations are leaked
+    /// instead.
+    ///
+    /// # Safety
+    ///
+    /// Nothing outside of `garbage` may refer to any allocation in `garbage`, and every allocation
+    /// in `garbage` must still have its value.
+    unsafe fn sweep(&self, garbage: &[(AllocationId, Reachability)]) -> Result<(), PanicPayload> {
+        for (id, _) in garbage {
+            id.0.as_ref().set(0);
+            // the finalizers must only run once every value has been dropped
+            let finalizers = self.take_finalizers(*id);
+            self.pending_finalizers.borrow_mut().extend(finalizers);
+            #[cfg(feature = "debug-allocations")]
+            self.allocations.borrow_mut().remove(id);
+        }
+
+        #[cfg_attr(not(feature = "std"), allow(unused_mut))]
+        let mut result = Ok(());
+        {
+            let _guard = CollectingGuard::new();
+            for (_, reachability) in garbage {
+                #[cfg(feature = "std")]
+                if let Err(payload) = catch_unwind(AssertUnwindSafe(|| {
+                    (reachability.drop_fn)(reachability.ptr);
+                })) {
+                    result = result.and(Err(payload));
+                }
+                #[cfg(not(feature = "std"))]
+                (reachability.drop_fn)(reachability.ptr);
+            }
+        }
+
+        for (_, reachability) in garbage {
+            (reachability.release_fn)(reachability.ptr);
+        }
+        result
+    }
+
+    /// Run every finalizer which was deferred until the end of a collection.
+    fn run_pending_finalizers(&self) {
+        let pending = take(&mut *self.pending_finalizers.borrow_mut());
+        for finalizer in pending {
+            finalizer();
+        }
+    }
+
+    #[cfg(feature = "metrics")]
+    /// Record how long a collection took, passing it on to the latency hook if there is one.
+    fn record_latency(&self, latency: Duration) {
+        #[cfg(debug_assertions)]
+        self.latencies.borrow_mut().push(latency);
+        if let Some(hook) = self.latency_hook.get() {
+            hook(latency);
         }
     }
 
@@ -177,15 +625,104 @@ impl Dumpster {
         self.to_collect
             .borrow_mut()
             .entry(AllocationId::from(box_ptr))
-            .or_insert_with(|| Cleanup::new(box_ptr));
+            .or_insert_with(|| {
+                let order = self.n_dirtied.get();
+                self.n_dirtied.set(order.wrapping_add(1));
+                let cleanup = Cleanup::new(box_ptr, order);
+                self.n_bytes_dirty
+                    .set(self.n_bytes_dirty.get() + cleanup.size);
+                cleanup
+            });
+    }
+
+    #[cfg(all(test, feature = "std"))]
+    /// Determine whether an allocation is currently marked as dirty.
+    pub fn is_dirty<T: Collectable + ?Sized>(&self, box_ptr: NonNull<GcBox<T>>) -> bool {
+        self.to_collect
+            .borrow()
+            .contains_key(&AllocationId::from(box_ptr))
     }
 
     /// Mark an allocation as "cleaned," implying that the allocation is about to be destroyed and
     /// therefore should not be cleaned up later.
     pub fn mark_cleaned<T: Collectable + ?Sized>(&self, box_ptr: NonNull<GcBox<T>>) {
-        self.to_collect
+        if let Some(cleanup) = self
+            .to_collect
             .borrow_mut()
-            .remove(&AllocationId::from(box_ptr));
+            .remove(&AllocationId::from(box_ptr))
+        {
+            self.n_bytes_dirty
+                .set(self.n_bytes_dirty.get() - cleanup.size);
+        }
+    }
+
+    /// Remove up to `limit` allocations from the set of allocations which may need to be
+    /// collected, returning them.
+    ///
+    /// If there are more than `limit` such allocations, the ones which were marked as dirty
+    /// earliest are taken, so that repeated calls eventually take every allocation.
+    fn take_dirty(&self, limit: usize) -> Map<AllocationId, Cleanup> {
+        let mut to_collect = self.to_collect.borrow_mut();
+        if limit >= to_collect.len() {
+            self.n_bytes_dirty.set(0);
+            return take(&mut *to_collect);
+        }
+
+        let mut oldest: Vec<(usize, AllocationId)> = to_collect
+            .iter()
+            .map(|(id, cleanup)| (cleanup.order, *id))
+            .collect();
+        oldest.select_nth_unstable(limit);
+        oldest.truncate(limit);
+        oldest
+            .into_iter()
+            .filter_map(|(_, id)| {
+                let cleanup = to_collect.remove(&id)?;
+                self.n_bytes_dirty
+                    .set(self.n_bytes_dirty.get() - cleanup.size);
+                Some((id, cleanup))
+            })
+            .collect()
+    }
+
+    /// Remove every allocation in `ids` from the set of allocations which may need to be
+    /// collected.
+    fn forget_dirty(&self, ids: &Set<AllocationId>) {
+        let mut to_collect = self.to_collect.borrow_mut();
+        if to_collect.is_empty() {
+            return;
+        }
+        for id in ids {
+            if let Some(cleanup) = to_collect.remove(id) {
+                self.n_bytes_dirty
+                    .set(self.n_bytes_dirty.get() - cleanup.size);
+            }
+        }
+    }
+
+    /// Mark every allocation in `untraced`, which a collection could not trace, as dirty again so
+    /// that a later collection tries to trace it once more.
+    fn mark_untraced_dirty(&self, untraced: &mut [(AllocationId, Cleanup)]) {
+        let mut to_collect = self.to_collect.borrow_mut();
+        for (id, cleanup) in untraced {
+            let order = self.n_dirtied.get();
+            self.n_dirtied.set(order.wrapping_add(1));
+            cleanup.order = order;
+            if to_collect.insert(*id, *cleanup).is_none() {
+                self.n_bytes_dirty
+                    .set(self.n_bytes_dirty.get() + cleanup.size);
+            }
+        }
+    }
+
+    /// Get the number of allocations which may need to be collected.
+    pub fn n_dirty(&self) -> usize {
+        self.to_collect.borrow().len()
+    }
+
+    /// Get the total size, in bytes, of the allocations which may need to be collected.
+    pub fn n_bytes_dirty(&self) -> usize {
+        self.n_bytes_dirty.get()
     }
 
     /// Notify the dumpster that a garbage-collected pointer has been dropped.
@@ -203,30 +740,326 @@ impl Dumpster {
         // check if it's been a long time since the last time we collected all
         // the garbage.
         // if so, go and collect it all again (amortized O(1))
-        if (self.collect_condition.get())(&CollectInfo { _private: () }) {
-            self.collect_all();
+        // the condition is copied out first so that it may replace itself
+        let condition = self.collect_condition.borrow().clone();
+        let info = CollectInfo { _private: () };
+        let should_collect = match condition {
+            StoredCondition::Fn(f) => f(&info),
+            StoredCondition::Closure(f) => f(&info),
+        };
+        if should_collect {
+            self.collect_all(false);
+        }
+    }
+
+    /// Panic if more references are living than the leak threshold permits.
+    pub fn check_leak_threshold(&self) {
+        if let Some(threshold) = self.leak_threshold.get() {
+            let n_living = self.n_refs_living.get();
+            assert!(
+                n_living <= threshold,
+                "leak detected: {n_living} unsync::Gc references are still living after \
+                 collection, but the leak threshold is {threshold}"
+            );
+        }
+    }
+
+    #[cfg(any(feature = "debug-allocations", feature = "track-allocations"))]
+    /// Register a newly-created allocation so that it can be found by debugging queries, and report
+    /// it to the allocation event sink.
+    pub fn notify_allocated<T: Collectable + ?Sized + 'static>(&self, box_ptr: NonNull<GcBox<T>>) {
+        #[cfg(feature = "track-allocations")]
+        self.emit_alloc_event(AllocEventKind::Created, box_ptr);
+        #[cfg(feature = "debug-allocations")]
+        self.record_allocation(box_ptr);
+    }
+
+    #[cfg(feature = "debug-allocations")]
+    /// Register a newly-created allocation so that it can be found by debugging queries.
+    fn record_allocation<T: Collectable + ?Sized + 'static>(&self, box_ptr: NonNull<GcBox<T>>) {
+        let serial = self.n_allocations_recorded.get();
+        self.n_allocations_recorded.set(serial.wrapping_add(1));
+        self.allocations.borrow_mut().insert(
+            AllocationId::from(box_ptr),
+            AllocationRecord {
+                type_id: TypeId::of::<T>(),
+                type_name: type_name::<T>(),
+                serial,
+                ptr: ErasedPtr::new(box_ptr),
+                #[cfg(feature = "backtrace")]
+                backtrace: self
+                    .capture_backtraces
+                    .get()
+                    .then(|| Rc::new(Backtrace::force_capture())),
+            },
+        );
+    }
+
+    #[cfg(feature = "debug-allocations")]
+    /// Remove an allocation whose value is about to be dropped from the debugging registry.
+    pub fn notify_deallocated<T: Collectable + ?Sized>(&self, box_ptr: NonNull<GcBox<T>>) {
+        self.allocations
+            .borrow_mut()
+            .remove(&AllocationId::from(box_ptr));
+    }
+
+    #[cfg(feature = "debug-allocations")]
+    /// Determine whether an allocation is registered as living.
+    pub fn is_allocated<T: Collectable + ?Sized>(&self, box_ptr: NonNull<GcBox<T>>) -> bool {
+        self.allocations
+            .borrow()
+            .contains_key(&AllocationId::from(box_ptr))
+    }
+
+    #[cfg(feature = "debug-allocations")]
+    /// Get pointers to every living allocation which was created with a value of type `T`.
+    pub fn allocations_of_type<T: Collectable + 'static>(&self) -> Vec<NonNull<GcBox<T>>> {
+        self.allocations
+            .borrow()
+            .values()
+            .filter(|record| record.type_id == TypeId::of::<T>())
+            .map(|record| unsafe { record.ptr.specify::<GcBox<T>>() })
+            .collect()
+    }
+
+    #[cfg(feature = "backtrace")]
+    /// Get the backtrace captured when the allocation with ID `id` was created, if any.
+    pub fn allocation_backtrace(&self, id: AllocationId) -> Option<Rc<Backtrace>> {
+        self.allocations.borrow().get(&id)?.backtrace.clone()
+    }
+
+    #[cfg(feature = "debug-allocations")]
+    /// Describe every living allocation, one per line, ordered by type name and then by creation.
+    pub fn debug_live_allocations(&self) -> String {
+        let allocations = self.allocations.borrow();
+        let mut records: Vec<(&AllocationId, &AllocationRecord)> = allocations.iter().collect();
+        records.sort_unstable_by_key(|(_, record)| (record.type_name, record.serial));
+        let mut report = String::new();
+        for (id, record) in records {
+            // writing to a `String` never fails
+            let _ = writeln!(report, "{:?}: {}", id.0, record.type_name);
+            #[cfg(feature = "backtrace")]
+            if let Some(backtrace) = &record.backtrace {
+                let _ = writeln!(report, "{backtrace}");
+            }
+        }
+        report
+    }
+
+    /// Register `finalizer` to be run after the value in the allocation at `box_ptr` is dropped.
+    pub fn register_finalizer<T: Collectable + ?Sized>(
+        &self,
+        box_ptr: NonNull<GcBox<T>>,
+        finalizer: Finalizer,
+    ) {
+        self.finalizers
+            .borrow_mut()
+            .entry(AllocationId::from(box_ptr))
+            .or_default()
+            .push(finalizer);
+    }
+
+    /// Run the finalizers of an allocation whose value has just been dropped.
+    ///
+    /// If a collection is running, the finalizers are instead deferred until it is over.
+    pub fn notify_finalized<T: Collectable + ?Sized>(&self, box_ptr: NonNull<GcBox<T>>) {
+        let finalizers = self.take_finalizers(AllocationId::from(box_ptr));
+        if COLLECTING.with(Cell::get) {
+            self.pending_finalizers.borrow_mut().extend(finalizers);
+        } else {
+            for finalizer in finalizers {
+                finalizer();
+            }
+        }
+    }
+
+    /// Forget the finalizers of an allocation whose value has been moved out instead of dropped.
+    pub fn discard_finalizers<T: Collectable + ?Sized>(&self, box_ptr: NonNull<GcBox<T>>) {
+        drop(self.take_finalizers(AllocationId::from(box_ptr)));
+    }
+
+    /// Remove and return the finalizers registered for the allocation with ID `id`.
+    fn take_finalizers(&self, id: AllocationId) -> Vec<Finalizer> {
+        // most programs never register a finalizer, so avoid hashing in the common case
+        if self.finalizers.borrow().is_empty() {
+            return Vec::new();
+        }
+        self.finalizers.borrow_mut().remove(&id).unwrap_or_default()
+    }
+
+    /// Take a freed allocation with the given layout out of the free list, if there is one.
+    pub fn reuse_allocation(&self, layout: Layout) -> Option<NonNull<u8>> {
+        if self.free_list_capacity.get() == 0 {
+            return None;
+        }
+        let ptr = self
+            .free_list
+            .borrow_mut()
+            .iter_mut()
+            .find(|(l, _)| *l == layout)?
+            .1
+            .pop()?;
+        self.n_free_list_hits.set(self.n_free_list_hits.get() + 1);
+        Some(ptr)
+    }
+
+    /// Offer an allocation which is about to be freed to the free list.
+    ///
+    /// Returns `true` if the allocation was kept for later reuse, and `false` if the caller must
+    /// deallocate it.
+    pub fn recycle_allocation(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        let capacity = self.free_list_capacity.get();
+        if capacity == 0 {
+            return false;
+        }
+        let mut free_list = self.free_list.borrow_mut();
+        let i = free_list
+            .iter()
+            .position(|(l, _)| *l == layout)
+            .unwrap_or_else(|| {
+                free_list.push((layout, Vec::new()));
+                free_list.len() - 1
+            });
+        let freed = &mut free_list[i].1;
+        if freed.len() >= capacity {
+            return false;
+        }
+        freed.push(ptr);
+        true
+    }
+
+    /// Set the maximum number of freed allocations of each layout to keep in the free list,
+    /// deallocating any which no longer fit.
+    pub fn set_free_list_capacity(&self, capacity: usize) {
+        self.free_list_capacity.set(capacity);
+        for (layout, freed) in self.free_list.borrow_mut().iter_mut() {
+            for ptr in freed.drain(capacity.min(freed.len())..) {
+                unsafe { dealloc(ptr.as_ptr(), *layout) };
+            }
         }
     }
 
     /// Notify the dumpster that a new [`Gc`] has been created.
     pub fn notify_created_gc(&self) {
         self.n_refs_living.set(self.n_refs_living.get() + 1);
+        self.n_refs_created.set(self.n_refs_created.get() + 1);
+    }
+
+    /// Notify this dumpster that `n` `Gc`s have been created at once.
+    pub fn notify_created_gcs(&self, n: usize) {
+        self.n_refs_living.set(self.n_refs_living.get() + n);
+        self.n_refs_created.set(self.n_refs_created.get() + n);
+    }
+
+    /// Notify this dumpster that an allocation is about to be deallocated, calling the hook set by
+    /// `set_on_free` and the allocation event sink if there are any.
+    pub fn notify_freed<T: Collectable + ?Sized>(&self, box_ptr: NonNull<GcBox<T>>) {
+        if let Some(on_free) = self.on_free.get() {
+            without_collections(|| on_free(AllocationId::from(box_ptr)));
+        }
+        #[cfg(feature = "track-allocations")]
+        self.emit_alloc_event(AllocEventKind::Freed, box_ptr);
+    }
+
+    #[cfg(feature = "track-allocations")]
+    /// Report an event about the allocation `box_ptr` to the allocation event sink, if there is
+    /// one.
+    fn emit_alloc_event<T: Collectable + ?Sized>(
+        &self,
+        kind: AllocEventKind,
+        box_ptr: NonNull<GcBox<T>>,
+    ) {
+        if let Some(sink) = self.alloc_event_sink.get() {
+            let event = AllocEvent {
+                kind,
+                id: AllocationId::from(box_ptr),
+                size: Layout::for_value(unsafe { box_ptr.as_ref() }).size(),
+                type_name: type_name::<T>(),
+            };
+            without_collections(|| sink(event));
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+/// The payload of a panic caught while dropping garbage.
+type PanicPayload = Box<dyn Any + Send>;
+
+#[cfg(not(feature = "std"))]
+/// The payload of a panic caught while dropping garbage, which cannot happen without `std`.
+type PanicPayload = Infallible;
+
+/// Resume the panic caught while dropping garbage, if there was one.
+fn resume_panic(result: Result<(), PanicPayload>) {
+    #[cfg(feature = "std")]
+    if let Err(payload) = result {
+        resume_unwind(payload);
     }
+    #[cfg(not(feature = "std"))]
+    let Ok(()) = result;
+}
+
+/// Run `f` as though a collection were in progress, so that it cannot start one.
+fn without_collections(f: impl FnOnce()) {
+    let _guard = CollectingGuard::new();
+    f();
 }
 
 impl Drop for Dumpster {
     fn drop(&mut self) {
-        // cleanup any leftover allocations
-        self.collect_all();
+        self.drain_on_thread_exit();
+    }
+}
+
+/// A guard which marks the current thread as collecting for as long as it lives.
+///
+/// The flag is restored to its previous value when the guard is dropped.
+/// If a destructor or hook panics and the panic unwinds, this ensures that the thread is not left
+/// in the collecting state forever.
+struct CollectingGuard {
+    /// Whether the thread was already marked as collecting when the guard was made.
+    was_collecting: bool,
+}
+
+impl CollectingGuard {
+    /// Mark the current thread as collecting until the returned guard is dropped.
+    fn new() -> CollectingGuard {
+        CollectingGuard {
+            was_collecting: COLLECTING.with(|c| c.replace(true)),
+        }
+    }
+}
+
+impl Drop for CollectingGuard {
+    fn drop(&mut self) {
+        COLLECTING.with(|c| c.set(self.was_collecting));
     }
 }
 
 /// The data required to construct the graph of reachable allocations.
 struct Dfs {
     /// The set of allocations which have already been visited.
-    visited: HashSet<AllocationId>,
+    visited: Set<AllocationId>,
     /// A map from allocation identifiers to information about their reachability.
-    ref_graph: HashMap<AllocationId, Reachability>,
+    ref_graph: Map<AllocationId, Reachability>,
+    /// The allocations whose values returned an error from [`Collectable::accept`], and so could
+    /// not be traced.
+    untraced: Vec<(AllocationId, Cleanup)>,
+}
+
+impl Dfs {
+    /// Trace the value inside the allocation at `box_ptr`, remembering the allocation if its
+    /// value could not be traced.
+    ///
+    /// # Safety
+    ///
+    /// `box_ptr` must point to a living allocation.
+    unsafe fn trace<T: Collectable + ?Sized>(&mut self, box_ptr: NonNull<GcBox<T>>) {
+        if box_ptr.as_ref().value.accept(self).is_err() {
+            self.untraced
+                .push((AllocationId::from(box_ptr), Cleanup::new(box_ptr, 0)));
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -239,9 +1072,19 @@ struct Reachability {
     ptr: ErasedPtr,
     /// A function used to mark descendants of this allocation as accessible.
     mark_fn: unsafe fn(ErasedPtr, &mut Mark),
+    /// A function used to find the unreachable allocations which this allocation points to.
+    count_fn: unsafe fn(ErasedPtr, &mut CycleCounter),
+    /// A function used to drop the value inside this allocation.
+    drop_fn: unsafe fn(ErasedPtr),
+    /// A function used to release the collector's hold on this allocation after its value has
+    /// been dropped, deallocating it if no weak references remain.
+    release_fn: unsafe fn(ErasedPtr),
+    /// The position of this allocation in the order in which the reference graph was explored.
+    order: usize,
 }
 
 impl Visitor for Dfs {
+    #[cfg(feature = "std")]
     fn visit_sync<T>(&mut self, _: &crate::sync::Gc<T>)
     where
         T: Collectable + Send + Sync + ?Sized,
@@ -255,31 +1098,87 @@ impl Visitor for Dfs {
         T: Collectable + ?Sized,
     {
         let next_id = AllocationId::from(gc.ptr);
+        let order = self.ref_graph.len();
         match self.ref_graph.entry(next_id) {
             Entry::Occupied(ref mut o) => {
                 o.get_mut().n_unaccounted -= 1;
             }
             Entry::Vacant(v) => {
                 v.insert(Reachability {
-                    n_unaccounted: unsafe { next_id.0.as_ref().get().get() - 1 },
+                    n_unaccounted: unsafe { next_id.0.as_ref().get() - 1 },
                     ptr: ErasedPtr::new(gc.ptr),
                     mark_fn: apply_visitor::<T, Mark>,
+                    count_fn: apply_visitor::<T, CycleCounter>,
+                    drop_fn: drop_value::<T>,
+                    release_fn: release::<T>,
+                    order,
                 });
             }
         }
         if self.visited.insert(next_id) {
-            let _ = unsafe { gc.ptr.as_ref() }.value.accept(self);
+            unsafe { self.trace(gc.ptr) };
+        }
+    }
+
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        Some(self)
+    }
+}
+
+/// A handle through which roots which the garbage collector cannot see are reported to it.
+///
+/// An `ExternalRoots` is passed to the function registered with
+/// [`set_external_roots`](super::set_external_roots) during every collection.
+pub struct ExternalRoots<'a> {
+    /// The mark traversal of the ongoing collection.
+    mark: &'a mut Mark,
+}
+
+impl ExternalRoots<'_> {
+    /// Report `root` as a root, so that every allocation reachable from it survives the ongoing
+    /// collection.
+    pub fn visit<T: Collectable + ?Sized>(&mut self, root: &T) {
+        let _ = root.accept(self.mark);
+        // nothing will tell us when `root` stops being a root, so check its allocations again next
+        // time
+        let _ = root.accept(&mut WriteBarrier);
+    }
+}
+
+/// A visitor which marks every `Gc` it finds as dirty, without descending into their allocations.
+struct WriteBarrier;
+
+impl Visitor for WriteBarrier {
+    #[cfg(feature = "std")]
+    fn visit_sync<T>(&mut self, _: &crate::sync::Gc<T>)
+    where
+        T: Collectable + Send + Sync + ?Sized,
+    {
+        // `sync::Gc`s are managed by a different collector
+    }
+
+    fn visit_unsync<T>(&mut self, gc: &Gc<T>)
+    where
+        T: Collectable + ?Sized,
+    {
+        if unsafe { !gc.ptr.as_ref().is_static() } {
+            DUMPSTER.with(|d| d.mark_dirty(gc.ptr));
         }
     }
+
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        Some(self)
+    }
 }
 
 /// A mark traversal, which marks allocations as reachable.
 struct Mark {
     /// The set of allocations which have been marked as reachable.
-    visited: HashSet<AllocationId>,
+    visited: Set<AllocationId>,
 }
 
 impl Visitor for Mark {
+    #[cfg(feature = "std")]
     fn visit_sync<T>(&mut self, _: &crate::sync::Gc<T>)
     where
         T: Collectable + Send + Sync + ?Sized,
@@ -296,62 +1195,100 @@ impl Visitor for Mark {
             let _ = unsafe { gc.ptr.as_ref().value.accept(self) };
         }
     }
+
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        Some(self)
+    }
 }
 
-/// A visitor for dropping allocations.
-struct DropAlloc<'a> {
-    /// The set of unreachable allocations we've already visited.
-    visited: HashSet<AllocationId>,
-    /// The set of unreachable allocations.
-    reachable: &'a HashSet<AllocationId>,
+/// A traversal which groups unreachable allocations into connected components.
+struct CycleCounter {
+    /// A map from the ID of each unreachable allocation to its index in the garbage.
+    indices: Map<AllocationId, usize>,
+    /// The parent of each unreachable allocation in a union-find forest of the components.
+    parents: Vec<usize>,
+    /// The index of the allocation whose value is being visited.
+    current: usize,
 }
 
-impl Visitor for DropAlloc<'_> {
+impl CycleCounter {
+    /// Find the representative of the component containing the `i`-th allocation.
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.parents[i] != i {
+            self.parents[i] = self.parents[self.parents[i]];
+            i = self.parents[i];
+        }
+        i
+    }
+}
+
+impl Visitor for CycleCounter {
+    #[cfg(feature = "std")]
     fn visit_sync<T>(&mut self, _: &crate::sync::Gc<T>)
     where
         T: Collectable + Send + Sync + ?Sized,
     {
-        // do nothing
+        // `sync::Gc`s are managed by a different collector
     }
 
     fn visit_unsync<T>(&mut self, gc: &Gc<T>)
     where
         T: Collectable + ?Sized,
     {
-        let id = AllocationId::from(gc.ptr);
-        if self.reachable.contains(&id) {
-            unsafe {
-                let cell_ref = id.0.as_ref();
-                cell_ref.set(NonZeroUsize::new(cell_ref.get().get() - 1).unwrap());
-            }
-        } else if self.visited.insert(id) {
-            unsafe {
-                gc.ptr.as_ref().value.accept(self).unwrap();
-                let layout = Layout::for_value(gc.ptr.as_ref());
-                drop_in_place(gc.ptr.as_ptr());
-                dealloc(gc.ptr.as_ptr().cast(), layout);
-            }
+        if let Some(&i) = self.indices.get(&AllocationId::from(gc.ptr)) {
+            let root = self.find(i);
+            let current_root = self.find(self.current);
+            self.parents[root] = current_root;
         }
     }
-}
 
-/// Decrement the outbound reference counts for any reachable allocations which this allocation can
-/// find.
-/// Also, drop the allocation when done.
-unsafe fn drop_assist<T: Collectable + ?Sized>(ptr: ErasedPtr, visitor: &mut DropAlloc<'_>) {
-    if visitor
-        .visited
-        .insert(AllocationId::from(ptr.specify::<GcBox<T>>()))
-    {
-        ptr.specify::<GcBox<T>>()
-            .as_ref()
-            .value
-            .accept(visitor)
-            .unwrap();
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        Some(self)
+    }
+}
 
-        let mut_spec = ptr.specify::<GcBox<T>>().as_mut();
-        let layout = Layout::for_value(mut_spec);
-        drop_in_place(mut_spec);
-        dealloc((mut_spec as *mut GcBox<T>).cast(), layout);
+/// Count the connected components of the graph of unreachable allocations.
+///
+/// Every component contains at least one cycle, since otherwise reference counting alone would
+/// have freed it.
+///
+/// # Safety
+///
+/// Every allocation in `garbage` must still have its value, and must not have been released.
+unsafe fn count_components(garbage: &[(AllocationId, Reachability)]) -> usize {
+    let mut counter = CycleCounter {
+        indices: garbage
+            .iter()
+            .enumerate()
+            .map(|(i, (id, _))| (*id, i))
+            .collect(),
+        parents: (0..garbage.len()).collect(),
+        current: 0,
+    };
+    for (i, (_, reachability)) in garbage.iter().enumerate() {
+        counter.current = i;
+        (reachability.count_fn)(reachability.ptr, &mut counter);
     }
+    (0..garbage.len()).filter(|&i| counter.find(i) == i).count()
+}
+
+/// Drop the value inside an erased allocation without deallocating it.
+///
+/// # Safety
+///
+/// `T` must be the same type that `ptr` was created with via [`ErasedPtr::new`], and the value must
+/// not have already been dropped.
+unsafe fn drop_value<T: Collectable + ?Sized>(ptr: ErasedPtr) {
+    drop_in_place(addr_of_mut!((*ptr.specify::<GcBox<T>>().as_ptr()).value));
+}
+
+/// Release the implicit weak reference held by the strong references to an erased allocation
+/// whose value has already been dropped.
+///
+/// # Safety
+///
+/// `T` must be the same type that `ptr` was created with via [`ErasedPtr::new`], and the value in
+/// the allocation must have already been dropped.
+unsafe fn release<T: Collectable + ?Sized>(ptr: ErasedPtr) {
+    GcBox::release_weak(ptr.specify::<GcBox<T>>());
 }
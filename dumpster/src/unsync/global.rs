@@ -0,0 +1,111 @@
+/*
+   dumpster, a cycle-tracking garbage collector for Rust.
+   Copyright (C) 2023 Clayton Ramsey.
+
+   This program is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   This program is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Storage for the garbage collector's state without `std`.
+//!
+//! With `std`, the state of the collector is thread-local.
+//! Without it, there are no threads to speak of, so the state lives in a static instead, which
+//! is only sound if the program never touches it from more than one thread.
+
+use core::{
+    cell::OnceCell,
+    convert::Infallible,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Whether the program has promised, by calling [`assume_single_threaded`], that it will only
+/// ever use `unsync::Gc` from a single thread.
+static SINGLE_THREADED: AtomicBool = AtomicBool::new(false);
+
+/// Promise that this program only ever uses [`Gc`](super::Gc)s, and the rest of the
+/// [`unsync`](super) module, from a single thread.
+///
+/// Without the `std` feature, there is no thread-local storage, so the state of the garbage
+/// collector is kept in a single static.
+/// This function must be called before the first `Gc` is created; otherwise, the first use of the
+/// garbage collector will panic.
+///
+/// This function is only available with the `std` feature disabled.
+///
+/// # Safety
+///
+/// The program must never use the `unsync` module from more than one thread, including from
+/// interrupt handlers which may preempt code that is using it.
+///
+/// # Examples
+///
+/// ```ignore
+/// use dumpster::unsync::{assume_single_threaded, Gc};
+///
+/// // SAFETY: this program only has one thread and no interrupt handlers
+/// unsafe { assume_single_threaded() };
+/// let gc = Gc::new(5);
+/// ```
+pub unsafe fn assume_single_threaded() {
+    SINGLE_THREADED.store(true, Ordering::Relaxed);
+}
+
+/// A value which is lazily initialized on first use and then shared by the whole program, with
+/// the same interface as the thread-local keys used with `std`.
+pub(super) struct Global<T> {
+    /// The value, once it has been initialized.
+    cell: OnceCell<T>,
+    /// The function which makes the initial value.
+    init: fn() -> T,
+}
+
+// SAFETY: the user has promised with `assume_single_threaded` that the value will only be
+// accessed from a single thread, and `with` refuses to access it otherwise.
+unsafe impl<T> Sync for Global<T> {}
+
+impl<T> Global<T> {
+    /// Construct a new global value which will be initialized by calling `init`.
+    pub const fn new(init: fn() -> T) -> Global<T> {
+        Global {
+            cell: OnceCell::new(),
+            init,
+        }
+    }
+
+    /// Call `f` with a reference to the value, initializing it first if needed.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if [`assume_single_threaded`] has not been called.
+    pub fn with<R>(&'static self, f: impl FnOnce(&T) -> R) -> R {
+        assert!(
+            SINGLE_THREADED.load(Ordering::Relaxed),
+            "`dumpster::unsync::assume_single_threaded` must be called before using `unsync::Gc` \
+             without the `std` feature"
+        );
+        f(self.cell.get_or_init(self.init))
+    }
+
+    #[allow(clippy::unnecessary_wraps)]
+    /// Call `f` with a reference to the value, like [`Global::with`].
+    ///
+    /// This returns a `Result` only to match `std::thread::LocalKey::try_with`; unlike with a
+    /// thread-local value, it can never fail, since the value is never destroyed.
+    ///
+    /// # Errors
+    ///
+    /// This function never returns an error.
+    pub fn try_with<R>(&'static self, f: impl FnOnce(&T) -> R) -> Result<R, Infallible> {
+        Ok(self.with(f))
+    }
+}
@@ -0,0 +1,156 @@
+/*
+   dumpster, a cycle-tracking garbage collector for Rust.
+   Copyright (C) 2023 Clayton Ramsey.
+
+   This program is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   This program is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Guards which trigger garbage collection when they go out of scope.
+
+use core::{cell::Cell, mem::ManuallyDrop, ops::Deref};
+
+use crate::Collectable;
+
+use super::{collect, collect::COLLECTING, Gc};
+
+/// A wrapper around a [`Gc`] which runs a garbage collection as soon as it is dropped.
+///
+/// Dropping a `CollectOnDrop` first drops the `Gc` it wraps, then calls [`collect`].
+/// This makes it possible to reclaim a cyclic structure deterministically at the moment its root
+/// goes out of scope, rather than waiting for the collector to decide to run.
+///
+/// Like [`collect`], this only reclaims allocations which are unreachable once the wrapped `Gc` is
+/// gone: if other `Gc`s to the structure are still alive elsewhere, nothing is freed.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::{
+///     unsync::{CollectOnDrop, Gc},
+///     Collectable,
+/// };
+/// use std::cell::RefCell;
+///
+/// #[derive(Collectable)]
+/// struct Node(RefCell<Option<Gc<Node>>>);
+///
+/// let weak;
+/// {
+///     let root = CollectOnDrop::new(Gc::new(Node(RefCell::new(None))));
+///     *root.0.borrow_mut() = Some(Gc::clone(&root));
+///     weak = Gc::downgrade(&root);
+/// } // `root` is dropped and its cycle is collected here
+///
+/// assert!(weak.is_dangling());
+/// ```
+pub struct CollectOnDrop<T: Collectable + ?Sized + 'static> {
+    /// The wrapped garbage-collected pointer.
+    gc: ManuallyDrop<Gc<T>>,
+}
+
+impl<T: Collectable + ?Sized> CollectOnDrop<T> {
+    #[must_use]
+    /// Wrap `gc` so that a collection is run as soon as it is dropped.
+    pub fn new(gc: Gc<T>) -> CollectOnDrop<T> {
+        CollectOnDrop {
+            gc: ManuallyDrop::new(gc),
+        }
+    }
+
+    #[must_use]
+    /// Unwrap `this` into the `Gc` it contains, without running a collection.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::{CollectOnDrop, Gc};
+    ///
+    /// let guard = CollectOnDrop::new(Gc::new(3));
+    /// let gc = CollectOnDrop::into_inner(guard);
+    /// assert_eq!(*gc, 3);
+    /// ```
+    pub fn into_inner(this: CollectOnDrop<T>) -> Gc<T> {
+        let mut this = ManuallyDrop::new(this);
+        // SAFETY: `this` is never used or dropped again, so the `Gc` is taken exactly once.
+        unsafe { ManuallyDrop::take(&mut this.gc) }
+    }
+}
+
+impl<T: Collectable + ?Sized> Deref for CollectOnDrop<T> {
+    type Target = Gc<T>;
+
+    fn deref(&self) -> &Gc<T> {
+        &self.gc
+    }
+}
+
+impl<T: Collectable + ?Sized> Drop for CollectOnDrop<T> {
+    /// Drop the wrapped `Gc`, then collect any garbage that dropping it created.
+    fn drop(&mut self) {
+        // SAFETY: `self.gc` is never used again.
+        unsafe { ManuallyDrop::drop(&mut self.gc) };
+        collect();
+    }
+}
+
+/// A guard which runs a garbage collection when it goes out of scope.
+///
+/// A `CollectGuard` is created by [`collect_on_drop`].
+/// Unlike [`CollectOnDrop`], it does not own any `Gc`: it is useful for scoping the cleanup of
+/// every temporary cycle created within a block.
+///
+/// If the guard is dropped while a collection is already running on this thread (such as from the
+/// `Drop` implementation of a collected value), it does nothing.
+#[must_use = "a `CollectGuard` collects as soon as it is dropped"]
+pub struct CollectGuard {
+    /// Dummy value so that a guard can only be created with [`collect_on_drop`].
+    _private: (),
+}
+
+/// Create a guard which calls [`collect`] when it is dropped.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::{
+///     unsync::{collect_on_drop, Gc},
+///     Collectable,
+/// };
+/// use std::cell::RefCell;
+///
+/// #[derive(Collectable)]
+/// struct Node(RefCell<Option<Gc<Node>>>);
+///
+/// let weak;
+/// {
+///     let _guard = collect_on_drop();
+///     let node = Gc::new(Node(RefCell::new(None)));
+///     *node.0.borrow_mut() = Some(node.clone());
+///     weak = Gc::downgrade(&node);
+/// } // `node` is dropped, then `_guard` collects its cycle
+///
+/// assert!(weak.is_dangling());
+/// ```
+pub fn collect_on_drop() -> CollectGuard {
+    CollectGuard { _private: () }
+}
+
+impl Drop for CollectGuard {
+    /// Collect all unreachable allocations, unless a collection is already running.
+    fn drop(&mut self) {
+        if !COLLECTING.with(Cell::get) {
+            collect();
+        }
+    }
+}
@@ -0,0 +1,164 @@
+/*
+   dumpster, a cycle-tracking garbage collector for Rust.
+   Copyright (C) 2023 Clayton Ramsey.
+
+   This program is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   This program is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Lazily-loaded garbage-collected values.
+
+use alloc::boxed::Box;
+use core::{
+    cell::{Cell, OnceCell},
+    ops::Deref,
+};
+
+use crate::{Collectable, Visitor};
+
+use super::Gc;
+
+/// A garbage-collected pointer whose contents are loaded on first access.
+///
+/// A `LazyGc` starts out holding a loader closure.
+/// The first time it is dereferenced (or [`LazyGc::hydrate`] is called), the loader is run, its
+/// result is moved into a new [`Gc`], and that `Gc` is cached for every later access.
+///
+/// Before it is loaded, a `LazyGc` holds no garbage-collected references at all, so it contributes
+/// nothing to collection.
+/// Any `Gc`s captured by the loader closure are not visible to the garbage collector; they are
+/// treated as roots until the loader runs, so capturing them will never cause a use-after-free,
+/// but it may delay the collection of cycles that pass through them.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::unsync::LazyGc;
+///
+/// let lazy = LazyGc::new(|| String::from("loaded from the database"));
+/// assert!(!LazyGc::is_loaded(&lazy));
+///
+/// assert_eq!(lazy.len(), 24);
+/// assert!(LazyGc::is_loaded(&lazy));
+/// ```
+pub struct LazyGc<T: Collectable + 'static> {
+    /// The loaded value, if it has been loaded.
+    value: OnceCell<Gc<T>>,
+    /// The function used to load the value.
+    /// This is `None` once loading has begun.
+    loader: Cell<Option<Box<dyn FnOnce() -> T>>>,
+}
+
+impl<T: Collectable + 'static> LazyGc<T> {
+    /// Construct a new `LazyGc` which will call `loader` to construct its value on first access.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::LazyGc;
+    ///
+    /// let lazy = LazyGc::new(|| 42);
+    /// assert_eq!(*lazy, 42);
+    /// ```
+    pub fn new<F: FnOnce() -> T + 'static>(loader: F) -> LazyGc<T> {
+        LazyGc {
+            value: OnceCell::new(),
+            loader: Cell::new(Some(Box::new(loader))),
+        }
+    }
+
+    #[must_use]
+    /// Construct a `LazyGc` which has already been loaded with `gc`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::{Gc, LazyGc};
+    ///
+    /// let lazy = LazyGc::loaded(Gc::new(1));
+    /// assert!(LazyGc::is_loaded(&lazy));
+    /// ```
+    pub fn loaded(gc: Gc<T>) -> LazyGc<T> {
+        LazyGc {
+            value: OnceCell::from(gc),
+            loader: Cell::new(None),
+        }
+    }
+
+    /// Load the value of `this` if it has not been loaded already, and get a reference to the
+    /// `Gc` holding it.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the loader of `this` attempts to access `this` while it is
+    /// being loaded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::{Gc, LazyGc};
+    ///
+    /// let lazy = LazyGc::new(|| 7);
+    /// let gc: Gc<i32> = LazyGc::hydrate(&lazy).clone();
+    /// assert_eq!(*gc, 7);
+    /// ```
+    pub fn hydrate(this: &LazyGc<T>) -> &Gc<T> {
+        this.value.get_or_init(|| {
+            let loader = this
+                .loader
+                .take()
+                .expect("LazyGc may not be accessed while it is being loaded");
+            Gc::new(loader())
+        })
+    }
+
+    #[must_use]
+    /// Determine whether `this` has already been loaded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::LazyGc;
+    ///
+    /// let lazy = LazyGc::new(|| 7);
+    /// assert!(!LazyGc::is_loaded(&lazy));
+    /// let _ = *lazy;
+    /// assert!(LazyGc::is_loaded(&lazy));
+    /// ```
+    pub fn is_loaded(this: &LazyGc<T>) -> bool {
+        this.value.get().is_some()
+    }
+}
+
+impl<T: Collectable + 'static> Deref for LazyGc<T> {
+    type Target = T;
+
+    /// Dereference this pointer, loading its value first if it has not been loaded already.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic under the same conditions as [`LazyGc::hydrate`] and
+    /// [`Gc::deref`].
+    fn deref(&self) -> &Self::Target {
+        LazyGc::hydrate(self)
+    }
+}
+
+unsafe impl<T: Collectable + 'static> Collectable for LazyGc<T> {
+    fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+        match self.value.get() {
+            Some(gc) => gc.accept(visitor),
+            None => Ok(()),
+        }
+    }
+}
@@ -42,24 +42,74 @@
 //! foo.refs.borrow_mut().push(foo.clone());
 //! ```
 
-use std::{
-    alloc::{dealloc, Layout},
+#[cfg(feature = "backtrace")]
+use alloc::rc::Rc;
+use alloc::{
+    alloc::{alloc, dealloc, handle_alloc_error, Layout},
+    boxed::Box,
+    string::String,
+    vec::Vec,
+};
+#[cfg(feature = "std")]
+use core::hash::BuildHasher;
+use core::{
+    any::{type_name, Any},
     borrow::Borrow,
     cell::Cell,
-    num::NonZeroUsize,
+    hash::Hash,
+    mem::ManuallyDrop,
     ops::Deref,
-    ptr::{addr_of, addr_of_mut, drop_in_place, NonNull},
+    ptr::{addr_of, addr_of_mut, drop_in_place, read, NonNull},
+};
+#[cfg(feature = "backtrace")]
+use std::backtrace::Backtrace;
+#[cfg(feature = "std")]
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
 };
 
-use crate::{Collectable, Visitor};
+use crate::{Acyclic, AnyCollectable, Collectable, DynPartialEq, Visitor};
 
-use self::collect::{Dumpster, COLLECTING, DUMPSTER};
+use self::collect::{Dumpster, StoredCondition, DUMPSTER};
 
+mod address;
+#[cfg(feature = "allocator_api")]
+mod allocator;
+#[cfg(feature = "std")]
+mod cache;
 mod collect;
-#[cfg(test)]
+#[cfg(not(feature = "std"))]
+mod global;
+mod guard;
+mod lazy;
+mod projection;
+mod scope;
+mod slice;
+mod structure;
+// without `std`, the whole test harness would share one collector across threads
+#[cfg(all(test, feature = "std"))]
 mod tests;
+mod vec;
+mod weak;
+mod write;
+
+pub use address::ByAddress;
+#[cfg(feature = "std")]
+pub use cache::WeakCache;
+pub(crate) use collect::accept_any;
+pub use collect::{AllocationId, ExternalRoots};
+#[cfg(not(feature = "std"))]
+pub use global::assume_single_threaded;
+pub use guard::{collect_on_drop, CollectGuard, CollectOnDrop};
+pub use lazy::LazyGc;
+pub use projection::GcProjection;
+pub use scope::{with_scope, GcScope};
+pub use structure::{reachable_from, ErasedValue};
+pub use vec::GcVec;
+pub use weak::{Weak, WeakVecExt};
+pub use write::GcStringExt;
 
-#[derive(Debug)]
 /// A garbage-collected pointer.
 ///
 /// This garbage-collected pointer may be used for data which is not safe to share across threads
@@ -89,6 +139,20 @@ pub struct Gc<T: Collectable + ?Sized + 'static> {
 /// collector is not eager under normal conditions.
 /// This only collects the allocations local to the caller's thread.
 ///
+/// A collection does not scan the whole heap.
+/// It only inspects the allocations which may have become unreachable since the last collection
+/// (those which had a `Gc` pointing to them dropped, known as the "dirty set"), along with
+/// everything reachable from them.
+/// Allocations which have not been touched since the last collection are never visited, so a
+/// collection on a large, mostly-stable heap only costs as much as the part of the heap which
+/// changed.
+/// To limit the cost of a single collection even further, use [`collect_bounded`].
+///
+/// The `Drop` implementation of a collected value may create new `Gc`s.
+/// Those allocations are tracked as usual: they are never freed by the collection which is
+/// dropping the value that created them, and if they become unreachable, they will be found by a
+/// later collection.
+///
 /// # Examples
 ///
 /// ```
@@ -109,8 +173,162 @@ pub struct Gc<T: Collectable + ?Sized + 'static> {
 /// # Ok(())
 /// # }
 /// ```
+///
+/// # Panics
+///
+/// This function will panic if a leak threshold has been configured with [`set_leak_threshold`]
+/// and more `Gc`s are still living after the collection than the threshold allows.
+///
+/// If the destructor of a collected value panics, the rest of the garbage is still dropped and
+/// freed, and then the first such panic is resumed.
 pub fn collect() {
-    DUMPSTER.with(Dumpster::collect_all);
+    DUMPSTER.with(|d| {
+        d.collect_all_checked(false);
+    });
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// Statistics about what a single garbage collection did, as returned by [`collect_stats`].
+pub struct CollectStats {
+    /// The number of allocations which were examined to find out whether they were reachable.
+    pub n_scanned: usize,
+    /// The number of unreachable allocations whose values were dropped.
+    pub n_freed: usize,
+    /// The number of separate groups of unreachable allocations which were found.
+    ///
+    /// Each group contains at least one reference cycle, along with anything only reachable from
+    /// it.
+    pub n_cycles: usize,
+}
+
+#[must_use]
+/// Collect all existing unreachable allocations, just like [`collect`], and report what the
+/// collection did.
+///
+/// This is slightly slower than [`collect`], since counting the cycles which were freed requires an
+/// extra traversal of the garbage.
+/// If a collection is already running on this thread (such as when this is called from the `Drop`
+/// implementation of a collected value), nothing happens, and every statistic is zero.
+///
+/// # Panics
+///
+/// This function will panic if a leak threshold has been configured with [`set_leak_threshold`]
+/// and more `Gc`s are still living after the collection than the threshold allows.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::{
+///     unsync::{collect, collect_stats, Gc},
+///     Collectable,
+/// };
+/// use std::cell::RefCell;
+///
+/// #[derive(Collectable)]
+/// struct Node(RefCell<Option<Gc<Node>>>);
+///
+/// collect();
+/// let node = Gc::new(Node(RefCell::new(None)));
+/// *node.0.borrow_mut() = Some(node.clone());
+/// drop(node);
+///
+/// let stats = collect_stats();
+/// assert_eq!(stats.n_freed, 1);
+/// assert_eq!(stats.n_cycles, 1);
+/// ```
+pub fn collect_stats() -> CollectStats {
+    DUMPSTER.with(|d| d.collect_all_checked(true))
+}
+
+#[must_use]
+/// Run a bounded, incremental collection, tracing from at most `max_allocations` of the
+/// allocations which may have become garbage, and report what the collection did.
+///
+/// The collector keeps a set of "dirty" allocations: those which lost a reference since they were
+/// last inspected, and so may now be unreachable.
+/// A full collection (such as [`collect`]) traces from every dirty allocation at once, which can
+/// cause a long pause when the set is large.
+/// This function only takes up to `max_allocations` of them, oldest first, and leaves the rest for
+/// later calls, so that the work can be spread out over time.
+/// Everything reachable from the allocations which are taken is still traced, so garbage is
+/// only freed once a whole unreachable cycle has been traced, and a single call may inspect more
+/// than `max_allocations` allocations.
+/// Calling this function repeatedly eventually collects everything that [`collect`] would.
+///
+/// Unlike [`collect`] and [`collect_stats`], this function never checks the leak threshold set
+/// with [`set_leak_threshold`], since a bounded collection may leave garbage behind.
+/// If a collection is already running on this thread, nothing happens, and every statistic is
+/// zero.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::{
+///     unsync::{collect, collect_bounded, Gc},
+///     Collectable,
+/// };
+/// use std::cell::RefCell;
+///
+/// #[derive(Collectable)]
+/// struct Node(RefCell<Option<Gc<Node>>>);
+///
+/// collect();
+/// for _ in 0..3 {
+///     let node = Gc::new(Node(RefCell::new(None)));
+///     *node.0.borrow_mut() = Some(node.clone());
+/// }
+///
+/// // each call traces from a single dirty allocation, freeing one cycle at a time
+/// assert_eq!(collect_bounded(1).n_freed, 1);
+/// assert_eq!(collect_bounded(1).n_freed, 1);
+/// assert_eq!(collect_bounded(1).n_freed, 1);
+/// assert_eq!(collect_bounded(1).n_freed, 0);
+/// ```
+pub fn collect_bounded(max_allocations: usize) -> CollectStats {
+    DUMPSTER.with(|d| d.collect_bounded(max_allocations, true))
+}
+
+#[must_use = "if the graph was not freed, it may still be living"]
+/// Drop `root`, immediately freeing everything reachable from it if nothing else refers to any of
+/// it.
+///
+/// This is meant for discarding a large graph, such as a whole document or scene, through its last
+/// remaining reference.
+/// The graph reachable from `root` is traced once, and if every reference to an allocation in it
+/// comes either from `root` or from inside the graph, the whole graph is freed at once, without
+/// searching for unreachable cycles in the rest of the heap.
+/// Otherwise, `root` is dropped as usual and the graph is left to the normal collection process.
+///
+/// Returns `true` if the graph was freed, and `false` if something outside of it still refers into
+/// it.
+/// This includes the roots reported by the function registered with [`set_external_roots`].
+/// If a collection is already running on this thread, `root` is dropped as usual and this function
+/// returns `false`.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::{
+///     unsync::{drop_subgraph, Gc},
+///     Collectable,
+/// };
+/// use std::cell::RefCell;
+///
+/// #[derive(Collectable)]
+/// struct Node(RefCell<Vec<Gc<Node>>>);
+///
+/// let root = Gc::new(Node(RefCell::new(Vec::new())));
+/// let child = Gc::new(Node(RefCell::new(vec![root.clone()])));
+/// root.0.borrow_mut().push(child.clone());
+///
+/// // `child` is still held outside of the graph, so nothing is freed
+/// assert!(!drop_subgraph(root.clone()));
+///
+/// drop(child);
+/// assert!(drop_subgraph(root));
+/// ```
+pub fn drop_subgraph<T: Collectable + ?Sized>(root: Gc<T>) -> bool {
+    DUMPSTER.with(|d| d.drop_subgraph(root))
 }
 
 /// Information passed to a [`CollectCondition`] used to determine whether the garbage collector
@@ -159,54 +377,1847 @@ pub fn default_collect_condition(info: &CollectInfo) -> bool {
     info.n_gcs_dropped_since_last_collect() > info.n_gcs_existing()
 }
 
-#[allow(clippy::missing_panics_doc)]
-/// Set the function which determines whether the garbage collector should be run.
-///
-/// `f` will be periodically called by the garbage collector to determine whether it should perform
-/// a full cleanup of the heap.
-/// When `f` returns true, a cleanup will begin.
-///
-/// # Examples
-///
-/// ```
-/// use dumpster::unsync::{set_collect_condition, CollectInfo};
-///
-/// /// This function will make sure a GC cleanup never happens unless directly activated.
-/// fn never_collect(_: &CollectInfo) -> bool {
-///     false
-/// }
+#[allow(clippy::missing_panics_doc)]
+/// Set the function which determines whether the garbage collector should be run.
+///
+/// `f` will be periodically called by the garbage collector to determine whether it should perform
+/// a full cleanup of the heap.
+/// When `f` returns true, a cleanup will begin.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::unsync::{set_collect_condition, CollectInfo};
+///
+/// /// This function will make sure a GC cleanup never happens unless directly activated.
+/// fn never_collect(_: &CollectInfo) -> bool {
+///     false
+/// }
+///
+/// set_collect_condition(never_collect);
+/// ```
+pub fn set_collect_condition(f: CollectCondition) {
+    DUMPSTER.with(|d| d.collect_condition.replace(StoredCondition::Fn(f)));
+}
+
+/// A closure which determines whether the garbage collector should start collecting.
+///
+/// Unlike a [`CollectCondition`], a closure can carry its own state, such as the time of the last
+/// collection.
+/// This type primarily exists so that it can be used with [`set_collect_condition_boxed`].
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::unsync::{set_collect_condition_boxed, BoxedCollectCondition};
+///
+/// let limit = 100;
+/// let condition: BoxedCollectCondition =
+///     Box::new(move |info| info.n_gcs_dropped_since_last_collect() > limit);
+/// set_collect_condition_boxed(condition);
+/// ```
+pub type BoxedCollectCondition = Box<dyn Fn(&CollectInfo) -> bool>;
+
+/// Set a closure which determines whether the garbage collector should be run.
+///
+/// This works like [`set_collect_condition`], except that the condition may capture state.
+/// The closure replaces any condition set before, including one set with
+/// [`set_collect_condition`].
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::unsync::set_collect_condition_boxed;
+///
+/// let limit = 100;
+/// set_collect_condition_boxed(Box::new(move |info| {
+///     info.n_gcs_dropped_since_last_collect() > limit
+/// }));
+/// ```
+pub fn set_collect_condition_boxed(f: BoxedCollectCondition) {
+    DUMPSTER.with(|d| {
+        d.collect_condition
+            .replace(StoredCondition::Closure(f.into()))
+    });
+}
+
+#[cfg(feature = "std")]
+#[must_use]
+/// Make a collection condition which starts a collection at most once every `interval`,
+/// regardless of how many `Gc`s are created or dropped in between.
+///
+/// The condition is only checked when a `Gc` is dropped, so a collection starts on the first drop
+/// after `interval` has passed since the condition was made or since its last collection.
+/// Explicit calls to [`collect`] are not affected by, and do not reset, the interval.
+///
+/// This function is only available with the `std` feature enabled.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::unsync::{collect_at_most_every, set_collect_condition_boxed};
+/// use std::time::Duration;
+///
+/// set_collect_condition_boxed(collect_at_most_every(Duration::from_millis(100)));
+/// ```
+pub fn collect_at_most_every(interval: Duration) -> BoxedCollectCondition {
+    collect_at_most_every_with_clock(interval, Instant::now)
+}
+
+#[cfg(feature = "std")]
+/// Make a collection condition like [`collect_at_most_every`], which reads the current time from
+/// `now`.
+fn collect_at_most_every_with_clock(
+    interval: Duration,
+    now: impl Fn() -> Instant + 'static,
+) -> BoxedCollectCondition {
+    let last_collect = Cell::new(now());
+    Box::new(move |_| {
+        let now = now();
+        let elapsed = now.saturating_duration_since(last_collect.get()) >= interval;
+        if elapsed {
+            last_collect.set(now);
+        }
+        elapsed
+    })
+}
+
+/// Set the function which reports roots that the garbage collector cannot otherwise see.
+///
+/// Every `Gc` owns a reference count, and the collector treats every reference it cannot account
+/// for as a root, so values held in ordinary `Gc`s are always safe from collection.
+/// However, a language runtime may hold pointers to allocations which do not own a reference count
+/// (for instance, a `std::mem::ManuallyDrop<Gc<T>>` made from a bitwise copy of a `Gc`, living on
+/// its own stack).
+/// If `f` is `Some`, it is called during every collection on this thread, and must report every
+/// such pointer to the [`ExternalRoots`] it is given, so that everything reachable from them
+/// survives the collection.
+/// Passing `None` removes the callback.
+///
+/// Since the collector cannot tell when a pointer stops being reported, the allocations directly
+/// reported by `f` are re-examined at every collection, and are freed at the first collection after
+/// they become unreachable.
+///
+/// `f` is called while the collector is partway through its work, so it must not create or drop
+/// any `Gc`.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::unsync::{collect, set_external_roots, ExternalRoots};
+///
+/// fn report_vm_stack(roots: &mut ExternalRoots<'_>) {
+///     // a real runtime would walk its stack here, calling `roots.visit` on each value
+/// }
+///
+/// set_external_roots(Some(report_vm_stack));
+/// collect();
+/// set_external_roots(None);
+/// ```
+pub fn set_external_roots(f: Option<fn(&mut ExternalRoots<'_>)>) {
+    DUMPSTER.with(|d| d.external_roots.set(f));
+}
+
+/// Set the function which is called right before each allocation on this thread is deallocated.
+///
+/// The function is given the [`AllocationId`] of the allocation, whose value has already been
+/// dropped.
+/// It is called for every allocation which is freed, whether because its last `Gc` was dropped or
+/// because it was collected as part of an unreachable cycle.
+/// If a [`Weak`] to the allocation still exists, the allocation is only deallocated, and the
+/// function only called, once the last `Weak` is dropped too.
+/// Passing `None` removes the function.
+///
+/// The function runs while the garbage collector may be partway through its work, so it must not
+/// create or drop any `Gc` or start a collection.
+/// While it runs, the thread is treated as though a collection were in progress, so calls to
+/// [`collect`] from within it do nothing.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::unsync::{set_on_free, AllocationId, Gc};
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// static N_FREED: AtomicUsize = AtomicUsize::new(0);
+///
+/// fn count_free(_: AllocationId) {
+///     N_FREED.fetch_add(1, Ordering::Relaxed);
+/// }
+///
+/// set_on_free(Some(count_free));
+/// drop(Gc::new(1));
+/// assert_eq!(N_FREED.load(Ordering::Relaxed), 1);
+/// set_on_free(None);
+/// ```
+pub fn set_on_free(f: Option<fn(AllocationId)>) {
+    DUMPSTER.with(|d| d.on_free.set(f));
+}
+
+#[cfg(feature = "track-allocations")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// An event in the life of a single allocation, as reported to the function set by
+/// [`set_alloc_event_sink`].
+///
+/// This type is only available with the `track-allocations` feature enabled.
+pub struct AllocEvent {
+    /// What happened to the allocation.
+    pub kind: AllocEventKind,
+    /// The ID of the allocation.
+    pub id: AllocationId,
+    /// The size of the allocation in bytes, including the reference-counting header stored
+    /// alongside the value.
+    pub size: usize,
+    /// The name of the type of the value in the allocation.
+    pub type_name: &'static str,
+}
+
+#[cfg(feature = "track-allocations")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+/// The kind of an [`AllocEvent`].
+///
+/// This type is only available with the `track-allocations` feature enabled.
+pub enum AllocEventKind {
+    /// The allocation was just created.
+    Created,
+    /// The allocation is about to be deallocated.
+    Freed,
+}
+
+#[cfg(feature = "track-allocations")]
+/// Set the function which is told about every allocation on this thread which is created or freed.
+///
+/// This makes it possible to attribute the memory used by `Gc`s in an allocation profiler.
+/// The function receives a [`AllocEventKind::Created`] event as soon as an allocation is made by a
+/// constructor such as [`Gc::new`], and a [`AllocEventKind::Freed`] event right before it is
+/// deallocated, which happens once its value has been dropped and no [`Weak`] to it is left.
+/// Passing `None` removes the function.
+///
+/// Like the function set by [`set_on_free`], the function must not create or drop any `Gc` or
+/// start a collection, and calls to [`collect`] from within it do nothing.
+///
+/// This function is only available with the `track-allocations` feature enabled.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::unsync::{set_alloc_event_sink, AllocEvent, AllocEventKind, Gc};
+/// use std::cell::Cell;
+///
+/// thread_local! {
+///     static LIVE_BYTES: Cell<usize> = const { Cell::new(0) };
+/// }
+///
+/// fn track(event: AllocEvent) {
+///     LIVE_BYTES.with(|live| match event.kind {
+///         AllocEventKind::Created => live.set(live.get() + event.size),
+///         AllocEventKind::Freed => live.set(live.get() - event.size),
+///     });
+/// }
+///
+/// set_alloc_event_sink(Some(track));
+/// let gc = Gc::new([0u8; 64]);
+/// assert!(LIVE_BYTES.with(Cell::get) >= 64);
+/// drop(gc);
+/// assert_eq!(LIVE_BYTES.with(Cell::get), 0);
+/// set_alloc_event_sink(None);
+/// ```
+pub fn set_alloc_event_sink(sink: Option<fn(AllocEvent)>) {
+    DUMPSTER.with(|d| d.alloc_event_sink.set(sink));
+}
+
+/// Set whether collections on this thread should run in a reproducible order.
+///
+/// By default, the order in which the garbage collector explores candidate allocations, and
+/// therefore the order in which the values inside an unreachable cycle are dropped, depends on the
+/// iteration order of internal hash maps, and may differ from run to run.
+/// When `deterministic` is `true`, candidate allocations are explored in the order they became
+/// candidates, and the members of each unreachable cycle are dropped in the order in which they
+/// were discovered.
+/// As long as the program performs the same operations (and the `Collectable` implementations
+/// involved visit their fields in a fixed order), the drop order within collected cycles is then
+/// the same on every run.
+///
+/// Deterministic collection is slower: every collection must sort its candidates and its garbage,
+/// which costs an extra `O(n log n)` time and `O(n)` memory for `n` candidate allocations.
+/// It is intended for tests whose behavior depends on drop order, not for production use.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::unsync::{collect, set_deterministic, Gc};
+///
+/// set_deterministic(true);
+///
+/// let gc = Gc::new(1);
+/// drop(gc);
+/// collect();
+///
+/// set_deterministic(false);
+/// ```
+pub fn set_deterministic(deterministic: bool) {
+    DUMPSTER.with(|d| d.deterministic.set(deterministic));
+}
+
+/// Set the number of freed allocations of each size which this thread keeps for reuse.
+///
+/// When a `Gc`'s allocation is freed, it is normally returned to the global allocator.
+/// With a nonzero capacity, up to `capacity` freed allocations of each memory layout are instead
+/// kept in a free list, and later allocations with the same layout (such as [`Gc::new`] with the
+/// same type) reuse them without calling the allocator.
+/// This helps workloads which create and destroy many `Gc`s of the same few types, at the cost of
+/// holding on to memory which is no longer in use.
+///
+/// The free list is disabled (a capacity of 0) by default.
+/// Lowering the capacity immediately frees any kept allocations which no longer fit.
+/// Use [`free_list_hits`] to check how often allocations are being reused.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::unsync::{free_list_hits, set_free_list_capacity, Gc};
+///
+/// set_free_list_capacity(16);
+///
+/// drop(Gc::new(1u64));
+/// let gc = Gc::new(2u64); // reuses the allocation of the first `Gc`
+/// assert_eq!(free_list_hits(), 1);
+///
+/// set_free_list_capacity(0);
+/// ```
+pub fn set_free_list_capacity(capacity: usize) {
+    DUMPSTER.with(|d| d.set_free_list_capacity(capacity));
+}
+
+#[must_use]
+/// Get the number of allocations on this thread which have been created by reusing a freed
+/// allocation from the free list.
+///
+/// See [`set_free_list_capacity`] for details on the free list.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::unsync::{free_list_hits, Gc};
+///
+/// // the free list is disabled by default
+/// drop(Gc::new(1u64));
+/// let gc = Gc::new(2u64);
+/// assert_eq!(free_list_hits(), 0);
+/// ```
+pub fn free_list_hits() -> usize {
+    DUMPSTER.with(|d| d.n_free_list_hits.get())
+}
+
+/// Set the maximum number of [`Gc`]s which may still be living after a call to [`collect`].
+///
+/// When the threshold is `Some(n)`, any call to [`collect`] which leaves more than `n` `Gc`s living
+/// on this thread will panic.
+/// This is useful for detecting runaway growth or uncollectable garbage in tests.
+/// Only explicit calls to [`collect`] are checked; collections triggered automatically by the
+/// collection condition never panic.
+///
+/// By default, there is no threshold.
+///
+/// # Examples
+///
+/// ```should_panic
+/// use dumpster::unsync::{collect, set_leak_threshold, Gc};
+///
+/// set_leak_threshold(Some(1));
+///
+/// let gc1 = Gc::new(1);
+/// let gc2 = Gc::new(2);
+///
+/// collect(); // panics: 2 `Gc`s are still living
+/// ```
+pub fn set_leak_threshold(threshold: Option<usize>) {
+    DUMPSTER.with(|d| d.leak_threshold.set(threshold));
+}
+
+#[derive(Clone)]
+/// A snapshot of the settings of the garbage collector on this thread, as returned by
+/// [`get_config`].
+///
+/// A snapshot can be restored later with [`set_config`], which makes it possible to change the
+/// collector's tuning for the duration of some operation and put it back afterwards.
+/// The collection condition is captured as well, but can only be changed through
+/// [`set_collect_condition`] or [`set_collect_condition_boxed`].
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::unsync::{get_config, set_config, set_deterministic};
+///
+/// let mut config = get_config();
+/// assert!(!config.deterministic);
+/// config.deterministic = true;
+/// set_config(config.clone());
+/// assert!(get_config().deterministic);
+///
+/// set_deterministic(false);
+/// set_config(config);
+/// assert!(get_config().deterministic);
+/// ```
+pub struct CollectorConfig {
+    /// The function which determines whether a collection should be run, as set by
+    /// [`set_collect_condition`] or [`set_collect_condition_boxed`].
+    condition: StoredCondition,
+    /// Whether collections run in a reproducible order, as set by [`set_deterministic`].
+    pub deterministic: bool,
+    /// The maximum number of `Gc`s which may be living after a call to [`collect`], as set by
+    /// [`set_leak_threshold`].
+    pub leak_threshold: Option<usize>,
+    /// The number of freed allocations of each size which are kept for reuse, as set by
+    /// [`set_free_list_capacity`].
+    pub free_list_capacity: usize,
+    /// The function which reports roots that the collector cannot otherwise see, as set by
+    /// [`set_external_roots`].
+    pub external_roots: Option<fn(&mut ExternalRoots<'_>)>,
+}
+
+#[must_use]
+/// Take a snapshot of every setting of the garbage collector on this thread.
+///
+/// See [`CollectorConfig`] for details.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::unsync::{get_config, set_leak_threshold};
+///
+/// set_leak_threshold(Some(10));
+/// assert_eq!(get_config().leak_threshold, Some(10));
+/// ```
+pub fn get_config() -> CollectorConfig {
+    DUMPSTER.with(|d| CollectorConfig {
+        condition: d.collect_condition.borrow().clone(),
+        deterministic: d.deterministic.get(),
+        leak_threshold: d.leak_threshold.get(),
+        free_list_capacity: d.free_list_capacity.get(),
+        external_roots: d.external_roots.get(),
+    })
+}
+
+/// Restore every setting of the garbage collector on this thread from a snapshot taken by
+/// [`get_config`].
+///
+/// This has the same effect as calling each of the individual setters, such as
+/// [`set_deterministic`] and [`set_free_list_capacity`], with the values in `config`.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::unsync::{get_config, set_collect_condition, set_config, CollectInfo};
+///
+/// fn never_collect(_: &CollectInfo) -> bool {
+///     false
+/// }
+///
+/// let saved = get_config();
+/// set_collect_condition(never_collect);
+/// // ... allocate without interruption ...
+/// set_config(saved);
+/// ```
+pub fn set_config(config: CollectorConfig) {
+    DUMPSTER.with(|d| {
+        d.collect_condition.replace(config.condition);
+        d.deterministic.set(config.deterministic);
+        d.leak_threshold.set(config.leak_threshold);
+        d.set_free_list_capacity(config.free_list_capacity);
+        d.external_roots.set(config.external_roots);
+    });
+}
+
+#[cfg(feature = "debug-allocations")]
+/// Get a `Gc` to every living allocation on this thread which was created with a value of type
+/// `T`.
+///
+/// Each returned `Gc` is a new reference, so the allocations it points to will stay alive for at
+/// least as long as the returned handles.
+/// The result may include allocations which are unreachable but have not yet been collected; if
+/// that is undesirable, call [`collect`] first.
+/// The order of the returned `Gc`s is unspecified.
+///
+/// This function is only available with the `debug-allocations` feature enabled.
+///
+/// # Panics
+///
+/// This function will panic if it is called while a collection is running, such as from the
+/// `Drop` implementation of a garbage-collected value.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::unsync::{allocations_of_type, collect, Gc};
+///
+/// let a = Gc::new(1u32);
+/// let b = Gc::new(2u32);
+/// let _c = Gc::new("unrelated");
+///
+/// collect();
+/// let mut found: Vec<u32> = allocations_of_type::<u32>().iter().map(|gc| **gc).collect();
+/// found.sort_unstable();
+/// assert_eq!(found, [1, 2]);
+/// ```
+pub fn allocations_of_type<T: Collectable + 'static>() -> Vec<Gc<T>> {
+    assert!(
+        !collect::COLLECTING.with(Cell::get),
+        "allocations may not be queried while collecting"
+    );
+    DUMPSTER
+        .with(Dumpster::allocations_of_type::<T>)
+        .into_iter()
+        .map(|ptr| Gc::clone(&ManuallyDrop::new(Gc { ptr })))
+        .collect()
+}
+
+#[cfg(feature = "debug-allocations")]
+#[must_use]
+/// Get a human-readable description of every living allocation on this thread.
+///
+/// The description contains one line per allocation, giving its address and the type of the value
+/// it was created with.
+/// The lines are sorted by the name of the type, and then by the order in which the allocations
+/// were created, so a program which makes the same allocations produces its lines in the same order
+/// on every run (although the addresses will differ).
+/// With the `backtrace` feature enabled, the line is followed by the backtrace of the creation of
+/// the allocation, if one was captured (see [`set_capture_backtraces`]).
+/// As with [`allocations_of_type`], the description may include unreachable allocations which
+/// have not yet been collected.
+///
+/// This function is only available with the `debug-allocations` feature enabled.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::unsync::{debug_live_allocations, Gc};
+///
+/// let gc = Gc::new(1u8);
+/// assert!(debug_live_allocations().contains("u8"));
+/// ```
+pub fn debug_live_allocations() -> String {
+    DUMPSTER.with(Dumpster::debug_live_allocations)
+}
+
+#[cfg(feature = "backtrace")]
+/// Set whether a backtrace should be captured every time a `Gc` allocation is created on this
+/// thread.
+///
+/// Backtraces are captured regardless of the `RUST_BACKTRACE` environment variable.
+/// Capturing a backtrace is expensive, so this is disabled by default; enable it only around the
+/// code suspected of leaking.
+/// Captured backtraces can be retrieved with [`allocation_backtrace`] and are included in
+/// [`debug_live_allocations`].
+///
+/// This function is only available with the `backtrace` feature enabled.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::unsync::{allocation_backtrace, set_capture_backtraces, Gc};
+///
+/// set_capture_backtraces(true);
+/// let gc = Gc::new(());
+/// set_capture_backtraces(false);
+///
+/// assert!(allocation_backtrace(Gc::allocation_id(&gc)).is_some());
+/// ```
+pub fn set_capture_backtraces(capture: bool) {
+    DUMPSTER.with(|d| d.capture_backtraces.set(capture));
+}
+
+#[cfg(feature = "backtrace")]
+#[must_use]
+/// Get the backtrace which was captured when the living allocation with ID `id` was created.
+///
+/// Returns `None` if no allocation with ID `id` is living on this thread, or if backtraces were not
+/// being captured when it was created.
+///
+/// This function is only available with the `backtrace` feature enabled.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::unsync::{allocation_backtrace, Gc};
+///
+/// // backtraces are not captured by default
+/// let gc = Gc::new(());
+/// assert!(allocation_backtrace(Gc::allocation_id(&gc)).is_none());
+/// ```
+pub fn allocation_backtrace(id: AllocationId) -> Option<Rc<Backtrace>> {
+    DUMPSTER.with(|d| d.allocation_backtrace(id))
+}
+
+#[cfg(feature = "bench")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+/// The time spent in each phase of a single garbage collection.
+///
+/// This is only available with the `bench` feature enabled, and is intended for measuring the
+/// performance of the garbage collector itself.
+pub struct PhaseTimes {
+    /// The time spent tracing the allocations which might be garbage to build a reference graph.
+    pub trace: Duration,
+    /// The time spent marking the allocations which are reachable from outside the graph.
+    pub mark: Duration,
+    /// The time spent dropping and freeing unreachable allocations.
+    pub sweep: Duration,
+}
+
+#[cfg(feature = "bench")]
+impl PhaseTimes {
+    /// A duration of zero for every phase.
+    pub const ZERO: PhaseTimes = PhaseTimes {
+        trace: Duration::ZERO,
+        mark: Duration::ZERO,
+        sweep: Duration::ZERO,
+    };
+}
+
+#[cfg(feature = "bench")]
+#[must_use]
+/// Get the time spent in each phase of the most recent garbage collection on this thread.
+///
+/// If no collection has run on this thread yet, every phase will have taken zero time.
+///
+/// This function is only available with the `bench` feature enabled.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::unsync::{collect, last_collect_phase_times, Gc};
+///
+/// let gc = Gc::new(());
+/// drop(gc.clone());
+/// collect();
+/// let times = last_collect_phase_times();
+/// println!("tracing took {:?}", times.trace);
+/// ```
+pub fn last_collect_phase_times() -> PhaseTimes {
+    DUMPSTER.with(|d| d.phase_times.get())
+}
+
+#[cfg(feature = "metrics")]
+/// Set a function to be called with the duration of every garbage collection on this thread, or
+/// `None` to stop reporting durations.
+///
+/// The duration covers the whole pause, from the start of tracing until every value has been
+/// dropped and every finalizer has run.
+/// This makes it possible to feed collection pauses into a latency histogram or another metrics
+/// system.
+///
+/// This function is only available with the `metrics` feature enabled.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::unsync::{collect, set_collect_latency_hook, Gc};
+/// use std::{cell::RefCell, time::Duration};
+///
+/// thread_local! {
+///     static PAUSES: RefCell<Vec<Duration>> = const { RefCell::new(Vec::new()) };
+/// }
+///
+/// set_collect_latency_hook(Some(|pause| PAUSES.with(|p| p.borrow_mut().push(pause))));
+/// collect();
+/// set_collect_latency_hook(None);
+///
+/// assert_eq!(PAUSES.with(|p| p.borrow().len()), 1);
+/// ```
+pub fn set_collect_latency_hook(hook: Option<fn(Duration)>) {
+    DUMPSTER.with(|d| d.latency_hook.set(hook));
+}
+
+#[cfg(all(feature = "metrics", debug_assertions))]
+#[must_use]
+/// Get the duration of every garbage collection which has run on this thread, in order.
+///
+/// This is a simpler alternative to [`set_collect_latency_hook`] for debugging.
+/// Since every duration is kept, this function is only available in debug builds (with
+/// `debug_assertions` enabled) with the `metrics` feature enabled.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::unsync::{collect, collection_latencies};
+///
+/// let before = collection_latencies().len();
+/// collect();
+/// assert_eq!(collection_latencies().len(), before + 1);
+/// ```
+pub fn collection_latencies() -> Vec<Duration> {
+    DUMPSTER.with(|d| d.latencies.borrow().clone())
+}
+
+/// Register a function to be run once the value that `gc` points to has been dropped.
+///
+/// This makes it possible to attach cleanup logic to values of types which do not implement
+/// `Drop` themselves, such as foreign types.
+/// The finalizer runs exactly once, after the value's own `Drop` implementation, whether the value
+/// is dropped because its last `Gc` went away or because it was collected as part of an unreachable
+/// cycle.
+/// Finalizers for values dropped by the garbage collector are run once the collection is over,
+/// so they are free to use `Gc`s (including starting another collection).
+/// If several finalizers are registered for the same allocation, they run in the order they were
+/// registered.
+///
+/// A finalizer never runs if the value is moved out by [`Gc::try_unwrap`] instead of being dropped,
+/// nor for a `Gc` created by a [`StaticGc`], whose value is never dropped.
+///
+/// # Panics
+///
+/// This function will panic if the value that `gc` points to has already been collected, which can
+/// only happen from within the implementation of `std::ops::Drop` of a value being collected.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::unsync::{register_finalizer, Gc};
+/// use std::{cell::Cell, rc::Rc};
+///
+/// let finalized = Rc::new(Cell::new(false));
+/// let gc = Gc::new(0u8);
+/// let flag = finalized.clone();
+/// register_finalizer(&gc, Box::new(move || flag.set(true)));
+///
+/// drop(gc);
+/// assert!(finalized.get());
+/// ```
+pub fn register_finalizer<T: Collectable + ?Sized>(gc: &Gc<T>, finalizer: Box<dyn FnOnce()>) {
+    let box_ref = unsafe { gc.ptr.as_ref() };
+    assert!(
+        !box_ref.is_zombie(),
+        "registering a finalizer on an already-collected object"
+    );
+    if box_ref.is_static() {
+        return;
+    }
+    DUMPSTER.with(|d| d.register_finalizer(gc.ptr, finalizer));
+}
+
+#[cfg(feature = "std")]
+/// Get the `Gc` stored in `map` under `key`, allocating a new one with the value returned by `make`
+/// only if `key` is absent.
+///
+/// Unlike `map.entry(key).or_insert(Gc::new(...))`, no allocation is made and the garbage collector
+/// is not notified of anything when `key` is already present.
+///
+/// This function is only available with the `std` feature enabled.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::unsync::{entry_or_gc, Gc};
+/// use std::collections::HashMap;
+///
+/// let mut map: HashMap<&str, Gc<u32>> = HashMap::new();
+///
+/// let first = entry_or_gc(&mut map, "a", || 1).clone();
+/// let second = entry_or_gc(&mut map, "a", || unreachable!());
+/// assert!(std::ptr::eq(&*first, &**second));
+/// ```
+pub fn entry_or_gc<K, V, S>(
+    map: &mut HashMap<K, Gc<V>, S>,
+    key: K,
+    make: impl FnOnce() -> V,
+) -> &Gc<V>
+where
+    K: Eq + Hash,
+    V: Collectable,
+    S: BuildHasher,
+{
+    map.entry(key).or_insert_with(|| Gc::new(make()))
+}
+
+#[repr(C)]
+/// The underlying heap allocation for a [`Gc`].
+///
+/// A `GcBox` holds a garbage-collected value alongside its reference counts.
+/// Its contents are private: it is only exposed so that pointers to it can be passed around with
+/// [`Gc::into_gcbox_ptr`] and [`Gc::from_gcbox_ptr`].
+pub struct GcBox<T: Collectable + ?Sized> {
+    /// The number of extant references to this garbage-collected data.
+    /// If the stored reference count is zero, then this value is a "zombie" - in the process of
+    /// being dropped - and should not be dropped again.
+    /// If the stored reference count is `usize::MAX`, then this allocation lives in static memory
+    /// (see [`StaticGc`]) and its reference count is never modified.
+    ref_count: Cell<usize>,
+    /// The number of extant [`Weak`]s to this allocation, plus one if the reference count is
+    /// nonzero or the value is still being dropped.
+    /// The allocation is freed once this count reaches zero.
+    /// Its highest bits are not part of the count: they are flags instead (see [`WEAK_FLAGS`]).
+    weak: Cell<usize>,
+    /// The stored value inside this garbage-collected box.
+    value: T,
+}
+
+/// The bit of [`GcBox::weak`] which is set for allocations made by [`Gc::new_acyclic`].
+/// Leaked `Weak`s cost no memory, so a program could make enough of them for the weak count to
+/// reach this bit; [`GcBox::add_weak`] aborts the process before that happens.
+const ACYCLIC_FLAG: usize = 1 << (usize::BITS - 1);
+
+#[cfg(feature = "allocator_api")]
+/// The bit of [`GcBox::weak`] which is set for allocations made by [`Gc::new_in`], whose memory
+/// must be returned to the allocator they were made in.
+/// Like [`ACYCLIC_FLAG`], the weak count can never reach it, since [`GcBox::add_weak`] aborts the
+/// process first.
+const IN_ALLOCATOR_FLAG: usize = 1 << (usize::BITS - 2);
+
+#[cfg(not(feature = "allocator_api"))]
+/// Every bit of [`GcBox::weak`] which is a flag rather than part of the weak count.
+const WEAK_FLAGS: usize = ACYCLIC_FLAG;
+#[cfg(feature = "allocator_api")]
+/// Every bit of [`GcBox::weak`] which is a flag rather than part of the weak count.
+const WEAK_FLAGS: usize = ACYCLIC_FLAG | IN_ALLOCATOR_FLAG;
+
+/// A garbage-collected value stored in static memory.
+///
+/// A `StaticGc` is usually declared using the [`gc_static!`](crate::gc_static) macro.
+/// Its allocation is never freed, so handles to it created by [`StaticGc::get`] are always valid
+/// and the collector treats it as a permanent root.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::unsync::{Gc, StaticGc};
+///
+/// static CONFIG: StaticGc<[u8; 3]> = StaticGc::new([1, 2, 3]);
+///
+/// let config: Gc<[u8; 3]> = CONFIG.get();
+/// assert_eq!(config[1], 2);
+/// ```
+pub struct StaticGc<T: Collectable + Sync + 'static> {
+    /// The allocation which all handles to this value point to.
+    gc_box: GcBox<T>,
+}
+
+/// Declare a static garbage-collected value.
+///
+/// The value is constructed at compile time and stored in a [`StaticGc`], which hands out
+/// [`unsync::Gc`](crate::unsync::Gc) handles on any thread using [`StaticGc::get`].
+/// The value must be [`Sync`], since it may be accessed from every thread.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::{gc_static, unsync::Gc};
+///
+/// gc_static! {
+///     /// The greeting for this program.
+///     static GREETING: Gc<&'static str> = "hello";
+/// }
+///
+/// let greeting: Gc<&'static str> = GREETING.get();
+/// assert_eq!(*greeting, "hello");
+/// ```
+#[macro_export]
+macro_rules! gc_static {
+    ($(#[$attr:meta])* $vis:vis static $name:ident: Gc<$t:ty> = $value:expr;) => {
+        $(#[$attr])*
+        $vis static $name: $crate::unsync::StaticGc<$t> = $crate::unsync::StaticGc::new($value);
+    };
+}
+
+impl<T: Collectable + ?Sized> Gc<T> {
+    /// Construct a new garbage-collected allocation, with `value` as its value.
+    pub fn new(value: T) -> Gc<T>
+    where
+        T: Sized,
+    {
+        DUMPSTER.with(|d| Gc::new_with(d, value))
+    }
+
+    /// Construct a new garbage-collected allocation with `value` as its value, like [`Gc::new`],
+    /// but using `dumpster` instead of looking up this thread's dumpster.
+    fn new_with(dumpster: &Dumpster, value: T) -> Gc<T>
+    where
+        T: Sized,
+    {
+        let ptr = GcBox::allocate_with(dumpster, value);
+        dumpster.notify_created_gc();
+        #[cfg(any(feature = "debug-allocations", feature = "track-allocations"))]
+        dumpster.notify_allocated(ptr);
+        Gc { ptr }
+    }
+
+    /// Create another `Gc` to the same allocation as `this`, like [`Gc::clone`], but using
+    /// `dumpster` instead of looking up this thread's dumpster.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the pointed-to value is being dropped by the garbage collector.
+    fn clone_with(this: &Gc<T>, dumpster: &Dumpster) -> Gc<T> {
+        let box_ref = unsafe { this.ptr.as_ref() };
+        assert!(
+            !box_ref.is_zombie(),
+            "cloning GC to already-collected object"
+        );
+        box_ref.add_strong(1);
+        dumpster.notify_created_gc();
+        Gc { ptr: this.ptr }
+    }
+
+    /// Construct a new garbage-collected allocation for each value yielded by `iter`.
+    ///
+    /// This is equivalent to calling [`Gc::new`] on each value, but it updates the garbage
+    /// collector's bookkeeping once for the whole batch instead of once per allocation, which makes
+    /// it cheaper to create many small allocations at once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    ///
+    /// let gcs = Gc::new_many(0..3);
+    /// assert_eq!(gcs.iter().map(|gc| **gc).collect::<Vec<_>>(), [0, 1, 2]);
+    /// ```
+    pub fn new_many<I: IntoIterator<Item = T>>(iter: I) -> Vec<Gc<T>>
+    where
+        T: Sized,
+    {
+        /// The number of `Gc`s made so far, which are reported to the collector when this is
+        /// dropped, even if `iter` panics.
+        struct Batch(usize);
+
+        impl Drop for Batch {
+            fn drop(&mut self) {
+                DUMPSTER.with(|d| d.notify_created_gcs(self.0));
+            }
+        }
+
+        let iter = iter.into_iter();
+        let mut gcs = Vec::with_capacity(iter.size_hint().0);
+        // declared after `gcs` so that it is dropped first if `iter` panics
+        let mut batch = Batch(0);
+        for value in iter {
+            let ptr = GcBox::allocate(value);
+            #[cfg(any(feature = "debug-allocations", feature = "track-allocations"))]
+            DUMPSTER.with(|d| d.notify_allocated(ptr));
+            gcs.push(Gc { ptr });
+            batch.0 += 1;
+        }
+        gcs
+    }
+
+    /// Construct a new garbage-collected allocation for a value which can never contain a `Gc`.
+    ///
+    /// Since the allocation can never be part of a cycle, the garbage collector does not need to
+    /// keep track of it: dropping one of several `Gc`s pointing to it only decrements its
+    /// reference count, and it is freed as soon as the last one is dropped, just like an `Rc`.
+    /// Otherwise, the returned `Gc` behaves exactly like one made by [`Gc::new`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    ///
+    /// let gc = Gc::new_acyclic(String::from("no cycles here"));
+    /// let gc2 = gc.clone();
+    /// drop(gc);
+    /// assert_eq!(*gc2, "no cycles here");
+    /// ```
+    pub fn new_acyclic(value: T) -> Gc<T>
+    where
+        T: Acyclic + Sized,
+    {
+        let gc = Gc::new(value);
+        let box_ref = unsafe { gc.ptr.as_ref() };
+        box_ref.weak.set(box_ref.weak.get() | ACYCLIC_FLAG);
+        gc
+    }
+
+    #[must_use]
+    /// Get the memory layout of the allocation backing `this`.
+    ///
+    /// The returned layout covers the entire allocation, including the reference-counting header
+    /// stored alongside the value, not just the value itself.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    ///
+    /// let gc = Gc::new(0u64);
+    /// let layout = Gc::allocation_layout(&gc);
+    /// assert!(layout.size() > std::mem::size_of::<u64>());
+    /// ```
+    pub fn allocation_layout(this: &Gc<T>) -> Layout {
+        Layout::for_value(unsafe { this.ptr.as_ref() })
+    }
+
+    #[must_use]
+    /// Get the ID of the allocation that `this` points to.
+    ///
+    /// IDs are only unique among living allocations: once an allocation is freed, its ID may be
+    /// reused.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    ///
+    /// let gc1 = Gc::new(());
+    /// let gc2 = gc1.clone();
+    /// assert_eq!(Gc::allocation_id(&gc1), Gc::allocation_id(&gc2));
+    /// ```
+    pub fn allocation_id(this: &Gc<T>) -> AllocationId {
+        AllocationId::from(this.ptr)
+    }
+
+    #[must_use]
+    /// Get a reference to the value that `this` points to, or `None` if that value has already
+    /// been collected.
+    ///
+    /// This is the non-panicking counterpart to dereferencing a `Gc`.
+    /// A value can only have been collected while the garbage collector is dropping values, so
+    /// this always returns `Some` except from within the `Drop` implementation of a value which is
+    /// being collected.
+    /// Code which might run in such a destructor can use this to skip `Gc`s to other garbage
+    /// instead of panicking.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    ///
+    /// let gc = Gc::new(5);
+    /// assert_eq!(Gc::try_deref(&gc), Some(&5));
+    /// ```
+    pub fn try_deref(this: &Gc<T>) -> Option<&T> {
+        let box_ref = unsafe { this.ptr.as_ref() };
+        (!box_ref.is_zombie()).then_some(&box_ref.value)
+    }
+
+    #[must_use]
+    /// Determine whether the value that `this` points to has already been collected, so that
+    /// dereferencing `this` would panic.
+    ///
+    /// This can only be `true` while the garbage collector is dropping values, from within the
+    /// `Drop` implementation of a value which is being collected along with the allocation of
+    /// `this`.
+    /// Destructors of types which may be part of a cycle can use this to skip their `Gc`s to other
+    /// garbage.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::{
+    ///     unsync::{collect, Gc},
+    ///     Collectable,
+    /// };
+    /// use std::cell::RefCell;
+    ///
+    /// #[derive(Collectable)]
+    /// struct Node {
+    ///     name: String,
+    ///     next: RefCell<Option<Gc<Node>>>,
+    /// }
+    ///
+    /// impl Drop for Node {
+    ///     fn drop(&mut self) {
+    ///         if let Some(next) = self.next.borrow().as_ref() {
+    ///             if !Gc::is_dead(next) {
+    ///                 println!("{} is no longer pointing to {}", self.name, next.name);
+    ///             }
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let node = Gc::new(Node {
+    ///     name: String::from("node"),
+    ///     next: RefCell::new(None),
+    /// });
+    /// assert!(!Gc::is_dead(&node));
+    /// *node.next.borrow_mut() = Some(node.clone());
+    /// drop(node);
+    /// collect();
+    /// ```
+    pub fn is_dead(this: &Gc<T>) -> bool {
+        unsafe { this.ptr.as_ref() }.is_zombie()
+    }
+
+    /// Move the value out of `this` if it is the only `Gc` pointing to its allocation.
+    ///
+    /// If any other `Gc` points to the same allocation, `this` is returned unchanged as an error.
+    /// On success, any [`Weak`]s to the allocation can no longer be upgraded.
+    ///
+    /// # Errors
+    ///
+    /// This function will return `Err(this)` if `this` is not the only `Gc` to its allocation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    ///
+    /// let gc = Gc::new(3);
+    /// assert_eq!(Gc::try_unwrap(gc).ok(), Some(3));
+    ///
+    /// let gc = Gc::new(4);
+    /// let gc2 = gc.clone();
+    /// let gc = Gc::try_unwrap(gc).unwrap_err();
+    /// assert_eq!(*gc, 4);
+    /// ```
+    pub fn try_unwrap(this: Gc<T>) -> Result<T, Gc<T>>
+    where
+        T: Sized,
+    {
+        let box_ref = unsafe { this.ptr.as_ref() };
+        if box_ref.ref_count.get() != 1 {
+            return Err(this);
+        }
+
+        let this = ManuallyDrop::new(this);
+        box_ref.ref_count.set(0);
+        let _ = DUMPSTER.try_with(|d| {
+            d.mark_cleaned(this.ptr);
+            d.discard_finalizers(this.ptr);
+            #[cfg(feature = "debug-allocations")]
+            d.notify_deallocated(this.ptr);
+        });
+        unsafe {
+            let value = read(addr_of!(this.ptr.as_ref().value));
+            // note: `box_ref` may no longer be usable after this
+            GcBox::release_weak(this.ptr);
+            let _ = DUMPSTER.try_with(Dumpster::notify_dropped_gc);
+            Ok(value)
+        }
+    }
+
+    #[must_use]
+    /// Get a mutable reference to the value that `this` points to, if `this` is the only reference
+    /// to its allocation.
+    ///
+    /// Returns `None` if any other `Gc` or [`Weak`] points to the same allocation, since mutating
+    /// the value could then be observed through them.
+    /// This also returns `None` while the garbage collector is running (such as from within the
+    /// `Drop` implementation of a collected value), since the collector may be inspecting the
+    /// value concurrently.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    ///
+    /// let mut gc = Gc::new(1);
+    /// *Gc::get_mut(&mut gc).unwrap() += 1;
+    /// assert_eq!(*gc, 2);
+    ///
+    /// let gc2 = gc.clone();
+    /// assert!(Gc::get_mut(&mut gc).is_none());
+    /// ```
+    pub fn get_mut(this: &mut Gc<T>) -> Option<&mut T> {
+        let box_ref = unsafe { this.ptr.as_ref() };
+        let unique = box_ref.ref_count.get() == 1 && box_ref.weak_count() == 1;
+        if !unique || collect::COLLECTING.with(Cell::get) {
+            return None;
+        }
+
+        // SAFETY: `this` is the only reference of any kind to the allocation, and it is mutably
+        // borrowed for the lifetime of the returned reference.
+        Some(unsafe { &mut this.ptr.as_mut().value })
+    }
+
+    #[must_use]
+    /// Get the number of `Gc`s which currently point to the same allocation as `this`.
+    ///
+    /// If the allocation is being dropped by the garbage collector, this returns 0.
+    /// This can only be observed from within the implementation of `std::ops::Drop` of a value
+    /// which is being collected.
+    /// Allocations created by a [`StaticGc`] have no meaningful count, and always report
+    /// `usize::MAX`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    ///
+    /// let gc = Gc::new(());
+    /// assert_eq!(Gc::strong_count(&gc), 1);
+    /// let gc2 = gc.clone();
+    /// assert_eq!(Gc::strong_count(&gc), 2);
+    /// ```
+    pub fn strong_count(this: &Gc<T>) -> usize {
+        unsafe { this.ptr.as_ref() }.ref_count.get()
+    }
+
+    #[track_caller]
+    /// Assert that `this` is the only `Gc` pointing to its allocation.
+    ///
+    /// This is intended for documenting and enforcing ownership invariants at call sites, such as
+    /// before relying on [`Gc::get_mut`] succeeding.
+    /// The check is only made in debug builds; in release builds, this function does nothing.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, this function will panic if the strong count of `this` is not 1.
+    /// The panic message includes the name of `T` and the current strong count.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    ///
+    /// let gc = Gc::new(5);
+    /// Gc::assert_unique(&gc);
+    /// ```
+    pub fn assert_unique(this: &Gc<T>) {
+        debug_assert!(
+            Gc::strong_count(this) == 1,
+            "expected a unique `Gc<{}>`, but its strong count is {}",
+            type_name::<T>(),
+            Gc::strong_count(this)
+        );
+    }
+
+    #[must_use]
+    /// Make `n` new `Gc`s pointing to the same allocation as `this`.
+    ///
+    /// This is equivalent to calling [`Clone::clone`] `n` times, but it updates the reference
+    /// count and the garbage collector's bookkeeping once instead of once per clone.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the pointed-to value is being dropped by the garbage collector,
+    /// just like [`Clone::clone`].
+    ///
+    /// # Aborts
+    ///
+    /// This function aborts the process if the number of `Gc`s pointing to the allocation would
+    /// overflow a `usize`, just like [`Clone::clone`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    ///
+    /// let parent = Gc::new(());
+    /// let handles = Gc::clone_n(&parent, 3);
+    /// assert_eq!(handles.len(), 3);
+    /// assert_eq!(Gc::strong_count(&parent), 4);
+    /// ```
+    pub fn clone_n(this: &Gc<T>, n: usize) -> Vec<Gc<T>> {
+        let mut clones = Vec::with_capacity(n);
+        let box_ref = unsafe { this.ptr.as_ref() };
+        assert!(
+            !box_ref.is_zombie(),
+            "cloning GC to already-collected object"
+        );
+        box_ref.add_strong(n);
+        DUMPSTER.with(|d| d.notify_created_gcs(n));
+        clones.resize_with(n, || Gc { ptr: this.ptr });
+        clones
+    }
+
+    #[must_use]
+    /// Get a raw pointer to the value that `this` points to, like [`std::rc::Rc::as_ptr`].
+    ///
+    /// The pointer is valid for as long as `this` (or any other `Gc` to the same allocation) lives,
+    /// and stays the same for every `Gc` to the allocation, so it can be used as the identity of
+    /// the value.
+    /// Unlike dereferencing `this`, this never panics, even while the value is being dropped by
+    /// the garbage collector.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    ///
+    /// let gc1 = Gc::new(1);
+    /// let gc2 = gc1.clone();
+    /// assert_eq!(Gc::as_ptr(&gc1), Gc::as_ptr(&gc2));
+    /// assert_eq!(Gc::as_ptr(&gc1), &*gc1 as *const i32);
+    /// ```
+    pub fn as_ptr(this: &Gc<T>) -> *const T {
+        unsafe { addr_of!((*this.ptr.as_ptr()).value) }
+    }
+
+    /// Order `this` and `other` by the address of the allocation they point to, without
+    /// inspecting their values.
+    ///
+    /// This is how `Gc`s are compared while the garbage collector is running, since their values
+    /// may already have been dropped by then.
+    fn addr_cmp(this: &Gc<T>, other: &Gc<T>) -> core::cmp::Ordering {
+        this.ptr
+            .as_ptr()
+            .cast::<()>()
+            .cmp(&other.ptr.as_ptr().cast::<()>())
+    }
+
+    #[must_use]
+    /// Get a pointer to the allocation backing `this`, without affecting its reference count.
+    ///
+    /// The pointer is valid for as long as `this` (or any other `Gc` to the same allocation) lives.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    ///
+    /// let gc1 = Gc::new(1);
+    /// let gc2 = gc1.clone();
+    /// assert_eq!(Gc::as_gcbox_ptr(&gc1), Gc::as_gcbox_ptr(&gc2));
+    /// ```
+    pub fn as_gcbox_ptr(this: &Gc<T>) -> NonNull<GcBox<T>> {
+        this.ptr
+    }
+
+    #[must_use]
+    /// Consume `this`, returning a pointer to its allocation.
+    ///
+    /// The reference held by `this` is transferred to the caller: the allocation's reference count
+    /// is left unchanged, and the allocation will stay alive at least until that reference is
+    /// given back with [`Gc::from_gcbox_ptr`].
+    /// While the reference is held as a pointer, the garbage collector cannot see where it is
+    /// stored, so it treats the allocation as reachable.
+    /// If the reference is never given back, the allocation (and everything reachable from it) is
+    /// leaked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    ///
+    /// let ptr = Gc::into_gcbox_ptr(Gc::new(1));
+    /// let gc = unsafe { Gc::from_gcbox_ptr(ptr) };
+    /// assert_eq!(*gc, 1);
+    /// ```
+    pub fn into_gcbox_ptr(this: Gc<T>) -> NonNull<GcBox<T>> {
+        ManuallyDrop::new(this).ptr
+    }
+
+    #[must_use]
+    /// Construct a `Gc` from a pointer to an allocation, taking over a reference which was
+    /// previously given up with [`Gc::into_gcbox_ptr`].
+    ///
+    /// The allocation's reference count is not incremented: the returned `Gc` owns the
+    /// transferred reference, and dropping it releases that reference as usual.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by [`Gc::into_gcbox_ptr`] on this thread, for a `Gc` with the
+    /// same `T` (or with a `T` which unsized-coerces to this one).
+    /// Each call to [`Gc::into_gcbox_ptr`] transfers exactly one reference, so it may be matched by
+    /// at most one call to this function.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    ///
+    /// let gc = Gc::new(1);
+    /// let ptr = Gc::into_gcbox_ptr(gc.clone());
+    /// assert_eq!(Gc::strong_count(&gc), 2);
+    ///
+    /// drop(unsafe { Gc::from_gcbox_ptr(ptr) });
+    /// assert_eq!(Gc::strong_count(&gc), 1);
+    /// ```
+    pub unsafe fn from_gcbox_ptr(ptr: NonNull<GcBox<T>>) -> Gc<T> {
+        Gc { ptr }
+    }
+
+    #[must_use]
+    /// Construct a new `Gc` from a pointer to an allocation which is kept alive by some other
+    /// reference, incrementing its reference count.
+    ///
+    /// This is equivalent to cloning a `Gc` to the allocation: the caller keeps whatever reference
+    /// kept the allocation alive, and the returned `Gc` holds a new one.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to a living allocation created on this thread with the same `T` (or a `T`
+    /// which unsized-coerces to this one), such as one obtained from [`Gc::as_gcbox_ptr`] or
+    /// [`Gc::into_gcbox_ptr`], and some reference must keep it alive for the duration of this
+    /// call.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the allocation is being dropped by the garbage collector, just
+    /// like cloning a `Gc` to it would.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    ///
+    /// let gc1 = Gc::new(1);
+    /// let gc2 = unsafe { Gc::from_gcbox_ptr_cloned(Gc::as_gcbox_ptr(&gc1)) };
+    /// assert_eq!(Gc::strong_count(&gc1), 2);
+    /// ```
+    pub unsafe fn from_gcbox_ptr_cloned(ptr: NonNull<GcBox<T>>) -> Gc<T> {
+        // the `Gc` borrowed here holds the caller's reference, so it must not be dropped
+        (*ManuallyDrop::new(Gc { ptr })).clone()
+    }
+
+    #[cfg(feature = "bytemuck")]
+    #[must_use]
+    /// View the value pointed to by `this` as raw bytes.
+    ///
+    /// This function is only available with the `bytemuck` feature enabled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    ///
+    /// let gc = Gc::new(0x0102_0304u32);
+    /// assert_eq!(Gc::as_bytes(&gc), 0x0102_0304u32.to_ne_bytes());
+    /// ```
+    pub fn as_bytes(this: &Gc<T>) -> &[u8]
+    where
+        T: bytemuck::Pod,
+    {
+        bytemuck::bytes_of::<T>(this)
+    }
+
+    #[must_use]
+    /// Convert `this` into a `Gc<dyn AnyCollectable>`, erasing the type of its contents.
+    ///
+    /// The returned `Gc` points to the same allocation as `this`.
+    /// The value can still be traced through a [`dyn AnyCollectable`](AnyCollectable), so the
+    /// allocation takes part in cycle collection just as it would through `this`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::{unsync::Gc, AnyCollectable};
+    ///
+    /// let gc: Gc<dyn AnyCollectable> = Gc::into_any(Gc::new(5u8));
+    /// assert_eq!(gc.downcast_ref::<u8>(), Some(&5));
+    /// ```
+    pub fn into_any(this: Gc<T>) -> Gc<dyn AnyCollectable>
+    where
+        T: Any + Sized,
+    {
+        let this = ManuallyDrop::new(this);
+        Gc { ptr: this.ptr }
+    }
+
+    #[must_use]
+    /// Store `this` in a `Box<dyn Any>`, erasing its type.
+    ///
+    /// Unlike [`Gc::into_any`], this erases the type of the `Gc` itself rather than the type of
+    /// the value it points to, so the value can still be traced as usual.
+    /// This is useful for storing handles of different types side by side, such as in a plugin
+    /// registry.
+    /// The `Gc` can be recovered with [`Box::downcast`], and its allocation stays alive for as long
+    /// as the box does.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    ///
+    /// let boxed = Gc::into_boxed_any(Gc::new(5u8));
+    /// assert!(boxed.is::<Gc<u8>>());
+    /// let gc: Box<Gc<u8>> = boxed.downcast().unwrap();
+    /// assert_eq!(**gc, 5);
+    /// ```
+    pub fn into_boxed_any(this: Gc<T>) -> Box<dyn Any> {
+        Box::new(this)
+    }
+
+    #[must_use]
+    /// Convert `this` into a `Gc<dyn DynPartialEq>`, erasing the type of its contents while
+    /// keeping the ability to compare it with [`Gc::dyn_eq`].
+    ///
+    /// The returned `Gc` points to the same allocation as `this`.
+    /// Like [`Gc::into_any`], the value can still be traced through the returned `Gc`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::{unsync::Gc, DynPartialEq};
+    ///
+    /// let gc: Gc<dyn DynPartialEq> = Gc::into_dyn_eq(Gc::new(5u8));
+    /// assert_eq!(gc.as_any().downcast_ref::<u8>(), Some(&5));
+    /// ```
+    pub fn into_dyn_eq(this: Gc<T>) -> Gc<dyn DynPartialEq>
+    where
+        T: DynPartialEq + Sized,
+    {
+        let this = ManuallyDrop::new(this);
+        Gc { ptr: this.ptr }
+    }
+}
+
+impl Gc<dyn DynPartialEq> {
+    #[must_use]
+    /// Determine whether the values that `a` and `b` point to have the same concrete type and are
+    /// equal.
+    ///
+    /// Values of different types are never equal, even if one could be compared with the other.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if either pointed-to value is being dropped by the garbage
+    /// collector, just like [`Deref::deref`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::{unsync::Gc, DynPartialEq};
+    ///
+    /// let a: Gc<dyn DynPartialEq> = Gc::into_dyn_eq(Gc::new(String::from("x")));
+    /// let b: Gc<dyn DynPartialEq> = Gc::into_dyn_eq(Gc::new(String::from("x")));
+    /// let c: Gc<dyn DynPartialEq> = Gc::into_dyn_eq(Gc::new('x'));
+    /// assert!(Gc::dyn_eq(&a, &b));
+    /// assert!(!Gc::dyn_eq(&a, &c));
+    /// ```
+    pub fn dyn_eq(a: &Gc<dyn DynPartialEq>, b: &Gc<dyn DynPartialEq>) -> bool {
+        (**a).dyn_eq(&**b)
+    }
+}
+
+impl Gc<dyn AnyCollectable> {
+    /// Attempt to convert `this` into a `Gc` to a concrete type.
+    ///
+    /// This is the inverse of [`Gc::into_any`], and works like [`std::rc::Rc::downcast`].
+    /// On success, the returned `Gc` points to the same allocation as `this`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return `Err(this)` if the value pointed to by `this` is not a `T`.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the pointed-to value is being dropped by the garbage collector,
+    /// just like [`Deref::deref`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::{unsync::Gc, AnyCollectable};
+    ///
+    /// let gc: Gc<dyn AnyCollectable> = Gc::into_any(Gc::new(5u32));
+    /// let gc = Gc::downcast::<u8>(gc).unwrap_err();
+    /// let gc: Gc<u32> = Gc::downcast(gc).unwrap();
+    /// assert_eq!(*gc, 5);
+    /// ```
+    pub fn downcast<T: Any + Collectable>(
+        this: Gc<dyn AnyCollectable>,
+    ) -> Result<Gc<T>, Gc<dyn AnyCollectable>> {
+        if (*this).is::<T>() {
+            let this = ManuallyDrop::new(this);
+            Ok(Gc {
+                ptr: this.ptr.cast(),
+            })
+        } else {
+            Err(this)
+        }
+    }
+}
+
+impl<T: Collectable> Gc<Vec<T>> {
+    /// Move the vector out of `this` if it is the only `Gc` pointing to its allocation.
+    ///
+    /// This is the same as [`Gc::try_unwrap`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return `Err(this)` if `this` is not the only `Gc` to its allocation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    ///
+    /// let gc = Gc::new(vec![1, 2, 3]);
+    /// assert_eq!(Gc::into_vec(gc).ok(), Some(vec![1, 2, 3]));
+    /// ```
+    pub fn into_vec(this: Gc<Vec<T>>) -> Result<Vec<T>, Gc<Vec<T>>> {
+        Gc::try_unwrap(this)
+    }
+}
+
+impl<T: Collectable + Clone> IntoIterator for Gc<Vec<T>> {
+    type Item = T;
+    type IntoIter = alloc::vec::IntoIter<T>;
+
+    /// Consume this `Gc`, creating an iterator over the elements of the vector it points to.
+    ///
+    /// If this is the only `Gc` to its allocation, the vector is moved out of the allocation and
+    /// its elements are yielded without being cloned.
+    /// Otherwise, the allocation is left untouched for the other `Gc`s to use, and the iterator
+    /// yields clones of its elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    ///
+    /// let gc = Gc::new(vec![String::from("a"), String::from("b")]);
+    /// let other = gc.clone();
+    ///
+    /// // `other` still exists, so the elements are cloned
+    /// assert_eq!(gc.into_iter().collect::<String>(), "ab");
+    /// // `other` is now unique, so the elements are moved out
+    /// assert_eq!(other.into_iter().collect::<String>(), "ab");
+    /// ```
+    fn into_iter(self) -> Self::IntoIter {
+        match Gc::into_vec(self) {
+            Ok(vec) => vec.into_iter(),
+            Err(gc) => Vec::clone(&gc).into_iter(),
+        }
+    }
+}
+
+// `iter` is already reachable through `Deref` for every type that has one.
+#[allow(clippy::into_iter_without_iter)]
+impl<'a, T: Collectable + ?Sized> IntoIterator for &'a Gc<T>
+where
+    &'a T: IntoIterator,
+{
+    type Item = <&'a T as IntoIterator>::Item;
+    type IntoIter = <&'a T as IntoIterator>::IntoIter;
+
+    /// Iterate over the value that this `Gc` points to by reference.
+    ///
+    /// The iterator borrows through the `Gc`, so it cannot outlive it.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the value that this `Gc` points to has already been collected,
+    /// just like dereferencing it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    ///
+    /// let gc = Gc::new(vec![1, 2, 3]);
+    /// let mut sum = 0;
+    /// for x in &gc {
+    ///     sum += x;
+    /// }
+    /// assert_eq!(sum, 6);
+    /// ```
+    fn into_iter(self) -> Self::IntoIter {
+        (**self).into_iter()
+    }
+}
+
+impl<T: Collectable + Sync> StaticGc<T> {
+    /// Construct a new statically-allocated garbage-collected value.
+    /// This function is intended for use in the initializer of a `static`.
+    pub const fn new(value: T) -> StaticGc<T> {
+        StaticGc {
+            gc_box: GcBox {
+                ref_count: Cell::new(usize::MAX),
+                weak: Cell::new(1),
+                value,
+            },
+        }
+    }
+
+    /// Get a garbage-collected handle to this value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::StaticGc;
+    ///
+    /// static ANSWER: StaticGc<u32> = StaticGc::new(42);
+    ///
+    /// assert_eq!(*ANSWER.get(), 42);
+    /// ```
+    pub fn get(&'static self) -> Gc<T> {
+        DUMPSTER.with(Dumpster::notify_created_gc);
+        Gc {
+            ptr: NonNull::from(&self.gc_box),
+        }
+    }
+}
+
+/// Static allocations are never written to after construction, since their reference count is
+/// pinned at `usize::MAX`, so they may be shared across threads whenever their contents can.
+unsafe impl<T: Collectable + Sync> Sync for StaticGc<T> {}
+
+impl<T: Collectable> GcBox<T> {
+    /// Allocate a new `GcBox` holding `value`, with one strong reference.
+    ///
+    /// The garbage collector is not notified of the new allocation.
+    fn allocate(value: T) -> NonNull<GcBox<T>> {
+        GcBox::init(allocate(Layout::new::<GcBox<T>>()), value)
+    }
+
+    /// Allocate a new `GcBox` holding `value` like [`GcBox::allocate`], reusing a freed allocation
+    /// from the free list of `dumpster` instead of looking up this thread's dumpster.
+    fn allocate_with(dumpster: &Dumpster, value: T) -> NonNull<GcBox<T>> {
+        GcBox::init(allocate_with(dumpster, Layout::new::<GcBox<T>>()), value)
+    }
+
+    /// Write a new `GcBox` holding `value`, with one strong reference, to the memory at `ptr`.
+    fn init(ptr: NonNull<u8>, value: T) -> NonNull<GcBox<T>> {
+        let ptr = ptr.cast::<GcBox<T>>();
+        unsafe {
+            ptr.as_ptr().write(GcBox {
+                ref_count: Cell::new(1),
+                weak: Cell::new(1),
+                value,
+            });
+        }
+        ptr
+    }
+}
+
+impl<T: Collectable + ?Sized> GcBox<T> {
+    /// Determine whether this allocation lives in static memory.
+    /// The reference count of a static allocation must never be modified.
+    fn is_static(&self) -> bool {
+        self.ref_count.get() == usize::MAX
+    }
+
+    /// Determine whether this allocation is a zombie, meaning that its value is in the process of
+    /// being dropped by the collector.
+    fn is_zombie(&self) -> bool {
+        self.ref_count.get() == 0
+    }
+
+    /// Determine whether this allocation was made by [`Gc::new_acyclic`], meaning that it can never
+    /// be part of a cycle.
+    fn is_acyclic(&self) -> bool {
+        self.weak.get() & ACYCLIC_FLAG != 0
+    }
+
+    /// Get the weak count of this allocation, without its flags.
+    fn weak_count(&self) -> usize {
+        self.weak.get() & !WEAK_FLAGS
+    }
+
+    /// Set the weak count of this allocation, keeping its flags.
+    ///
+    /// `count` must not reach into the flags; [`GcBox::add_weak`] checks this for new `Weak`s.
+    fn set_weak_count(&self, count: usize) {
+        debug_assert_eq!(
+            count & WEAK_FLAGS,
+            0,
+            "weak count overflowed into its flags"
+        );
+        self.weak.set(count | (self.weak.get() & WEAK_FLAGS));
+    }
+
+    /// Add `n` strong references to the reference count of this allocation, unless it is static.
+    ///
+    /// Like `Rc`, this aborts the process if the count would overflow, since the allocation could
+    /// otherwise be freed while references to it remain.
+    fn add_strong(&self, n: usize) {
+        if self.is_static() {
+            return;
+        }
+        match checked_increment(self.ref_count.get(), n) {
+            Some(count) => self.ref_count.set(count),
+            None => ref_count_overflow(),
+        }
+    }
+
+    /// Add one weak reference to the weak count of this allocation, unless it is static.
+    ///
+    /// Like [`GcBox::add_strong`], this aborts the process if the count would overflow into the
+    /// flags, since the allocation could otherwise be freed while `Weak`s to it remain.
+    fn add_weak(&self) {
+        if self.is_static() {
+            return;
+        }
+        match checked_weak_increment(self.weak_count()) {
+            Some(count) => self.set_weak_count(count),
+            None => ref_count_overflow(),
+        }
+    }
+
+    /// Release one weak reference to an allocation, deallocating it if that was the last weak
+    /// reference.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to a valid allocation, and the weak reference being released must not be
+    /// used again.
+    /// If this is the last weak reference, the value in the allocation must already be dropped.
+    unsafe fn release_weak(ptr: NonNull<GcBox<T>>) {
+        let box_ref = ptr.as_ref();
+        let weak = box_ref.weak_count() - 1;
+        box_ref.set_weak_count(weak);
+        if weak == 0 {
+            let _ = DUMPSTER.try_with(|d| d.notify_freed(ptr));
+            #[cfg(feature = "allocator_api")]
+            if box_ref.weak.get() & IN_ALLOCATOR_FLAG != 0 {
+                allocator::deallocate_in(ptr.cast(), Layout::for_value(box_ref));
+                return;
+            }
+            deallocate(ptr.cast(), Layout::for_value(box_ref));
+        }
+    }
+}
+
+/// Add `n` to a strong reference count, returning `None` if the result would overflow.
 ///
-/// set_collect_condition(never_collect);
-/// ```
-pub fn set_collect_condition(f: CollectCondition) {
-    DUMPSTER.with(|d| d.collect_condition.set(f));
+/// A count of `usize::MAX` marks a static allocation, so it is treated as an overflow too.
+fn checked_increment(count: usize, n: usize) -> Option<usize> {
+    count.checked_add(n).filter(|&count| count != usize::MAX)
 }
 
-#[repr(C)]
-/// The underlying heap allocation for a [`Gc`].
-struct GcBox<T: Collectable + ?Sized> {
-    /// The number of extant references to this garbage-collected data.
-    /// If the stored reference count is zero, then this value is a "zombie" - in the process of
-    /// being dropped - and should not be dropped again.
-    ref_count: Cell<NonZeroUsize>,
-    /// The stored value inside this garbage-collected box.
-    value: T,
+/// Add one to a weak count, returning `None` if the result would reach the flags in
+/// [`GcBox::weak`].
+fn checked_weak_increment(count: usize) -> Option<usize> {
+    Some(count + 1).filter(|&count| count & WEAK_FLAGS == 0)
 }
 
-impl<T: Collectable + ?Sized> Gc<T> {
-    /// Construct a new garbage-collected allocation, with `value` as its value.
-    pub fn new(value: T) -> Gc<T>
-    where
-        T: Sized,
+#[cold]
+/// Abort the process because a strong or weak reference count overflowed.
+fn ref_count_overflow() -> ! {
+    #[cfg(feature = "std")]
+    std::process::abort();
+
+    #[cfg(not(feature = "std"))]
     {
-        DUMPSTER.with(Dumpster::notify_created_gc);
-        Gc {
-            ptr: Box::leak(Box::new(GcBox {
-                ref_count: Cell::new(NonZeroUsize::MIN),
-                value,
-            }))
-            .into(),
+        /// A guard which panics again while unwinding, which always aborts the process.
+        struct Abort;
+
+        impl Drop for Abort {
+            fn drop(&mut self) {
+                panic!("`Gc` reference count overflow");
+            }
         }
+
+        let _abort = Abort;
+        panic!("`Gc` reference count overflow");
+    }
+}
+
+/// Allocate memory for a [`GcBox`] with the given layout, reusing a freed allocation from this
+/// thread's free list if one is available.
+fn allocate(layout: Layout) -> NonNull<u8> {
+    DUMPSTER
+        .try_with(|d| allocate_with(d, layout))
+        .unwrap_or_else(|_| allocate_fresh(layout))
+}
+
+/// Allocate memory for a [`GcBox`] with the given layout, reusing a freed allocation from the free
+/// list of `dumpster` if one is available.
+fn allocate_with(dumpster: &Dumpster, layout: Layout) -> NonNull<u8> {
+    dumpster
+        .reuse_allocation(layout)
+        .unwrap_or_else(|| allocate_fresh(layout))
+}
+
+/// Allocate new memory for a [`GcBox`] with the given layout.
+fn allocate_fresh(layout: Layout) -> NonNull<u8> {
+    NonNull::new(unsafe { alloc(layout) }).unwrap_or_else(|| handle_alloc_error(layout))
+}
+
+/// Free the memory of a [`GcBox`] with the given layout, or keep it in this thread's free list for
+/// later reuse.
+///
+/// # Safety
+///
+/// `ptr` must have been allocated by [`allocate`] with the same `layout`, and must not be used
+/// again.
+unsafe fn deallocate(ptr: NonNull<u8>, layout: Layout) {
+    let recycled = DUMPSTER
+        .try_with(|d| d.recycle_allocation(ptr, layout))
+        .unwrap_or(false);
+    if !recycled {
+        dealloc(ptr.as_ptr(), layout);
     }
 }
 
@@ -217,9 +2228,13 @@ impl<T: Collectable + ?Sized> Deref for Gc<T> {
     ///
     /// # Panics
     ///
-    /// This function may panic if it is called from within the implementation of `std::ops::Drop`
-    /// of its owning value, since returning such a reference could cause a use-after-free.
-    /// It is not guaranteed to panic.
+    /// This function will panic if the pointed-to value is being dropped by the garbage collector.
+    /// This can only happen from within the implementation of `std::ops::Drop` of a value which is
+    /// being collected, since returning such a reference could cause a use-after-free.
+    /// Use [`Gc::try_deref`] to get `None` instead.
+    /// The panic message names the type `T`, and with the `backtrace` feature enabled, it also
+    /// gives the [`AllocationId`] of the allocation so that it can be looked up with
+    /// `allocation_backtrace`.
     ///
     /// # Examples
     ///
@@ -236,8 +2251,8 @@ impl<T: Collectable + ?Sized> Deref for Gc<T> {
     ///
     /// ```should_panic
     /// // This is wrong!
-    /// use std::cell::RefCell;
     /// use dumpster::{unsync::Gc, Collectable};
+    /// use std::cell::RefCell;
     ///
     /// #[derive(Collectable)]
     /// struct Bad {
@@ -259,31 +2274,55 @@ impl<T: Collectable + ?Sized> Deref for Gc<T> {
     /// });
     /// ```
     fn deref(&self) -> &Self::Target {
-        assert!(
-            !COLLECTING.with(Cell::get),
-            "dereferencing GC to already-collected object"
-        );
-        unsafe { &self.ptr.as_ref().value }
+        let Some(value) = Gc::try_deref(self) else {
+            #[cfg(not(feature = "backtrace"))]
+            panic!(
+                "dereferencing GC to already-collected object of type `{}`",
+                type_name::<T>()
+            );
+            #[cfg(feature = "backtrace")]
+            panic!(
+                "dereferencing GC to already-collected object of type `{}` ({:?})",
+                type_name::<T>(),
+                AllocationId::from(self.ptr)
+            );
+        };
+        value
     }
 }
 
 impl<T: Collectable + ?Sized> Clone for Gc<T> {
-    #[allow(clippy::clone_on_copy)]
     /// Create a duplicate reference to the same data pointed to by `self`.
     /// This does not duplicate the data.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the pointed-to value is being dropped by the garbage collector,
+    /// which can only happen from within the implementation of `std::ops::Drop` of a value which
+    /// is being collected.
+    ///
+    /// # Aborts
+    ///
+    /// Like [`std::rc::Rc`], this function aborts the process if the number of `Gc`s pointing to
+    /// the allocation would overflow a `usize`.
+    /// This can only happen if `Gc`s are leaked with [`std::mem::forget`].
     fn clone(&self) -> Self {
-        unsafe {
-            let box_ref = self.ptr.as_ref();
-            box_ref
-                .ref_count
-                .set(box_ref.ref_count.get().saturating_add(1));
-        }
-        DUMPSTER.with(|d| {
-            d.notify_created_gc();
-            // d.mark_cleaned(self.ptr);
-        });
-        Self {
-            ptr: self.ptr.clone(),
+        DUMPSTER.with(|d| Gc::clone_with(self, d))
+    }
+
+    /// Make `self` point to the same allocation as `source`.
+    ///
+    /// If both already point to the same allocation, nothing happens.
+    /// Otherwise, `source` is cloned before the old value of `self` is dropped, just like an
+    /// assignment.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `self` and `source` point to different allocations and the value
+    /// pointed to by `source` is being dropped by the garbage collector.
+    fn clone_from(&mut self, source: &Self) {
+        if self.ptr.cast::<u8>() != source.ptr.cast::<u8>() {
+            *self = source.clone();
         }
     }
 }
@@ -294,38 +2333,73 @@ impl<T: Collectable + ?Sized> Drop for Gc<T> {
     /// If this is the last reference which can reach the pointed-to data, the allocation that it
     /// points to will be destroyed.
     fn drop(&mut self) {
-        if COLLECTING.with(Cell::get) {
-            return;
-        }
-        DUMPSTER.with(|d| {
-            let box_ref = unsafe { self.ptr.as_ref() };
-            match box_ref.ref_count.get() {
-                NonZeroUsize::MIN => {
+        // the dumpster may already be gone if this is called while the thread is exiting, so we
+        // must be able to cope without it
+        let box_ref = unsafe { self.ptr.as_ref() };
+        // a `Gc` which was never created on this thread (such as one which was fabricated from a
+        // pointer) can only be told apart from a real one by consulting the registry of
+        // allocations, which needs `debug-allocations`. without it, we can still catch it when
+        // this thread has no `Gc`s at all
+        debug_assert!(
+            box_ref.is_zombie()
+                || box_ref.is_static()
+                || DUMPSTER
+                    .try_with(|d| d.n_refs_living.get() > 0)
+                    .unwrap_or(true),
+            "dropped a `Gc<{}>` while no `Gc`s were living on this thread",
+            type_name::<T>()
+        );
+        #[cfg(feature = "debug-allocations")]
+        debug_assert!(
+            box_ref.is_zombie()
+                || box_ref.is_static()
+                || DUMPSTER
+                    .try_with(|d| d.is_allocated(self.ptr))
+                    .unwrap_or(true),
+            "dropped a `Gc<{}>` to an allocation which was never created by this thread",
+            type_name::<T>()
+        );
+        match box_ref.ref_count.get() {
+            0 => {
+                // zombie allocations are being dropped by the collector, which will take care
+                // of deallocating them.
+                // outside of a collection, this can only be a `Gc` which was dropped twice
+                debug_assert!(
+                    collect::COLLECTING.try_with(Cell::get).unwrap_or(true),
+                    "dropped a `Gc<{}>` to an allocation which was already freed",
+                    type_name::<T>()
+                );
+            }
+            usize::MAX => {
+                // static allocations are never freed or tracked
+            }
+            1 => {
+                // this was the last reference, drop unconditionally
+                box_ref.ref_count.set(0);
+                let _ = DUMPSTER.try_with(|d| {
                     d.mark_cleaned(self.ptr);
-                    unsafe {
-                        // this was the last reference, drop unconditionally
-                        drop_in_place(addr_of_mut!(self.ptr.as_mut().value));
-                        // note: `box_ref` is no longer usable
-                        dealloc(
-                            self.ptr.as_ptr().cast::<u8>(),
-                            Layout::for_value(self.ptr.as_ref()),
-                        );
-                    }
+                    #[cfg(feature = "debug-allocations")]
+                    d.notify_deallocated(self.ptr);
+                });
+                unsafe {
+                    drop_in_place(addr_of_mut!(self.ptr.as_mut().value));
+                    // note: `box_ref` may no longer be usable after this
+                    GcBox::release_weak(self.ptr);
                 }
-                n => {
-                    // decrement the ref count - but another reference to this data still
-                    // lives
-                    box_ref
-                        .ref_count
-                        .set(NonZeroUsize::new(n.get() - 1).unwrap());
-                    // remaining references could be a cycle - therefore, mark it as dirty
-                    // so we can check later
-                    d.mark_dirty(self.ptr);
+                let _ = DUMPSTER.try_with(|d| d.notify_finalized(self.ptr));
+            }
+            n => {
+                // decrement the ref count - but another reference to this data still lives
+                box_ref.ref_count.set(n - 1);
+                // remaining references could be a cycle - therefore, mark it as dirty so we can
+                // check later
+                if !box_ref.is_acyclic() {
+                    let _ = DUMPSTER.try_with(|d| d.mark_dirty(self.ptr));
                 }
             }
-            // Notify that a GC has been dropped, potentially triggering a cleanup
-            d.notify_dropped_gc();
-        });
+        }
+        // Notify that a GC has been dropped, potentially triggering a cleanup
+        let _ = DUMPSTER.try_with(Dumpster::notify_dropped_gc);
     }
 }
 
@@ -368,6 +2442,103 @@ impl CollectInfo {
     pub fn n_gcs_existing(&self) -> usize {
         DUMPSTER.with(|d| d.n_refs_living.get())
     }
+
+    #[must_use]
+    /// Get the number of [`Gc`]s which have been created, by construction or by cloning, since the
+    /// last time a collection operation was performed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::{set_collect_condition, CollectInfo};
+    ///
+    /// // Collection condition for whether the heap is churning: many Gc's have been both created
+    /// // and dropped.
+    /// fn is_heap_churning(info: &CollectInfo) -> bool {
+    ///     info.n_gcs_created_since_last_collect() > 100
+    ///         && info.n_gcs_dropped_since_last_collect() > 100
+    /// }
+    ///
+    /// set_collect_condition(is_heap_churning);
+    /// ```
+    pub fn n_gcs_created_since_last_collect(&self) -> usize {
+        DUMPSTER.with(|d| d.n_refs_created.get())
+    }
+
+    #[cfg(feature = "std")]
+    #[must_use]
+    /// Get how long the most recent collection took, or zero if there has not been one yet.
+    ///
+    /// This function is only available with the `std` feature enabled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::{default_collect_condition, set_collect_condition, CollectInfo};
+    /// use std::time::Duration;
+    ///
+    /// // Collection condition which collects less eagerly if the last collection was slow.
+    /// fn back_off_when_slow(info: &CollectInfo) -> bool {
+    ///     if info.last_collect_duration() > Duration::from_millis(10) {
+    ///         info.n_gcs_dropped_since_last_collect() > 4 * info.n_gcs_existing()
+    ///     } else {
+    ///         default_collect_condition(info)
+    ///     }
+    /// }
+    ///
+    /// set_collect_condition(back_off_when_slow);
+    /// ```
+    pub fn last_collect_duration(&self) -> Duration {
+        DUMPSTER.with(|d| d.last_collect_duration.get())
+    }
+
+    #[must_use]
+    /// Get the number of allocations which the garbage collector is currently tracking as possible
+    /// garbage.
+    ///
+    /// An allocation becomes tracked when a `Gc` to it is dropped without freeing it, since it may
+    /// have become part of an unreachable cycle.
+    /// Every tracked allocation is examined (and untracked) by the next collection.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::{set_collect_condition, CollectInfo};
+    ///
+    /// // Collection condition for whether many allocations might be garbage.
+    /// fn are_many_tracked(info: &CollectInfo) -> bool {
+    ///     info.n_allocations_tracked() > 1000
+    /// }
+    ///
+    /// set_collect_condition(are_many_tracked);
+    /// ```
+    pub fn n_allocations_tracked(&self) -> usize {
+        DUMPSTER.with(Dumpster::n_dirty)
+    }
+
+    #[must_use]
+    /// Get the approximate total size, in bytes, of the allocations which the garbage collector is
+    /// currently tracking as possible garbage.
+    ///
+    /// This counts the size of each tracked allocation, including its reference counts, but not
+    /// any memory which its value owns indirectly (such as the buffer of a `Vec`).
+    /// See [`CollectInfo::n_allocations_tracked`] for which allocations are tracked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::{set_collect_condition, CollectInfo};
+    ///
+    /// // Collection condition for whether a megabyte of allocations might be garbage.
+    /// fn is_much_memory_tracked(info: &CollectInfo) -> bool {
+    ///     info.approximate_bytes_tracked() > 1 << 20
+    /// }
+    ///
+    /// set_collect_condition(is_much_memory_tracked);
+    /// ```
+    pub fn approximate_bytes_tracked(&self) -> usize {
+        DUMPSTER.with(Dumpster::n_bytes_dirty)
+    }
 }
 
 unsafe impl<T: Collectable + ?Sized> Collectable for Gc<T> {
@@ -396,16 +2567,289 @@ impl<T: Collectable + Default> Default for Gc<T> {
     }
 }
 
-impl<T: Collectable + ?Sized> std::fmt::Pointer for Gc<T> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        std::fmt::Pointer::fmt(&addr_of!(**self), f)
+impl<T: Collectable> From<T> for Gc<T> {
+    /// Move a value into a new garbage-collected allocation.
+    ///
+    /// This is equivalent to calling [`Gc::new`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    ///
+    /// let gc: Gc<String> = "hi".to_string().into();
+    /// assert_eq!(*gc, "hi");
+    /// ```
+    fn from(value: T) -> Self {
+        Gc::new(value)
+    }
+}
+
+impl<T: Collectable> From<Box<T>> for Gc<T> {
+    /// Move a boxed value into a new garbage-collected allocation.
+    ///
+    /// The value is moved out of the box and the box's allocation is freed, since a `Gc`'s
+    /// allocation also holds its reference count and so cannot reuse the box's memory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    ///
+    /// let gc: Gc<i32> = Gc::from(Box::new(5));
+    /// assert_eq!(*gc, 5);
+    /// ```
+    fn from(value: Box<T>) -> Self {
+        Gc::new(*value)
+    }
+}
+
+impl<T: Collectable + PartialEq + ?Sized> PartialEq for Gc<T> {
+    /// Determine whether the values that two `Gc`s point to are equal.
+    ///
+    /// Two `Gc`s to the same allocation are always equal, without comparing their values.
+    /// This means that a `Gc` is equal to itself even if its value is not equal to itself (such as
+    /// `f64::NAN`).
+    ///
+    /// While the garbage collector is running (such as from within the `Drop` implementation of a
+    /// collected value), the values are never inspected, and two `Gc`s are equal only if they
+    /// point to the same allocation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    ///
+    /// let gc1 = Gc::new(1);
+    /// let gc2 = Gc::new(1);
+    /// assert!(gc1 == gc2);
+    /// assert!(gc1 != Gc::new(2));
+    /// ```
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr.as_ptr().cast::<()>() == other.ptr.as_ptr().cast::<()>()
+            || (!collect::COLLECTING.with(Cell::get) && **self == **other)
+    }
+}
+
+impl<T: Collectable + Eq + ?Sized> Eq for Gc<T> {}
+
+impl<T: Collectable + PartialEq + ?Sized> PartialEq<T> for Gc<T> {
+    /// Determine whether the value that this `Gc` points to is equal to `other`.
+    ///
+    /// Unlike comparing two `Gc`s, this always inspects the value, even while the garbage
+    /// collector is running.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the pointed-to value has already been dropped by the garbage
+    /// collector, just like [`Deref::deref`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    ///
+    /// assert!(Gc::new(5) == 5);
+    /// ```
+    fn eq(&self, other: &T) -> bool {
+        **self == *other
+    }
+}
+
+impl<T: Collectable + PartialOrd + ?Sized> PartialOrd for Gc<T> {
+    /// Compare the values that two `Gc`s point to.
+    ///
+    /// To agree with [`PartialEq`], two `Gc`s to the same allocation are always equal, without
+    /// comparing their values.
+    /// While the garbage collector is running, the values are never inspected, and `Gc`s are
+    /// instead ordered by the address of the allocation they point to, just like [`Ord::cmp`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    /// use std::cmp::Ordering;
+    ///
+    /// let nan = Gc::new(f64::NAN);
+    /// assert_eq!(nan.partial_cmp(&nan), Some(Ordering::Equal));
+    /// assert_eq!(nan.partial_cmp(&Gc::new(f64::NAN)), None);
+    /// assert!(Gc::new(1.0) < Gc::new(2.0));
+    /// ```
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        if self.ptr.as_ptr().cast::<()>() == other.ptr.as_ptr().cast::<()>()
+            || collect::COLLECTING.with(Cell::get)
+        {
+            Some(Gc::addr_cmp(self, other))
+        } else {
+            (**self).partial_cmp(&**other)
+        }
+    }
+}
+
+impl<T: Collectable + PartialOrd + ?Sized> PartialOrd<T> for Gc<T> {
+    /// Compare the value that this `Gc` points to with `other`.
+    ///
+    /// Like comparing a `Gc` with a `T` for equality, this always inspects the value.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the pointed-to value has already been dropped by the garbage
+    /// collector, just like [`Deref::deref`].
+    fn partial_cmp(&self, other: &T) -> Option<core::cmp::Ordering> {
+        (**self).partial_cmp(other)
+    }
+}
+
+impl<T: Collectable + Ord + ?Sized> Ord for Gc<T> {
+    /// Compare the values that two `Gc`s point to.
+    ///
+    /// Two `Gc`s to the same allocation are always equal, without comparing their values.
+    /// While the garbage collector is running, the values are never inspected, and `Gc`s are
+    /// instead ordered by the address of the allocation they point to, which agrees with
+    /// [`PartialEq`] comparing them by identity at that time.
+    /// Like any order which depends on addresses, this may not agree with the order of the same
+    /// `Gc`s outside of a collection.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    /// use std::cmp::Ordering;
+    ///
+    /// let gc = Gc::new(1);
+    /// assert_eq!(gc.cmp(&gc), Ordering::Equal);
+    /// assert_eq!(gc.cmp(&Gc::new(2)), Ordering::Less);
+    /// ```
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        if self.ptr.as_ptr().cast::<()>() == other.ptr.as_ptr().cast::<()>()
+            || collect::COLLECTING.with(Cell::get)
+        {
+            Gc::addr_cmp(self, other)
+        } else {
+            (**self).cmp(&**other)
+        }
+    }
+}
+
+impl<T: Collectable + Hash + ?Sized> Hash for Gc<T> {
+    /// Hash the value that this `Gc` points to.
+    ///
+    /// Like [`PartialEq`], hashing a `Gc` only depends on its value, not on which allocation it
+    /// points to, so `Gc`s can be used as keys in a map just like the values they contain.
+    /// To hash and compare `Gc`s by which allocation they point to instead, wrap them in a
+    /// [`ByAddress`].
+    ///
+    /// The value is inspected even while the garbage collector is running.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the pointed-to value has already been dropped by the garbage
+    /// collector, just like [`Deref::deref`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    /// use std::collections::HashSet;
+    ///
+    /// let mut set = HashSet::new();
+    /// set.insert(Gc::new(1));
+    /// assert!(set.contains(&Gc::new(1)));
+    /// ```
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        (**self).hash(state);
+    }
+}
+
+/// Implement comparisons between a primitive type and a `Gc` pointing to that same type, so that
+/// the value may appear on either side of the operator.
+macro_rules! reflexive_cmp_impl {
+    ($($t: ty),*) => {
+        $(
+            impl PartialEq<Gc<$t>> for $t {
+                fn eq(&self, other: &Gc<$t>) -> bool {
+                    *self == **other
+                }
+            }
+
+            impl PartialOrd<Gc<$t>> for $t {
+                fn partial_cmp(&self, other: &Gc<$t>) -> Option<core::cmp::Ordering> {
+                    self.partial_cmp(&**other)
+                }
+            }
+        )*
+    };
+}
+
+reflexive_cmp_impl!(
+    i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64, bool, char, String
+);
+
+impl<T: Collectable + ?Sized> core::fmt::Pointer for Gc<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Pointer::fmt(&addr_of!(**self), f)
+    }
+}
+
+impl<T: Collectable + core::fmt::Debug + ?Sized> core::fmt::Debug for Gc<T> {
+    /// Format the value that this `Gc` points to.
+    ///
+    /// If the value has already been collected (which can only be observed from within the
+    /// implementation of `std::ops::Drop` of a value being collected), this formats the address of
+    /// the allocation instead.
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if unsafe { self.ptr.as_ref() }.is_zombie() {
+            write!(f, "Gc({:?}, collected)", self.ptr)
+        } else {
+            core::fmt::Debug::fmt(&**self, f)
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: Collectable + serde::Serialize + ?Sized> serde::Serialize for Gc<T> {
+    /// Serialize the value that this `Gc` points to, as if the `Gc` were not there.
+    ///
+    /// Every `Gc` is serialized separately, so a value reachable through several `Gc`s is written
+    /// once for each of them, and serializing a cycle of `Gc`s never terminates.
+    /// Only acyclic graphs can be serialized; shared allocations are duplicated when they are
+    /// deserialized.
+    ///
+    /// This implementation is only available with the `serde` feature enabled.
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (**self).serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: Collectable + serde::Deserialize<'de>> serde::Deserialize<'de> for Gc<T> {
+    /// Deserialize a value and move it into a new allocation.
+    ///
+    /// This implementation is only available with the `serde` feature enabled.
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Gc::new)
     }
 }
 
+/// Implement formatting traits for `Gc` by forwarding them to the value it points to.
+macro_rules! fmt_forward_impl {
+    ($($tr: ident),*) => {
+        $(
+            impl<T: Collectable + core::fmt::$tr + ?Sized> core::fmt::$tr for Gc<T> {
+                fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                    core::fmt::$tr::fmt(&**self, f)
+                }
+            }
+        )*
+    };
+}
+
+fmt_forward_impl!(Display, LowerHex, UpperHex, Binary, Octal, LowerExp, UpperExp);
+
 #[cfg(feature = "coerce-unsized")]
-impl<T, U> std::ops::CoerceUnsized<Gc<U>> for Gc<T>
+impl<T, U> core::ops::CoerceUnsized<Gc<U>> for Gc<T>
 where
-    T: std::marker::Unsize<U> + Collectable + ?Sized,
+    T: core::marker::Unsize<U> + Collectable + ?Sized,
     U: Collectable + ?Sized,
 {
 }
@@ -0,0 +1,173 @@
+/*
+   dumpster, a cycle-tracking garbage collector for Rust.
+   Copyright (C) 2023 Clayton Ramsey.
+
+   This program is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   This program is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Garbage-collected pointers to a part of a larger garbage-collected value.
+
+use core::{ops::Deref, ptr::NonNull};
+
+use crate::{Collectable, Visitor};
+
+use super::Gc;
+
+/// A pointer to a part of the value inside a garbage-collected allocation of type `T`, such as
+/// one of its fields, which keeps the whole allocation alive.
+///
+/// A `GcProjection` owns a [`Gc`] to the whole allocation and dereferences to the projected part,
+/// of type `U`.
+/// The type of the whole allocation is kept as a type parameter so that the garbage collector can
+/// trace through the owning `Gc`: a `GcProjection` stored inside a cycle is collected along with
+/// the rest of the cycle.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::unsync::Gc;
+///
+/// let gc = Gc::new((String::from("name"), vec![1u8, 2, 3]));
+/// let (name, bytes) = Gc::split2(&gc);
+/// drop(gc);
+///
+/// assert_eq!(*name, "name");
+/// assert_eq!(bytes.len(), 3);
+/// ```
+pub struct GcProjection<T: Collectable + ?Sized + 'static, U: ?Sized> {
+    /// A pointer to the whole allocation, which keeps it alive.
+    owner: Gc<T>,
+    /// A pointer to the projected part of the value inside `owner`.
+    ptr: NonNull<U>,
+}
+
+impl<T: Collectable + ?Sized, U: ?Sized> GcProjection<T, U> {
+    #[must_use]
+    /// Get the `Gc` to the whole allocation which `this` is a part of.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::{Gc, GcProjection};
+    ///
+    /// let gc = Gc::new((1, 2));
+    /// let (first, _) = Gc::split2(&gc);
+    /// assert_eq!(*GcProjection::owner(&first), (1, 2));
+    /// ```
+    pub fn owner(this: &GcProjection<T, U>) -> &Gc<T> {
+        &this.owner
+    }
+}
+
+impl<T: Collectable + ?Sized> Gc<T> {
+    /// Turn `this` into a projection to the part of its value selected by `f`, such as one of its
+    /// fields.
+    ///
+    /// The projection takes over the reference held by `this`, so the whole allocation stays alive
+    /// for as long as the projection does.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the value has already been collected, which can only happen
+    /// from within the implementation of `std::ops::Drop` of a value being collected.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::{
+    ///     unsync::{Gc, GcProjection},
+    ///     Collectable,
+    /// };
+    ///
+    /// #[derive(Collectable)]
+    /// struct Point {
+    ///     x: u32,
+    ///     y: u32,
+    /// }
+    ///
+    /// let point = Gc::new(Point { x: 1, y: 2 });
+    /// let y = Gc::map(point, |point| &point.y);
+    /// assert_eq!(*y, 2);
+    /// assert_eq!(GcProjection::owner(&y).x, 1);
+    /// ```
+    pub fn map<U: ?Sized>(this: Gc<T>, f: impl FnOnce(&T) -> &U) -> GcProjection<T, U> {
+        let ptr = NonNull::from(f(&this));
+        GcProjection { owner: this, ptr }
+    }
+}
+
+impl<A: Collectable, B: Collectable> Gc<(A, B)> {
+    #[must_use]
+    #[allow(clippy::type_complexity)]
+    /// Split a `Gc` to a pair into a projection of each of its elements.
+    ///
+    /// Both projections share the allocation of `this`, each holding a new reference to it, so the
+    /// allocation stays alive until `this` and both projections are gone.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    ///
+    /// let gc = Gc::new((1u8, 'a'));
+    /// let (number, letter) = Gc::split2(&gc);
+    /// assert_eq!(Gc::strong_count(&gc), 3);
+    /// assert_eq!((*number, *letter), (1, 'a'));
+    /// ```
+    pub fn split2(this: &Gc<(A, B)>) -> (GcProjection<(A, B), A>, GcProjection<(A, B), B>) {
+        let first = GcProjection {
+            owner: this.clone(),
+            ptr: NonNull::from(&this.0),
+        };
+        let second = GcProjection {
+            owner: this.clone(),
+            ptr: NonNull::from(&this.1),
+        };
+        (first, second)
+    }
+}
+
+impl<T: Collectable + ?Sized, U: ?Sized> Deref for GcProjection<T, U> {
+    type Target = U;
+
+    /// Dereference this projection, creating a reference to the projected part of the value.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the value has already been collected, which can only happen
+    /// from within the implementation of `std::ops::Drop` of a value being collected.
+    fn deref(&self) -> &U {
+        // dereferencing the owner checks that the value has not been collected
+        let _: &T = &self.owner;
+        // SAFETY: `ptr` points into the value of `owner`, which is alive and never moves.
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T: Collectable + ?Sized, U: ?Sized> Clone for GcProjection<T, U> {
+    /// Create another projection to the same part of the same allocation.
+    fn clone(&self) -> Self {
+        GcProjection {
+            owner: self.owner.clone(),
+            ptr: self.ptr,
+        }
+    }
+}
+
+unsafe impl<T: Collectable + ?Sized, U: ?Sized> Collectable for GcProjection<T, U> {
+    #[inline]
+    fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+        self.owner.accept(visitor)
+    }
+}
@@ -0,0 +1,190 @@
+/*
+   dumpster, a cycle-tracking garbage collector for Rust.
+   Copyright (C) 2023 Clayton Ramsey.
+
+   This program is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   This program is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Construction of garbage-collected slices and strings.
+
+use core::{
+    alloc::Layout,
+    cell::Cell,
+    ptr::{self, addr_of_mut, NonNull},
+};
+
+use crate::Collectable;
+
+use super::{allocate, deallocate, Gc, GcBox, DUMPSTER};
+
+impl<T: Collectable + Clone + 'static> Gc<[T]> {
+    #[must_use]
+    /// Construct a new garbage-collected slice containing clones of the elements of `slice`.
+    ///
+    /// The elements are stored directly in the allocation, with no separate buffer, so this works
+    /// on stable Rust without the `coerce-unsized` feature.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the size of the allocation would overflow `isize`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    ///
+    /// let gc: Gc<[u32]> = Gc::from_slice(&[1, 2, 3]);
+    /// assert_eq!(*gc, [1, 2, 3]);
+    /// ```
+    pub fn from_slice(slice: &[T]) -> Gc<[T]> {
+        let ptr = allocate_slice(slice);
+        DUMPSTER.with(|d| {
+            d.notify_created_gc();
+            #[cfg(any(feature = "debug-allocations", feature = "track-allocations"))]
+            d.notify_allocated(ptr);
+        });
+        Gc { ptr }
+    }
+}
+
+impl<T: Collectable + 'static> Gc<[T]> {
+    #[must_use]
+    /// Get a mutable reference to the elements of the slice that `this` points to, if `this` is
+    /// the only reference to its allocation.
+    ///
+    /// This is the same as [`Gc::get_mut`], and returns `None` in the same cases: if any other
+    /// `Gc` or [`Weak`](super::Weak) points to the same allocation, or while the garbage collector
+    /// is running.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    ///
+    /// let mut gc: Gc<[u32]> = Gc::from_slice(&[1, 2, 3]);
+    /// Gc::get_mut_slice(&mut gc).unwrap()[1] = 5;
+    /// assert_eq!(*gc, [1, 5, 3]);
+    /// ```
+    pub fn get_mut_slice(this: &mut Gc<[T]>) -> Option<&mut [T]> {
+        Gc::get_mut(this)
+    }
+}
+
+/// Allocate a [`GcBox`] containing clones of the elements of `slice`, with a reference count of 1.
+///
+/// The allocation is not registered with the dumpster.
+///
+/// # Panics
+///
+/// This function will panic if the size of the allocation would overflow `isize`.
+fn allocate_slice<T: Collectable + Clone>(slice: &[T]) -> NonNull<GcBox<[T]>> {
+    let (layout, _) = Layout::new::<GcBox<()>>()
+        .extend(Layout::array::<T>(slice.len()).expect("slice is too large"))
+        .expect("slice is too large");
+    let layout = layout.pad_to_align();
+    let mem = allocate(layout);
+    let ptr =
+        ptr::slice_from_raw_parts_mut(mem.as_ptr().cast::<T>(), slice.len()) as *mut GcBox<[T]>;
+
+    unsafe {
+        addr_of_mut!((*ptr).ref_count).write(Cell::new(1));
+        addr_of_mut!((*ptr).weak).write(Cell::new(1));
+
+        // if a clone panics, drop the elements written so far and free the allocation
+        let mut guard = PartialSlice {
+            mem,
+            layout,
+            elems: addr_of_mut!((*ptr).value).cast::<T>(),
+            n_init: 0,
+        };
+        for elem in slice {
+            guard.elems.add(guard.n_init).write(elem.clone());
+            guard.n_init += 1;
+        }
+        core::mem::forget(guard);
+    }
+
+    unsafe { NonNull::new_unchecked(ptr) }
+}
+
+impl Gc<str> {
+    #[must_use]
+    #[allow(clippy::should_implement_trait)]
+    /// Construct a new garbage-collected string containing a copy of `s`.
+    ///
+    /// Like [`Gc::from_slice`], the string is stored directly in the allocation.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the size of the allocation would overflow `isize`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    ///
+    /// let gc: Gc<str> = Gc::from_str("hello");
+    /// assert_eq!(gc.len(), 5);
+    /// assert_eq!(&*gc, "hello");
+    /// ```
+    pub fn from_str(s: &str) -> Gc<str> {
+        // `str` has the same layout as `[u8]`, and the bytes came from a `str`, so they are valid
+        // UTF-8
+        let ptr = unsafe {
+            NonNull::new_unchecked(allocate_slice(s.as_bytes()).as_ptr() as *mut GcBox<str>)
+        };
+        DUMPSTER.with(|d| {
+            d.notify_created_gc();
+            #[cfg(any(feature = "debug-allocations", feature = "track-allocations"))]
+            d.notify_allocated(ptr);
+        });
+        Gc { ptr }
+    }
+}
+
+impl<T: Collectable + Clone + 'static> From<&[T]> for Gc<[T]> {
+    /// Construct a new garbage-collected slice with [`Gc::from_slice`].
+    fn from(slice: &[T]) -> Gc<[T]> {
+        Gc::from_slice(slice)
+    }
+}
+
+impl From<&str> for Gc<str> {
+    /// Construct a new garbage-collected string with [`Gc::from_str`].
+    fn from(s: &str) -> Gc<str> {
+        Gc::from_str(s)
+    }
+}
+
+/// A slice allocation which is partway through being initialized.
+/// Dropping it drops the initialized elements and frees the allocation.
+struct PartialSlice<T> {
+    /// The start of the allocation.
+    mem: NonNull<u8>,
+    /// The layout of the allocation.
+    layout: Layout,
+    /// The start of the elements of the slice.
+    elems: *mut T,
+    /// The number of elements which have been initialized.
+    n_init: usize,
+}
+
+impl<T> Drop for PartialSlice<T> {
+    fn drop(&mut self) {
+        unsafe {
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(self.elems, self.n_init));
+            deallocate(self.mem, self.layout);
+        }
+    }
+}
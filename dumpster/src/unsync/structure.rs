@@ -0,0 +1,547 @@
+/*
+   dumpster, a cycle-tracking garbage collector for Rust.
+   Copyright (C) 2023 Clayton Ramsey.
+
+   This program is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   This program is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Structural comparisons and traversals of garbage-collected graphs.
+
+use alloc::{boxed::Box, collections::VecDeque, vec, vec::Vec};
+#[cfg(feature = "std")]
+use core::hash::{Hash, Hasher};
+use core::{
+    any::{Any, TypeId},
+    cell::Cell,
+    mem::ManuallyDrop,
+    ptr::NonNull,
+};
+
+#[cfg(feature = "std")]
+use std::collections::hash_map::DefaultHasher;
+
+use crate::{
+    map::{Entry, Map, Set},
+    Collectable, ErasedPtr, Visitor,
+};
+
+use super::{collect::COLLECTING, AllocationId, Gc, GcBox};
+
+impl<T: Collectable + ?Sized> Gc<T> {
+    /// Determine whether the graphs of allocations reachable from `a` and `b` have the same shape,
+    /// giving up after examining `budget` pairs of allocations.
+    ///
+    /// Two graphs have the same shape if there is a one-to-one correspondence between their
+    /// allocations such that corresponding allocations contain values of the same type, and
+    /// visiting their contents yields corresponding `Gc`s in the same order.
+    /// Cycles are handled correctly.
+    /// The values inside the allocations are not compared, only the structure of the references
+    /// between them.
+    ///
+    /// Returns `Some(true)` if the graphs have the same shape, `Some(false)` if they do not, and
+    /// `None` if the comparison could not be decided within `budget` steps.
+    /// `None` is also returned if an allocation could not be inspected (for instance, because a
+    /// `RefCell` inside it was mutably borrowed).
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if it is called while a collection is running, such as from the
+    /// `Drop` implementation of a garbage-collected value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::{unsync::Gc, Collectable};
+    /// use std::cell::RefCell;
+    ///
+    /// #[derive(Collectable)]
+    /// struct Node(RefCell<Vec<Gc<Node>>>);
+    ///
+    /// let a = Gc::new(Node(RefCell::new(Vec::new())));
+    /// a.0.borrow_mut().push(a.clone());
+    ///
+    /// let b = Gc::new(Node(RefCell::new(Vec::new())));
+    /// let c = Gc::new(Node(RefCell::new(vec![b.clone()])));
+    /// b.0.borrow_mut().push(c.clone());
+    ///
+    /// // `a` is a one-cycle, while `b` is part of a two-cycle
+    /// assert_eq!(Gc::structural_eq_bounded(&a, &b, 100), Some(false));
+    /// assert_eq!(Gc::structural_eq_bounded(&b, &c, 100), Some(true));
+    /// ```
+    pub fn structural_eq_bounded(a: &Gc<T>, b: &Gc<T>, budget: usize) -> Option<bool> {
+        assert!(
+            !COLLECTING.with(Cell::get),
+            "structure may not be inspected while collecting"
+        );
+        if a.ptr.cast::<()>() == b.ptr.cast::<()>() {
+            return Some(true);
+        }
+
+        let mut budget = budget;
+        let mut a_to_b = Map::from([(a.ptr.cast::<()>(), b.ptr.cast::<()>())]);
+        let mut b_to_a = Map::from([(b.ptr.cast::<()>(), a.ptr.cast::<()>())]);
+        let mut queue = VecDeque::from([(Node::new(a), Node::new(b))]);
+
+        while let Some((x, y)) = queue.pop_front() {
+            if budget == 0 {
+                return None;
+            }
+            budget -= 1;
+
+            let x_children = x.children()?;
+            let y_children = y.children()?;
+            if x_children.len() != y_children.len() {
+                return Some(false);
+            }
+
+            for (p, q) in x_children.into_iter().zip(y_children) {
+                if p.type_id != q.type_id {
+                    return Some(false);
+                }
+                match (a_to_b.entry(p.id), b_to_a.entry(q.id)) {
+                    (Entry::Vacant(ab), Entry::Vacant(ba)) => {
+                        ab.insert(q.id);
+                        ba.insert(p.id);
+                        queue.push_back((p, q));
+                    }
+                    (Entry::Occupied(ab), Entry::Occupied(ba))
+                        if *ab.get() == q.id && *ba.get() == p.id => {}
+                    _ => return Some(false),
+                }
+            }
+        }
+
+        Some(true)
+    }
+
+    #[cfg(feature = "std")]
+    #[must_use]
+    /// Hash the graph of allocations reachable from `root`, including the values in them.
+    ///
+    /// This is the hashing counterpart to [`Gc::structural_eq_bounded`]: graphs which have the
+    /// same shape and hold equal values always have the same structural hash.
+    /// The hash covers the type of the value in each allocation and which allocations it refers
+    /// to, in visitation order.
+    /// The value in every allocation which holds a `T`, including `root` itself, is hashed with
+    /// its own [`Hash`] implementation; the values of any other type are only hashed by their
+    /// type.
+    /// Allocations are identified by the order in which they are first reached rather than by
+    /// their addresses, so cycles are handled correctly and separately-built copies of a graph
+    /// hash the same.
+    /// An allocation whose contents cannot be visited (for instance, because a `RefCell` inside it
+    /// is mutably borrowed) is hashed as if it had no references, but distinctly from an
+    /// allocation which really has none.
+    ///
+    /// The hash depends on [`TypeId`]s, so it is only stable within a single build of a program.
+    ///
+    /// This function is only available with the `std` feature enabled.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if it is called while a collection is running, such as from the
+    /// `Drop` implementation of a garbage-collected value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::{unsync::Gc, Collectable};
+    /// use std::{
+    ///     cell::RefCell,
+    ///     hash::{Hash, Hasher},
+    /// };
+    ///
+    /// #[derive(Collectable)]
+    /// struct Node {
+    ///     label: u8,
+    ///     edges: RefCell<Vec<Gc<Node>>>,
+    /// }
+    ///
+    /// impl Hash for Node {
+    ///     fn hash<H: Hasher>(&self, state: &mut H) {
+    ///         self.label.hash(state);
+    ///     }
+    /// }
+    ///
+    /// let node = |label| {
+    ///     let gc = Gc::new(Node {
+    ///         label,
+    ///         edges: RefCell::new(Vec::new()),
+    ///     });
+    ///     gc.edges.borrow_mut().push(gc.clone());
+    ///     gc
+    /// };
+    ///
+    /// assert_eq!(Gc::structural_hash(&node(1)), Gc::structural_hash(&node(1)));
+    /// assert_ne!(Gc::structural_hash(&node(1)), Gc::structural_hash(&node(2)));
+    /// ```
+    pub fn structural_hash(root: &Gc<T>) -> u64
+    where
+        T: Hash,
+    {
+        assert!(
+            !COLLECTING.with(Cell::get),
+            "structure may not be inspected while collecting"
+        );
+        let mut hasher = DefaultHasher::new();
+        let mut indices = Map::from([(root.ptr.cast::<()>(), 0usize)]);
+        let mut queue = VecDeque::from([Node::new(root)]);
+
+        while let Some(node) = queue.pop_front() {
+            node.type_id.hash(&mut hasher);
+            if node.type_id == TypeId::of::<T>() {
+                // like visiting its children, this relies on the allocation staying reachable from
+                // `root` for the whole traversal
+                unsafe { node.ptr.specify::<GcBox<T>>().as_ref() }
+                    .value
+                    .hash(&mut hasher);
+            }
+            let Some(children) = node.children() else {
+                usize::MAX.hash(&mut hasher);
+                continue;
+            };
+            children.len().hash(&mut hasher);
+            for child in children {
+                let next = indices.len();
+                let index = *indices.entry(child.id).or_insert_with(|| {
+                    queue.push_back(child);
+                    next
+                });
+                index.hash(&mut hasher);
+            }
+        }
+
+        hasher.finish()
+    }
+
+    /// Fold over every allocation reachable from `root`, including `root` itself.
+    ///
+    /// Each allocation is visited exactly once, even if it is reachable through many `Gc`s or is
+    /// part of a cycle, in breadth-first order starting from `root`.
+    /// For each allocation, `f` is called with the accumulator, the value in the allocation, and
+    /// the address of the allocation, which is the same for every `Gc` pointing to it.
+    /// The value is type-erased; use [`ErasedValue::downcast_ref`] to inspect it.
+    ///
+    /// Every visited allocation is kept alive until the fold is over.
+    /// The contents of an allocation which cannot be visited (for instance, because a `RefCell`
+    /// inside it is mutably borrowed) are skipped.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if it is called while a collection is running, such as from the
+    /// `Drop` implementation of a garbage-collected value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::{unsync::Gc, Collectable};
+    /// use std::cell::RefCell;
+    ///
+    /// #[derive(Collectable)]
+    /// struct Node {
+    ///     weight: u32,
+    ///     next: RefCell<Option<Gc<Node>>>,
+    /// }
+    ///
+    /// let a = Gc::new(Node {
+    ///     weight: 1,
+    ///     next: RefCell::new(None),
+    /// });
+    /// let b = Gc::new(Node {
+    ///     weight: 2,
+    ///     next: RefCell::new(Some(a.clone())),
+    /// });
+    /// *a.next.borrow_mut() = Some(b.clone());
+    ///
+    /// let total = Gc::fold_reachable(&a, 0, |sum, value, _| {
+    ///     sum + value.downcast_ref::<Node>().map_or(0, |node| node.weight)
+    /// });
+    /// assert_eq!(total, 3);
+    /// ```
+    pub fn fold_reachable<A>(
+        root: &Gc<T>,
+        init: A,
+        mut f: impl FnMut(A, &ErasedValue<'_>, usize) -> A,
+    ) -> A {
+        assert!(
+            !COLLECTING.with(Cell::get),
+            "structure may not be inspected while collecting"
+        );
+        let mut acc = init;
+        let mut seen = Set::from([root.ptr.cast::<()>()]);
+        // `Gc`s to every allocation found so far, so that `f` cannot free any of them
+        let mut retained: Vec<Box<dyn Any>> = vec![Box::new(root.clone())];
+        let mut queue = VecDeque::from([Node::new(root)]);
+
+        while let Some(node) = queue.pop_front() {
+            let value = ErasedValue { node: &node };
+            acc = f(acc, &value, node.id.as_ptr() as usize);
+
+            for child in node.children().unwrap_or_default() {
+                if seen.insert(child.id) {
+                    retained.push(unsafe { (child.retain_fn)(child.ptr) });
+                    queue.push_back(child);
+                }
+            }
+        }
+
+        acc
+    }
+
+    /// Call `f` on the value in every allocation reachable from `root` which contains a `U`,
+    /// including `root` itself.
+    ///
+    /// Allocations are visited in the same order as [`Gc::fold_reachable`], and each one is
+    /// visited exactly once.
+    /// Allocations containing values of any other type are traversed but not passed to `f`.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if it is called while a collection is running, such as from the
+    /// `Drop` implementation of a garbage-collected value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::{unsync::Gc, Collectable};
+    ///
+    /// #[derive(Collectable)]
+    /// struct Pair(Gc<u32>, Gc<String>);
+    ///
+    /// let pair = Gc::new(Pair(Gc::new(7), Gc::new(String::from("seven"))));
+    /// let mut numbers = Vec::new();
+    /// Gc::for_each_of_type(&pair, |n: &u32| numbers.push(*n));
+    /// assert_eq!(numbers, [7]);
+    /// ```
+    pub fn for_each_of_type<U: Collectable + 'static>(root: &Gc<T>, mut f: impl FnMut(&U)) {
+        Gc::fold_reachable(root, (), |(), value, _| {
+            if let Some(value) = value.downcast_ref::<U>() {
+                f(value);
+            }
+        });
+    }
+}
+
+/// Find every allocation reachable from `root`.
+///
+/// `root` may be any collectable value, such as a [`Gc`] or a structure containing several of
+/// them.
+/// Its contents are visited the same way the garbage collector visits them, and every allocation
+/// found this way is returned exactly once, in breadth-first order, even if it is part of a cycle.
+/// The contents of an allocation which cannot be visited (for instance, because a `RefCell` inside
+/// it is mutably borrowed) are skipped.
+///
+/// An [`AllocationId`] is only meaningful while its allocation is alive, so the result describes
+/// the graph at the time of the call and may become stale as `Gc`s are dropped.
+///
+/// # Panics
+///
+/// This function will panic if it is called while a collection is running, such as from the
+/// `Drop` implementation of a garbage-collected value.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::{
+///     unsync::{reachable_from, Gc},
+///     Collectable,
+/// };
+/// use std::cell::RefCell;
+///
+/// #[derive(Collectable)]
+/// struct Node(RefCell<Vec<Gc<Node>>>);
+///
+/// let a = Gc::new(Node(RefCell::new(Vec::new())));
+/// let b = Gc::new(Node(RefCell::new(vec![a.clone()])));
+/// a.0.borrow_mut().push(b.clone());
+///
+/// let found = reachable_from(&a);
+/// assert_eq!(found, [Gc::allocation_id(&a), Gc::allocation_id(&b)]);
+/// ```
+pub fn reachable_from<T: Collectable + ?Sized>(root: &T) -> Vec<AllocationId> {
+    assert!(
+        !COLLECTING.with(Cell::get),
+        "structure may not be inspected while collecting"
+    );
+    let mut children = Children(Vec::new());
+    // the root itself is not in an allocation, so there is nothing to record if it can't be
+    // visited
+    let _ = root.accept(&mut children);
+
+    let mut seen = Set::new();
+    let mut found = Vec::new();
+    let mut queue = VecDeque::from(children.0);
+    while let Some(node) = queue.pop_front() {
+        if !seen.insert(node.id) {
+            continue;
+        }
+        found.push(node.allocation_id());
+        queue.extend(node.children().unwrap_or_default());
+    }
+
+    found
+}
+
+/// The type-erased value inside an allocation, as passed to the folding function of
+/// [`Gc::fold_reachable`].
+pub struct ErasedValue<'a> {
+    /// The allocation containing the value.
+    node: &'a Node,
+}
+
+impl ErasedValue<'_> {
+    #[must_use]
+    /// Get the [`TypeId`] of the value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    /// use std::any::TypeId;
+    ///
+    /// let gc = Gc::new(1u8);
+    /// Gc::fold_reachable(&gc, (), |(), value, _| {
+    ///     assert_eq!(value.type_id(), TypeId::of::<u8>());
+    /// });
+    /// ```
+    pub fn type_id(&self) -> TypeId {
+        self.node.type_id
+    }
+
+    #[must_use]
+    /// Determine whether the value is a `U`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    ///
+    /// let gc = Gc::new(1u8);
+    /// Gc::fold_reachable(&gc, (), |(), value, _| {
+    ///     assert!(value.is::<u8>());
+    ///     assert!(!value.is::<u16>());
+    /// });
+    /// ```
+    pub fn is<U: Collectable + 'static>(&self) -> bool {
+        self.node.type_id == TypeId::of::<U>()
+    }
+
+    #[must_use]
+    /// Get a reference to the value if it is a `U`, or `None` if it is not.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    ///
+    /// let gc = Gc::new(1u8);
+    /// Gc::fold_reachable(&gc, (), |(), value, _| {
+    ///     assert_eq!(value.downcast_ref::<u8>(), Some(&1));
+    ///     assert_eq!(value.downcast_ref::<u16>(), None);
+    /// });
+    /// ```
+    pub fn downcast_ref<U: Collectable + 'static>(&self) -> Option<&U> {
+        // the allocation is kept alive by `fold_reachable` for as long as `self` is borrowed
+        self.is::<U>()
+            .then(|| unsafe { &self.node.ptr.specify::<GcBox<U>>().as_ref().value })
+    }
+}
+
+/// A type-erased allocation found while traversing a graph.
+struct Node {
+    /// The address of the allocation, used to identify it.
+    id: NonNull<()>,
+    /// The type of the value stored in the allocation.
+    type_id: TypeId,
+    /// An erased pointer to the allocation.
+    ptr: ErasedPtr,
+    /// A function which visits the contents of the allocation with a `Children` visitor.
+    children_fn: unsafe fn(ErasedPtr, &mut Children) -> Result<(), ()>,
+    /// A function which creates a new `Gc` to the allocation, keeping it alive.
+    retain_fn: unsafe fn(ErasedPtr) -> Box<dyn Any>,
+}
+
+impl Node {
+    /// Construct a node referring to the allocation pointed to by `gc`.
+    fn new<T: Collectable + ?Sized>(gc: &Gc<T>) -> Node {
+        Node {
+            id: gc.ptr.cast(),
+            type_id: TypeId::of::<T>(),
+            ptr: ErasedPtr::new(gc.ptr),
+            children_fn: visit_children::<T>,
+            retain_fn: retain::<T>,
+        }
+    }
+
+    /// Get the public identifier of this allocation.
+    fn allocation_id(&self) -> AllocationId {
+        AllocationId::from(self.id.cast::<GcBox<()>>())
+    }
+
+    /// Get the allocations directly referred to by this allocation, in visitation order.
+    /// Returns `None` if the allocation's contents could not be visited.
+    fn children(&self) -> Option<Vec<Node>> {
+        let mut children = Children(Vec::new());
+        unsafe { (self.children_fn)(self.ptr, &mut children) }.ok()?;
+        Some(children.0)
+    }
+}
+
+/// Visit the contents of an erased allocation with a `Children` visitor.
+///
+/// # Safety
+///
+/// `T` must be the same type that `ptr` was created with via [`ErasedPtr::new`].
+unsafe fn visit_children<T: Collectable + ?Sized>(
+    ptr: ErasedPtr,
+    children: &mut Children,
+) -> Result<(), ()> {
+    ptr.specify::<GcBox<T>>().as_ref().value.accept(children)
+}
+
+/// Create a new `Gc` to an erased allocation, erasing its type.
+///
+/// # Safety
+///
+/// `T` must be the same type that `ptr` was created with via [`ErasedPtr::new`], and the
+/// allocation must be alive.
+unsafe fn retain<T: Collectable + ?Sized + 'static>(ptr: ErasedPtr) -> Box<dyn Any> {
+    let gc = ManuallyDrop::new(Gc {
+        ptr: ptr.specify::<GcBox<T>>(),
+    });
+    Box::new(Gc::clone(&gc))
+}
+
+/// A visitor which records every `Gc` directly contained in a value.
+pub(super) struct Children(Vec<Node>);
+
+impl Visitor for Children {
+    #[cfg(feature = "std")]
+    fn visit_sync<T>(&mut self, _: &crate::sync::Gc<T>)
+    where
+        T: Collectable + Send + Sync + ?Sized,
+    {
+        // `sync::Gc`s are not part of the thread-local graph
+    }
+
+    fn visit_unsync<T>(&mut self, gc: &Gc<T>)
+    where
+        T: Collectable + ?Sized,
+    {
+        self.0.push(Node::new(gc));
+    }
+
+    fn as_any_mut(&mut self) -> Option<&mut dyn Any> {
+        Some(self)
+    }
+}
@@ -20,7 +20,7 @@
 
 use crate::Visitor;
 
-use super::*;
+use super::{collect::COLLECTING, *};
 use std::{
     cell::RefCell,
     sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering},
@@ -30,6 +30,7 @@ use std::{
 /// Test a simple data structure
 fn simple() {
     static DROPPED: AtomicBool = AtomicBool::new(false);
+    #[allow(unused)]
     struct Foo(u8);
 
     impl Drop for Foo {
@@ -251,6 +252,76 @@ fn double_borrow() {
     assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 1);
 }
 
+#[test]
+/// Check that a cycle is kept alive while one of its `RefCell`s is borrowed, since the collector
+/// cannot see what the cell holds.
+fn borrowed_refcell_retained() {
+    static DROP_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    let a = Gc::new(MultiRef {
+        refs: RefCell::new(Vec::new()),
+        drop_count: &DROP_COUNT,
+    });
+    let b = Gc::new(MultiRef {
+        refs: RefCell::new(vec![a.clone()]),
+        drop_count: &DROP_COUNT,
+    });
+    a.refs.borrow_mut().push(b.clone());
+    let weak = Gc::downgrade(&a);
+
+    // SAFETY: the collector keeps `a` alive while its cell is borrowed, which is what this test
+    // checks
+    let node = unsafe { &*Gc::as_ptr(&a) };
+    let guard = node.refs.borrow_mut();
+    drop((a, b));
+    collect();
+    assert!(!weak.is_dangling());
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 0);
+    assert_eq!(guard.len(), 1);
+
+    drop(guard);
+    collect();
+    assert!(weak.is_dangling());
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 2);
+}
+
+#[test]
+/// Check that a borrowed `RefCell` partway around a cycle keeps the whole cycle alive, and that the
+/// cycle is collected once the borrow is released without anything else touching it.
+fn borrowed_refcell_in_cycle() {
+    static DROP_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    let nodes: Vec<Gc<MultiRef>> = (0..3)
+        .map(|_| {
+            Gc::new(MultiRef {
+                refs: RefCell::new(Vec::new()),
+                drop_count: &DROP_COUNT,
+            })
+        })
+        .collect();
+    for (i, node) in nodes.iter().enumerate() {
+        node.refs.borrow_mut().push(nodes[(i + 1) % 3].clone());
+    }
+    let weaks: Vec<Weak<MultiRef>> = nodes.iter().map(Gc::downgrade).collect();
+
+    // SAFETY: the collector keeps the cycle alive while the cell is borrowed, which is what this
+    // test checks
+    let middle = unsafe { &*Gc::as_ptr(&nodes[1]) };
+    let guard = middle.refs.borrow_mut();
+    drop(nodes);
+    for _ in 0..2 {
+        collect();
+        assert!(weaks.iter().all(|weak| !weak.is_dangling()));
+        assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 0);
+    }
+    assert_eq!(guard.len(), 1);
+
+    drop(guard);
+    collect();
+    assert!(weaks.iter().all(Weak::is_dangling));
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 3);
+}
+
 #[test]
 #[cfg(feature = "coerce-unsized")]
 fn coerce_array() {
@@ -262,3 +333,4315 @@ fn coerce_array() {
         2 * std::mem::size_of::<usize>()
     );
 }
+
+#[test]
+/// Check that a `Gc` to a static value can be used like any other `Gc`, and is never collected.
+fn static_gc() {
+    static DROP_COUNT: AtomicUsize = AtomicUsize::new(0);
+    static CYCLE_DROP_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    struct Config(u8);
+
+    unsafe impl Collectable for Config {
+        fn accept<V: Visitor>(&self, _: &mut V) -> Result<(), ()> {
+            Ok(())
+        }
+    }
+
+    impl Drop for Config {
+        fn drop(&mut self) {
+            DROP_COUNT.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    struct Foo {
+        config: Gc<Config>,
+        cycle: RefCell<Option<Gc<Foo>>>,
+    }
+
+    unsafe impl Collectable for Foo {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.config.accept(visitor)?;
+            self.cycle.accept(visitor)
+        }
+    }
+
+    impl Drop for Foo {
+        fn drop(&mut self) {
+            CYCLE_DROP_COUNT.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    crate::gc_static! {
+        static CONFIG: Gc<Config> = Config(7);
+    }
+
+    let gc1 = CONFIG.get();
+    let gc2 = gc1.clone();
+    assert_eq!(gc1.0, 7);
+    drop(gc1);
+    drop(gc2);
+    collect();
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 0);
+
+    let foo = Gc::new(Foo {
+        config: CONFIG.get(),
+        cycle: RefCell::new(None),
+    });
+    foo.cycle.replace(Some(foo.clone()));
+    drop(foo);
+    collect();
+    assert_eq!(CYCLE_DROP_COUNT.load(Ordering::Relaxed), 1);
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 0);
+
+    assert_eq!(CONFIG.get().0, 7);
+    assert_eq!(
+        unsafe { CONFIG.get().ptr.as_ref() }.ref_count.get(),
+        usize::MAX
+    );
+}
+
+#[test]
+fn allocation_layout() {
+    let gc = Gc::new(0u64);
+    let layout = Gc::allocation_layout(&gc);
+    assert_eq!(layout.size(), std::mem::size_of::<GcBox<u64>>());
+    assert_eq!(layout.align(), std::mem::align_of::<GcBox<u64>>());
+}
+
+#[test]
+#[cfg(feature = "coerce-unsized")]
+fn allocation_layout_unsized() {
+    let gc: Gc<[u8]> = Gc::new([1u8, 2, 3, 4, 5]);
+    let layout = Gc::allocation_layout(&gc);
+    assert_eq!(layout, Layout::new::<GcBox<[u8; 5]>>());
+}
+
+#[test]
+fn lazy_gc() {
+    static LOADS: AtomicUsize = AtomicUsize::new(0);
+    static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+    struct Node {
+        next: RefCell<Option<Gc<LazyGc<Node>>>>,
+    }
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            DROPS.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.next.accept(visitor)
+        }
+    }
+
+    let lazy = Gc::new(LazyGc::new(|| {
+        LOADS.fetch_add(1, Ordering::Relaxed);
+        Node {
+            next: RefCell::new(None),
+        }
+    }));
+    assert!(!LazyGc::is_loaded(&lazy));
+    assert_eq!(LOADS.load(Ordering::Relaxed), 0);
+
+    // first access loads the value, then it's cached
+    *lazy.next.borrow_mut() = Some(lazy.clone());
+    assert!(LazyGc::is_loaded(&lazy));
+    assert!(lazy.next.borrow().is_some());
+    assert_eq!(LOADS.load(Ordering::Relaxed), 1);
+
+    collect();
+    assert_eq!(DROPS.load(Ordering::Relaxed), 0);
+
+    drop(lazy);
+    collect();
+    assert_eq!(LOADS.load(Ordering::Relaxed), 1);
+    assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn entry_or_gc_existing() {
+    let mut map = std::collections::HashMap::new();
+    let n_living = || DUMPSTER.with(|d| d.n_refs_living.get());
+
+    let before = n_living();
+    let first = entry_or_gc(&mut map, 1, || 10u32).clone();
+    assert_eq!(n_living(), before + 2);
+
+    let mut made = false;
+    let second = entry_or_gc(&mut map, 1, || {
+        made = true;
+        20
+    });
+    assert!(!made);
+    assert_eq!(**second, 10);
+    assert_eq!(n_living(), before + 2);
+    drop(first);
+}
+
+#[test]
+fn into_any() {
+    static DROPPED: AtomicBool = AtomicBool::new(false);
+
+    struct Foo(u32);
+
+    impl Drop for Foo {
+        fn drop(&mut self) {
+            DROPPED.store(true, Ordering::Relaxed);
+        }
+    }
+
+    unsafe impl Collectable for Foo {
+        fn accept<V: Visitor>(&self, _: &mut V) -> Result<(), ()> {
+            Ok(())
+        }
+    }
+
+    let gc = Gc::new(Foo(7));
+    let any_gc: Gc<dyn AnyCollectable> = Gc::into_any(gc.clone());
+    assert!(std::ptr::eq(
+        any_gc.downcast_ref::<Foo>().unwrap(),
+        addr_of!(*gc)
+    ));
+    assert_eq!(any_gc.downcast_ref::<Foo>().unwrap().0, 7);
+    assert!(any_gc.downcast_ref::<u32>().is_none());
+
+    drop(gc);
+    collect();
+    assert!(!DROPPED.load(Ordering::Relaxed));
+    drop(any_gc);
+    collect();
+    assert!(DROPPED.load(Ordering::Relaxed));
+}
+
+#[test]
+/// Check that an allocation which is traced as its concrete type and then marked through a
+/// `Gc<dyn AnyCollectable>` keeps its children alive.
+fn into_any_traced() {
+    static DROPPED: AtomicBool = AtomicBool::new(false);
+
+    struct Leaf(u32);
+
+    impl Drop for Leaf {
+        fn drop(&mut self) {
+            DROPPED.store(true, Ordering::Relaxed);
+        }
+    }
+
+    unsafe impl Collectable for Leaf {
+        fn accept<V: Visitor>(&self, _: &mut V) -> Result<(), ()> {
+            Ok(())
+        }
+    }
+
+    struct Node {
+        child: Gc<Leaf>,
+    }
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.child.accept(visitor)
+        }
+    }
+
+    struct Holder {
+        any: Gc<dyn AnyCollectable>,
+    }
+
+    unsafe impl Collectable for Holder {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.any.accept(visitor)
+        }
+    }
+
+    set_deterministic(true);
+    let a = Gc::new(Node {
+        child: Gc::new(Leaf(7)),
+    });
+    // `a` is traced as a `Node` first, and only reached through `holder` as a
+    // `dyn AnyCollectable` afterwards
+    drop(a.clone());
+    let holder = Gc::new(Holder {
+        any: Gc::into_any(a.clone()),
+    });
+    drop(holder.clone());
+    collect();
+    set_deterministic(false);
+
+    assert!(!DROPPED.load(Ordering::Relaxed));
+    assert_eq!(a.child.0, 7);
+    assert!(holder.any.is::<Node>());
+}
+
+#[test]
+fn into_boxed_any() {
+    static DROPPED: AtomicBool = AtomicBool::new(false);
+
+    struct Plugin {
+        name: &'static str,
+        next: RefCell<Option<Gc<Plugin>>>,
+    }
+
+    impl Drop for Plugin {
+        fn drop(&mut self) {
+            DROPPED.store(true, Ordering::Relaxed);
+        }
+    }
+
+    unsafe impl Collectable for Plugin {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.next.accept(visitor)
+        }
+    }
+
+    let plugin = Gc::new(Plugin {
+        name: "plugin",
+        next: RefCell::new(None),
+    });
+    let registry: Vec<Box<dyn std::any::Any>> = vec![
+        Gc::into_boxed_any(Gc::new(1u8)),
+        Gc::into_boxed_any(plugin.clone()),
+    ];
+    assert_eq!(Gc::strong_count(&plugin), 2);
+    drop(plugin);
+    collect();
+    assert!(!DROPPED.load(Ordering::Relaxed));
+
+    let mut registry = registry.into_iter();
+    assert!(registry.next().unwrap().downcast::<Gc<Plugin>>().is_err());
+    let plugin: Gc<Plugin> = *registry.next().unwrap().downcast().unwrap();
+    assert_eq!(plugin.name, "plugin");
+    assert_eq!(Gc::strong_count(&plugin), 1);
+
+    // the recovered `Gc` still takes part in cycle collection
+    *plugin.next.borrow_mut() = Some(plugin.clone());
+    drop(plugin);
+    collect();
+    assert!(DROPPED.load(Ordering::Relaxed));
+}
+
+#[test]
+fn dyn_eq() {
+    #[derive(PartialEq)]
+    struct Point(i32, i32);
+
+    unsafe impl Collectable for Point {
+        fn accept<V: Visitor>(&self, _: &mut V) -> Result<(), ()> {
+            Ok(())
+        }
+    }
+
+    let point = Gc::new(Point(1, 2));
+    let a = Gc::into_dyn_eq(point.clone());
+    let b = Gc::into_dyn_eq(Gc::new(Point(1, 2)));
+    let c = Gc::into_dyn_eq(Gc::new(Point(2, 1)));
+    let d = Gc::into_dyn_eq(Gc::new((1, 2)));
+
+    assert!(Gc::dyn_eq(&a, &a));
+    assert!(Gc::dyn_eq(&a, &b));
+    assert!(Gc::dyn_eq(&b, &a));
+    assert!(!Gc::dyn_eq(&a, &c));
+    assert!(!Gc::dyn_eq(&a, &d));
+    assert!(!Gc::dyn_eq(&d, &a));
+    assert!(std::ptr::eq(
+        a.as_any().downcast_ref::<Point>().unwrap(),
+        addr_of!(*point)
+    ));
+}
+
+#[test]
+/// Check that an allocation which is traced as its concrete type and then marked through a
+/// `Gc<dyn DynPartialEq>` keeps its children alive.
+fn dyn_eq_traced() {
+    static DROPPED: AtomicBool = AtomicBool::new(false);
+
+    #[derive(PartialEq)]
+    struct Leaf(u32);
+
+    impl Drop for Leaf {
+        fn drop(&mut self) {
+            DROPPED.store(true, Ordering::Relaxed);
+        }
+    }
+
+    unsafe impl Collectable for Leaf {
+        fn accept<V: Visitor>(&self, _: &mut V) -> Result<(), ()> {
+            Ok(())
+        }
+    }
+
+    #[derive(PartialEq)]
+    struct Node {
+        child: Gc<Leaf>,
+    }
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.child.accept(visitor)
+        }
+    }
+
+    struct Holder {
+        eq: Gc<dyn DynPartialEq>,
+    }
+
+    unsafe impl Collectable for Holder {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.eq.accept(visitor)
+        }
+    }
+
+    set_deterministic(true);
+    let a = Gc::new(Node {
+        child: Gc::new(Leaf(7)),
+    });
+    // `a` is traced as a `Node` first, and only reached through `holder` as a
+    // `dyn DynPartialEq` afterwards
+    drop(a.clone());
+    let holder = Gc::new(Holder {
+        eq: Gc::into_dyn_eq(a.clone()),
+    });
+    drop(holder.clone());
+    collect();
+    set_deterministic(false);
+
+    assert!(!DROPPED.load(Ordering::Relaxed));
+    assert_eq!(a.child.0, 7);
+    assert!(Gc::dyn_eq(&holder.eq, &Gc::into_dyn_eq(a.clone())));
+}
+
+#[test]
+#[should_panic = "leak detected"]
+fn leak_threshold() {
+    struct Cyclic(RefCell<Option<Gc<Cyclic>>>);
+
+    unsafe impl Collectable for Cyclic {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.0.accept(visitor)
+        }
+    }
+
+    let before = DUMPSTER.with(|d| d.n_refs_living.get());
+    set_leak_threshold(Some(before));
+
+    let gc = Gc::new(Cyclic(RefCell::new(None)));
+    *gc.0.borrow_mut() = Some(gc.clone());
+    // holding a borrow prevents the cycle from ever being visited, so it cannot be collected
+    std::mem::forget(gc.0.borrow_mut());
+    drop(gc);
+
+    collect();
+}
+
+#[test]
+fn leak_threshold_collect_in_drop() {
+    let before = DUMPSTER.with(|d| d.n_refs_living.get());
+    set_leak_threshold(Some(before));
+
+    // the garbage is still living while the nested call returns, but it is all freed by the time
+    // the outer collection finishes
+    make_garbage(collect);
+    collect();
+    set_leak_threshold(None);
+}
+
+#[test]
+#[cfg(feature = "debug-allocations")]
+fn allocations_of_type() {
+    struct Tracked(usize);
+
+    unsafe impl Collectable for Tracked {
+        fn accept<V: Visitor>(&self, _: &mut V) -> Result<(), ()> {
+            Ok(())
+        }
+    }
+
+    let gcs: Vec<Gc<Tracked>> = (0..5).map(|i| Gc::new(Tracked(i))).collect();
+    let _others = (Gc::new(0usize), Gc::new(RefCell::new(Vec::<Gc<u8>>::new())));
+
+    let found = super::allocations_of_type::<Tracked>();
+    assert_eq!(found.len(), gcs.len());
+    let mut ids: Vec<usize> = found.iter().map(|gc| gc.0).collect();
+    ids.sort_unstable();
+    assert_eq!(ids, [0, 1, 2, 3, 4]);
+    assert!(found.iter().all(|gc| gcs
+        .iter()
+        .any(|orig| std::ptr::eq(addr_of!(**gc), addr_of!(**orig)))));
+    drop(found);
+
+    drop(gcs);
+    collect();
+    assert!(super::allocations_of_type::<Tracked>().is_empty());
+}
+
+#[test]
+#[cfg(feature = "debug-allocations")]
+fn debug_live_allocations_order() {
+    let gcs = (
+        Gc::new(1u16),
+        Gc::new(2u8),
+        Gc::new(3u16),
+        Gc::new(4u8),
+        Gc::from_str("five"),
+    );
+
+    let report = debug_live_allocations();
+    assert_eq!(report, debug_live_allocations());
+
+    // sorted by type name, then by creation
+    let types: Vec<&str> = report
+        .lines()
+        .map(|line| line.split_once(": ").unwrap().1)
+        .collect();
+    assert_eq!(types, ["str", "u16", "u16", "u8", "u8"]);
+    let addresses: Vec<&str> = report
+        .lines()
+        .map(|line| line.split_once(": ").unwrap().0)
+        .collect();
+    assert_eq!(
+        addresses[1],
+        format!("{:?}", Gc::as_gcbox_ptr(&gcs.0).cast::<Cell<usize>>())
+    );
+    assert_eq!(
+        addresses[3],
+        format!("{:?}", Gc::as_gcbox_ptr(&gcs.1).cast::<Cell<usize>>())
+    );
+}
+
+#[test]
+#[cfg(feature = "backtrace")]
+fn allocation_backtrace() {
+    use std::backtrace::BacktraceStatus;
+
+    struct Leaky(RefCell<Option<Gc<Leaky>>>);
+
+    unsafe impl Collectable for Leaky {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.0.accept(visitor)
+        }
+    }
+
+    let untracked = Gc::new(Leaky(RefCell::new(None)));
+
+    set_capture_backtraces(true);
+    let leaked = Gc::new(Leaky(RefCell::new(None)));
+    set_capture_backtraces(false);
+    let id = Gc::allocation_id(&leaked);
+    // leak the allocation by keeping a `Gc` to it alive forever
+    std::mem::forget(leaked.clone());
+    drop(leaked);
+    collect();
+
+    let backtrace = super::allocation_backtrace(id).unwrap();
+    assert_eq!(backtrace.status(), BacktraceStatus::Captured);
+    assert!(super::allocation_backtrace(Gc::allocation_id(&untracked)).is_none());
+    assert!(debug_live_allocations().contains(&backtrace.to_string()));
+}
+
+#[test]
+/// Test that an edge added between two steps of a bounded collection keeps its target alive.
+fn edge_between_bounded_steps() {
+    static DROP_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    struct Node {
+        edges: RefCell<Vec<Gc<Node>>>,
+    }
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            DROP_COUNT.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.edges.accept(visitor)
+        }
+    }
+
+    let new_node = || {
+        Gc::new(Node {
+            edges: RefCell::new(Vec::new()),
+        })
+    };
+
+    let a = new_node();
+    let b = new_node();
+    collect();
+
+    // leave plenty of dirty allocations so that no single step sees all of them
+    let fillers: Vec<_> = (0..10).map(|_| new_node()).collect();
+    for filler in &fillers {
+        drop(filler.clone());
+    }
+    assert_eq!(collect_bounded(1).n_freed, 0);
+
+    // add an edge between two steps, then drop the only other reference to its target
+    a.edges.borrow_mut().push(b.clone());
+    drop(b);
+    while DUMPSTER.with(Dumpster::n_dirty) > 0 {
+        let _ = collect_bounded(1);
+    }
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 0);
+    assert_eq!(a.edges.borrow().len(), 1);
+
+    // close the cycle between steps, then drop it
+    let b = a.edges.borrow()[0].clone();
+    drop(b.clone());
+    assert_eq!(collect_bounded(1).n_freed, 0);
+    b.edges.borrow_mut().push(a.clone());
+    drop((a, b));
+    while DUMPSTER.with(Dumpster::n_dirty) > 0 {
+        let _ = collect_bounded(1);
+    }
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 2);
+    drop(fillers);
+}
+
+#[test]
+fn structural_eq_bounded() {
+    struct Node(RefCell<Vec<Gc<Node>>>);
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.0.accept(visitor)
+        }
+    }
+
+    /// Construct a cycle of `n` nodes, returning a reference to one of them.
+    fn cycle(n: usize) -> Gc<Node> {
+        let first = Gc::new(Node(RefCell::new(Vec::new())));
+        let mut last = first.clone();
+        for _ in 1..n {
+            let next = Gc::new(Node(RefCell::new(Vec::new())));
+            last.0.borrow_mut().push(next.clone());
+            last = next;
+        }
+        last.0.borrow_mut().push(first.clone());
+        first
+    }
+
+    let small_a = cycle(3);
+    let small_b = cycle(3);
+    let small_c = cycle(4);
+    assert_eq!(
+        Gc::structural_eq_bounded(&small_a, &small_b, 10),
+        Some(true)
+    );
+    assert_eq!(
+        Gc::structural_eq_bounded(&small_a, &small_c, 10),
+        Some(false)
+    );
+    assert_eq!(Gc::structural_eq_bounded(&small_a, &small_a, 0), Some(true));
+
+    // two nodes pointing to one node is not the same as two nodes pointing to different nodes
+    let shared = Gc::new(Node(RefCell::new(Vec::new())));
+    let diamond = Gc::new(Node(RefCell::new(vec![shared.clone(), shared])));
+    let fork = Gc::new(Node(RefCell::new(vec![
+        Gc::new(Node(RefCell::new(Vec::new()))),
+        Gc::new(Node(RefCell::new(Vec::new()))),
+    ])));
+    assert_eq!(Gc::structural_eq_bounded(&diamond, &fork, 10), Some(false));
+
+    let large_a = cycle(1000);
+    let large_b = cycle(1000);
+    assert_eq!(Gc::structural_eq_bounded(&large_a, &large_b, 10), None);
+    assert_eq!(
+        Gc::structural_eq_bounded(&large_a, &large_b, 1000),
+        Some(true)
+    );
+
+    // an allocation which cannot be inspected cannot be decided
+    let _guard = small_b.0.borrow_mut();
+    assert_eq!(Gc::structural_eq_bounded(&small_a, &small_b, 10), None);
+}
+
+#[test]
+fn for_each_of_type() {
+    enum Child {
+        Branch(Gc<Branch>),
+        Leaf(Gc<Leaf>),
+        Text(Gc<String>),
+    }
+
+    unsafe impl Collectable for Child {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            match self {
+                Child::Branch(gc) => gc.accept(visitor),
+                Child::Leaf(gc) => gc.accept(visitor),
+                Child::Text(gc) => gc.accept(visitor),
+            }
+        }
+    }
+
+    struct Branch(RefCell<Vec<Child>>);
+
+    unsafe impl Collectable for Branch {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.0.accept(visitor)
+        }
+    }
+
+    struct Leaf(u32);
+
+    unsafe impl Collectable for Leaf {
+        fn accept<V: Visitor>(&self, _: &mut V) -> Result<(), ()> {
+            Ok(())
+        }
+    }
+
+    let leaf = |n| Child::Leaf(Gc::new(Leaf(n)));
+    let inner = Gc::new(Branch(RefCell::new(vec![leaf(1), leaf(2)])));
+    let root = Gc::new(Branch(RefCell::new(vec![
+        leaf(3),
+        Child::Branch(inner.clone()),
+        Child::Text(Gc::new(String::from("not a leaf"))),
+    ])));
+    // a cycle back to the root, and a second path to a leaf which was already seen
+    inner.0.borrow_mut().push(Child::Branch(root.clone()));
+    let shared = Gc::new(Leaf(4));
+    inner.0.borrow_mut().push(Child::Leaf(shared.clone()));
+    root.0.borrow_mut().push(Child::Leaf(shared));
+
+    let mut leaves = Vec::new();
+    Gc::for_each_of_type(&root, |l: &Leaf| leaves.push(l.0));
+    leaves.sort_unstable();
+    assert_eq!(leaves, [1, 2, 3, 4]);
+
+    let mut n_branches = 0;
+    Gc::for_each_of_type(&root, |_: &Branch| n_branches += 1);
+    assert_eq!(n_branches, 2);
+
+    let mut n_texts = 0;
+    Gc::for_each_of_type(&inner, |_: &String| n_texts += 1);
+    assert_eq!(n_texts, 1);
+}
+
+#[test]
+fn reachable_from() {
+    struct Node(RefCell<Vec<Gc<Node>>>);
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.0.accept(visitor)
+        }
+    }
+
+    let new_node = |children: &[&Gc<Node>]| {
+        Gc::new(Node(RefCell::new(
+            children.iter().map(|&gc| gc.clone()).collect(),
+        )))
+    };
+
+    // d -> c -> a <-> b, and a -> c
+    let a = new_node(&[]);
+    let b = new_node(&[&a]);
+    let c = new_node(&[&a]);
+    a.0.borrow_mut().extend([b.clone(), c.clone()]);
+    let d = new_node(&[&c]);
+
+    let found = super::reachable_from(&a);
+    assert_eq!(found.len(), 3);
+    for gc in [&a, &b, &c] {
+        assert!(found.contains(&Gc::allocation_id(gc)));
+    }
+    assert_eq!(super::reachable_from(&d).len(), 4);
+
+    // any collectable value can be the root, not just a `Gc`
+    assert_eq!(super::reachable_from(&vec![b.clone(), d.clone()]).len(), 4);
+    assert!(super::reachable_from(&5).is_empty());
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn structural_hash() {
+    use std::hash::{Hash, Hasher};
+
+    struct Node(u8, RefCell<Vec<Gc<Node>>>);
+
+    impl Hash for Node {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            self.0.hash(state);
+        }
+    }
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.1.accept(visitor)
+        }
+    }
+
+    /// Construct a leaf node with the given label.
+    fn leaf(label: u8) -> Gc<Node> {
+        Gc::new(Node(label, RefCell::new(Vec::new())))
+    }
+
+    /// Construct a cycle of `n` nodes labelled with their position, returning a reference to the
+    /// first one.
+    fn cycle(n: u8) -> Gc<Node> {
+        let first = leaf(0);
+        let mut last = first.clone();
+        for label in 1..n {
+            let next = leaf(label);
+            last.1.borrow_mut().push(next.clone());
+            last = next;
+        }
+        last.1.borrow_mut().push(first.clone());
+        first
+    }
+
+    let a = cycle(3);
+    let b = cycle(3);
+    assert_eq!(Gc::structural_hash(&a), Gc::structural_hash(&b));
+    assert_ne!(Gc::structural_hash(&a), Gc::structural_hash(&cycle(4)));
+
+    // the values are part of the hash, not just the shape
+    assert_eq!(Gc::structural_hash(&leaf(1)), Gc::structural_hash(&leaf(1)));
+    assert_ne!(Gc::structural_hash(&leaf(1)), Gc::structural_hash(&leaf(2)));
+    let chain = |label| Gc::new(Node(0, RefCell::new(vec![leaf(label)])));
+    assert_ne!(
+        Gc::structural_hash(&chain(1)),
+        Gc::structural_hash(&chain(2))
+    );
+
+    // sharing is part of the shape
+    let shared = leaf(0);
+    let diamond = Gc::new(Node(0, RefCell::new(vec![shared.clone(), shared])));
+    let fork = Gc::new(Node(0, RefCell::new(vec![leaf(0), leaf(0)])));
+    assert_ne!(Gc::structural_hash(&diamond), Gc::structural_hash(&fork));
+
+    // so are the types of the values
+    assert_ne!(
+        Gc::structural_hash(&Gc::new(0u8)),
+        Gc::structural_hash(&Gc::new(0u16))
+    );
+
+    // an allocation which cannot be inspected is not the same as an empty one
+    let empty = leaf(0);
+    let locked = leaf(0);
+    let _guard = locked.1.borrow_mut();
+    assert_ne!(Gc::structural_hash(&empty), Gc::structural_hash(&locked));
+}
+
+/// A self-referential structure which runs some function when it is dropped.
+struct DropHook {
+    cycle: RefCell<Option<Gc<DropHook>>>,
+    hook: fn(),
+}
+
+unsafe impl Collectable for DropHook {
+    fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+        self.cycle.accept(visitor)
+    }
+}
+
+impl Drop for DropHook {
+    fn drop(&mut self) {
+        (self.hook)();
+    }
+}
+
+/// Construct a self-referential cycle which calls `hook` when dropped, then drop it.
+fn make_garbage(hook: fn()) {
+    let gc = Gc::new(DropHook {
+        cycle: RefCell::new(None),
+        hook,
+    });
+    *gc.cycle.borrow_mut() = Some(gc.clone());
+}
+
+#[test]
+fn collecting_flag_reset() {
+    static SAW_COLLECTING: AtomicBool = AtomicBool::new(false);
+
+    make_garbage(|| SAW_COLLECTING.store(COLLECTING.with(Cell::get), Ordering::Relaxed));
+    collect();
+    assert!(SAW_COLLECTING.load(Ordering::Relaxed));
+    assert!(!COLLECTING.with(Cell::get));
+}
+
+#[test]
+fn collect_in_drop() {
+    static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+    make_garbage(|| {
+        collect();
+        DROPPED.fetch_add(1, Ordering::Relaxed);
+    });
+    collect();
+    assert_eq!(DROPPED.load(Ordering::Relaxed), 1);
+    assert!(!COLLECTING.with(Cell::get));
+}
+
+#[test]
+#[cfg(panic = "unwind")]
+fn panic_in_drop() {
+    static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+    make_garbage(|| panic!("oops"));
+    assert!(std::panic::catch_unwind(collect).is_err());
+    assert!(!COLLECTING.with(Cell::get));
+
+    // the collector must still work after a panic
+    make_garbage(|| {
+        DROPPED.fetch_add(1, Ordering::Relaxed);
+    });
+    collect();
+    assert_eq!(DROPPED.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+#[cfg(panic = "unwind")]
+fn panic_in_drop_frees_rest() {
+    static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+    struct Node {
+        panics: bool,
+        next: RefCell<Option<Gc<Node>>>,
+    }
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            DROPPED.fetch_add(1, Ordering::Relaxed);
+            assert!(!self.panics, "oops");
+        }
+    }
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.next.accept(visitor)
+        }
+    }
+
+    let new_node = |panics| {
+        Gc::new(Node {
+            panics,
+            next: RefCell::new(None),
+        })
+    };
+    let nodes = [new_node(false), new_node(true), new_node(false)];
+    for i in 0..3 {
+        *nodes[i].next.borrow_mut() = Some(nodes[(i + 1) % 3].clone());
+    }
+    let weaks = nodes.each_ref().map(Gc::downgrade);
+    drop(nodes);
+
+    let payload = std::panic::catch_unwind(collect).unwrap_err();
+    assert_eq!(*payload.downcast_ref::<&str>().unwrap(), "oops");
+    assert!(!COLLECTING.with(Cell::get));
+    // every value was still dropped and every allocation freed
+    assert_eq!(DROPPED.load(Ordering::Relaxed), 3);
+    assert!(weaks.iter().all(Weak::is_dangling));
+
+    // the collector must still work after a panic
+    let node = new_node(false);
+    *node.next.borrow_mut() = Some(node.clone());
+    assert_eq!(Gc::strong_count(&node), 2);
+    drop(node);
+    collect();
+    assert_eq!(DROPPED.load(Ordering::Relaxed), 4);
+}
+
+#[test]
+fn clone_from() {
+    let n_dropped = || DUMPSTER.with(|d| d.n_ref_drops.get());
+    let a = Gc::new(1u8);
+    let b = Gc::new(2u8);
+
+    // same allocation: nothing changes
+    let mut target = a.clone();
+    let before = n_dropped();
+    target.clone_from(&a);
+    assert_eq!(n_dropped(), before);
+    assert_eq!(Gc::strong_count(&a), 2);
+    assert_eq!(*target, 1);
+
+    // different allocation: the old target loses a reference and the source gains one
+    target.clone_from(&b);
+    assert_eq!(Gc::strong_count(&a), 1);
+    assert_eq!(Gc::strong_count(&b), 2);
+    assert_eq!(*target, 2);
+
+    drop(target);
+    assert_eq!(Gc::strong_count(&b), 1);
+}
+
+#[test]
+fn strong_count() {
+    let gc1 = Gc::new(0u8);
+    assert_eq!(Gc::strong_count(&gc1), 1);
+    let gc2 = gc1.clone();
+    assert_eq!(Gc::strong_count(&gc1), 2);
+    assert_eq!(Gc::strong_count(&gc2), 2);
+    drop(gc1);
+    assert_eq!(Gc::strong_count(&gc2), 1);
+    collect();
+    assert_eq!(Gc::strong_count(&gc2), 1);
+}
+
+#[test]
+fn strong_count_zombie() {
+    static SEEN_COUNT: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+    struct Peek(RefCell<Option<Gc<Peek>>>);
+
+    unsafe impl Collectable for Peek {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.0.accept(visitor)
+        }
+    }
+
+    impl Drop for Peek {
+        fn drop(&mut self) {
+            if let Some(gc) = self.0.borrow().as_ref() {
+                SEEN_COUNT.store(Gc::strong_count(gc), Ordering::Relaxed);
+            }
+        }
+    }
+
+    let gc = Gc::new(Peek(RefCell::new(None)));
+    *gc.0.borrow_mut() = Some(gc.clone());
+    drop(gc);
+    collect();
+    // a zombie must not be reported as living
+    assert_eq!(SEEN_COUNT.load(Ordering::Relaxed), 0);
+}
+
+#[test]
+#[cfg(feature = "bytemuck")]
+fn as_bytes() {
+    let gc = Gc::new([1u32, 2, 3, 0xdead_beef]);
+    let bytes = Gc::as_bytes(&gc);
+    assert_eq!(bytes.len(), 16);
+    let expected: Vec<u8> = gc.iter().flat_map(|x| x.to_ne_bytes()).collect();
+    assert_eq!(bytes, expected);
+}
+
+#[test]
+fn weak_upgrade() {
+    static DROPPED: AtomicBool = AtomicBool::new(false);
+
+    struct Foo;
+
+    impl Drop for Foo {
+        fn drop(&mut self) {
+            DROPPED.store(true, Ordering::Relaxed);
+        }
+    }
+
+    unsafe impl Collectable for Foo {
+        fn accept<V: Visitor>(&self, _: &mut V) -> Result<(), ()> {
+            Ok(())
+        }
+    }
+
+    let gc = Gc::new(Foo);
+    let weak = Gc::downgrade(&gc);
+    let weak2 = weak.clone();
+    assert_eq!(Gc::strong_count(&gc), 1);
+
+    let upgraded = weak.upgrade().unwrap();
+    assert_eq!(Gc::strong_count(&gc), 2);
+    drop(upgraded);
+    drop(gc);
+
+    // the value is dropped even though weak references remain
+    assert!(DROPPED.load(Ordering::Relaxed));
+    assert!(weak.upgrade().is_none());
+    drop(weak);
+    assert!(weak2.upgrade().is_none());
+}
+
+#[test]
+fn weak_cycle() {
+    static DROP_COUNT: AtomicUsize = AtomicUsize::new(0);
+    static UPGRADES: AtomicUsize = AtomicUsize::new(0);
+
+    struct Node {
+        strong: RefCell<Option<Gc<Node>>>,
+        weak: RefCell<Option<Weak<Node>>>,
+        outside: RefCell<Option<Weak<Node>>>,
+    }
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            DROP_COUNT.fetch_add(1, Ordering::Relaxed);
+            // upgrading a weak reference to a value being collected must fail
+            if let Some(weak) = self.weak.borrow().as_ref() {
+                assert!(weak.upgrade().is_none());
+            }
+            // upgrading a weak reference to a living value must succeed
+            if let Some(weak) = self.outside.borrow().as_ref() {
+                if weak.upgrade().is_some() {
+                    UPGRADES.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.strong.accept(visitor)?;
+            self.weak.accept(visitor)?;
+            self.outside.accept(visitor)
+        }
+    }
+
+    let new_node = || {
+        Gc::new(Node {
+            strong: RefCell::new(None),
+            weak: RefCell::new(None),
+            outside: RefCell::new(None),
+        })
+    };
+
+    let survivor = new_node();
+    let a = new_node();
+    let b = new_node();
+    *a.strong.borrow_mut() = Some(b.clone());
+    *b.strong.borrow_mut() = Some(a.clone());
+    *a.weak.borrow_mut() = Some(Gc::downgrade(&b));
+    *b.weak.borrow_mut() = Some(Gc::downgrade(&a));
+    *a.outside.borrow_mut() = Some(Gc::downgrade(&survivor));
+    *survivor.weak.borrow_mut() = Some(Gc::downgrade(&a));
+
+    let weak_a = Gc::downgrade(&a);
+    drop(a);
+    drop(b);
+    collect();
+
+    // weak references must not keep the cycle alive
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 2);
+    assert_eq!(UPGRADES.load(Ordering::Relaxed), 1);
+    assert!(weak_a.upgrade().is_none());
+    assert!(survivor.weak.borrow().as_ref().unwrap().upgrade().is_none());
+}
+
+#[test]
+fn gc_vec_tree() {
+    static DROP_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    struct Node {
+        id: usize,
+        children: GcVec<Gc<Node>>,
+    }
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            DROP_COUNT.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.children.accept(visitor)
+        }
+    }
+
+    let new_node = |id| {
+        Gc::new(Node {
+            id,
+            children: GcVec::new(),
+        })
+    };
+
+    // root -> {1, 2}, 1 -> {3}, 3 -> root, 2 -> 2
+    let root = new_node(0);
+    let one = new_node(1);
+    let two = new_node(2);
+    let three = new_node(3);
+    root.children.push(one.clone());
+    root.children.push(two.clone());
+    one.children.push(three.clone());
+    three.children.push(root.clone());
+    two.children.push(two.clone());
+    drop((one, two, three));
+
+    assert_eq!(root.children.len(), 2);
+    assert_eq!(root.children.get(0).unwrap().id, 1);
+    assert_eq!(root.children.get(1).unwrap().id, 2);
+    assert!(root.children.get(2).is_none());
+    let ids: Vec<usize> = root.children.as_slice().iter().map(|c| c.id).collect();
+    assert_eq!(ids, [1, 2]);
+
+    collect();
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 0);
+
+    // detach the self-loop and replace it with a fresh leaf
+    let detached = root.children.set(1, new_node(4));
+    assert_eq!(detached.id, 2);
+    drop(detached);
+    collect();
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 1);
+
+    let leaf = root.children.pop().unwrap();
+    assert_eq!(leaf.id, 4);
+    drop(leaf);
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 2);
+
+    drop(root);
+    collect();
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 5);
+}
+
+#[test]
+fn try_unwrap() {
+    static DROP_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    struct Foo(RefCell<Option<Gc<Foo>>>);
+
+    impl Drop for Foo {
+        fn drop(&mut self) {
+            DROP_COUNT.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    unsafe impl Collectable for Foo {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.0.accept(visitor)
+        }
+    }
+
+    // unique
+    let gc = Gc::new(Foo(RefCell::new(None)));
+    let weak = Gc::downgrade(&gc);
+    let foo = Gc::try_unwrap(gc).ok().unwrap();
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 0);
+    assert!(weak.upgrade().is_none());
+    drop(foo);
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 1);
+    drop(weak);
+
+    // shared
+    let gc1 = Gc::new(Foo(RefCell::new(None)));
+    let gc2 = gc1.clone();
+    let Err(gc1) = Gc::try_unwrap(gc1) else {
+        panic!("try_unwrap succeeded on shared Gc");
+    };
+    assert_eq!(Gc::strong_count(&gc1), 2);
+    drop(gc2);
+    assert!(Gc::try_unwrap(gc1).is_ok());
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 2);
+
+    // self-referential
+    let gc = Gc::new(Foo(RefCell::new(None)));
+    *gc.0.borrow_mut() = Some(gc.clone());
+    let Err(gc) = Gc::try_unwrap(gc) else {
+        panic!("try_unwrap succeeded on self-referential Gc");
+    };
+    drop(gc);
+    collect();
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 3);
+}
+
+#[test]
+fn weak_dangling() {
+    struct Cycle(RefCell<Option<Gc<Cycle>>>);
+
+    unsafe impl Collectable for Cycle {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.0.accept(visitor)
+        }
+    }
+
+    let gc = Gc::new(());
+    assert!(!Gc::has_weak_refs(&gc));
+    let weak = Gc::downgrade(&gc);
+    let gc2 = gc.clone();
+    assert!(Gc::has_weak_refs(&gc));
+    assert!(!weak.is_dangling());
+
+    drop(gc);
+    assert!(!weak.is_dangling());
+    drop(gc2);
+    assert!(weak.is_dangling());
+    assert!(weak.upgrade().is_none());
+
+    // a weakly-referenced cycle only dangles once it has been collected
+    let cycle = Gc::new(Cycle(RefCell::new(None)));
+    *cycle.0.borrow_mut() = Some(cycle.clone());
+    let weak = Gc::downgrade(&cycle);
+    drop(cycle);
+    assert!(!weak.is_dangling());
+    collect();
+    assert!(weak.is_dangling());
+}
+
+#[test]
+/// Test the observer pattern, where a subject keeps a list of weak references to its observers.
+fn prune_dead_observers() {
+    static SUBJECT_DROPS: AtomicUsize = AtomicUsize::new(0);
+
+    struct Subject {
+        observers: RefCell<Vec<Weak<Observer>>>,
+        cycle: RefCell<Option<Gc<Subject>>>,
+    }
+
+    struct Observer {
+        id: usize,
+        subject: Gc<Subject>,
+    }
+
+    impl Drop for Subject {
+        fn drop(&mut self) {
+            SUBJECT_DROPS.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    unsafe impl Collectable for Subject {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.observers.accept(visitor)?;
+            self.cycle.accept(visitor)
+        }
+    }
+
+    unsafe impl Collectable for Observer {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.subject.accept(visitor)
+        }
+    }
+
+    let subject = Gc::new(Subject {
+        observers: RefCell::new(Vec::new()),
+        cycle: RefCell::new(None),
+    });
+    *subject.cycle.borrow_mut() = Some(subject.clone());
+    let mut observers: Vec<Gc<Observer>> = (0..4)
+        .map(|id| {
+            Gc::new(Observer {
+                id,
+                subject: subject.clone(),
+            })
+        })
+        .collect();
+    subject
+        .observers
+        .borrow_mut()
+        .extend(observers.iter().map(Gc::downgrade));
+
+    observers.remove(2);
+    observers.remove(0);
+    subject.observers.prune_dead();
+    let ids: Vec<usize> = subject
+        .observers
+        .borrow()
+        .iter()
+        .map(|weak| weak.upgrade().unwrap().id)
+        .collect();
+    assert_eq!(ids, [1, 3]);
+
+    drop(observers);
+    subject.observers.prune_dead();
+    assert!(subject.observers.borrow().is_empty());
+
+    drop(subject);
+    collect();
+    assert_eq!(SUBJECT_DROPS.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn get_mut() {
+    let mut gc = Gc::new(1);
+    *Gc::get_mut(&mut gc).unwrap() = 2;
+    assert_eq!(*gc, 2);
+
+    let gc2 = gc.clone();
+    assert!(Gc::get_mut(&mut gc).is_none());
+    drop(gc2);
+    assert!(Gc::get_mut(&mut gc).is_some());
+
+    let weak = Gc::downgrade(&gc);
+    assert!(Gc::get_mut(&mut gc).is_none());
+    drop(weak);
+    assert_eq!(Gc::get_mut(&mut gc), Some(&mut 2));
+}
+
+#[test]
+fn get_mut_while_collecting() {
+    thread_local! {
+        static OTHER: RefCell<Option<Gc<i32>>> = const { RefCell::new(None) };
+    }
+    static GOT_MUT: AtomicBool = AtomicBool::new(true);
+
+    OTHER.with(|o| *o.borrow_mut() = Some(Gc::new(0)));
+    make_garbage(|| {
+        OTHER.with(|o| {
+            let mut o = o.borrow_mut();
+            let other = o.as_mut().unwrap();
+            GOT_MUT.store(Gc::get_mut(other).is_some(), Ordering::Relaxed);
+        });
+    });
+    collect();
+    assert!(!GOT_MUT.load(Ordering::Relaxed));
+    OTHER.with(|o| assert!(Gc::get_mut(o.borrow_mut().as_mut().unwrap()).is_some()));
+}
+
+#[test]
+fn try_deref() {
+    static N_DEAD: AtomicUsize = AtomicUsize::new(0);
+    static N_LIVE: AtomicUsize = AtomicUsize::new(0);
+
+    struct Pair {
+        live: Gc<i32>,
+        other: RefCell<Option<Gc<Pair>>>,
+    }
+
+    impl Drop for Pair {
+        fn drop(&mut self) {
+            assert_eq!(Gc::try_deref(&self.live), Some(&7));
+            N_LIVE.fetch_add(1, Ordering::Relaxed);
+            if Gc::try_deref(self.other.borrow().as_ref().unwrap()).is_none() {
+                N_DEAD.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    unsafe impl Collectable for Pair {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.live.accept(visitor)?;
+            self.other.accept(visitor)
+        }
+    }
+
+    let live = Gc::new(7);
+    assert_eq!(Gc::try_deref(&live), Some(&7));
+
+    let a = Gc::new(Pair {
+        live: live.clone(),
+        other: RefCell::new(None),
+    });
+    let b = Gc::new(Pair {
+        live: live.clone(),
+        other: RefCell::new(Some(a.clone())),
+    });
+    assert!(Gc::try_deref(&b).is_some());
+    *a.other.borrow_mut() = Some(b);
+    drop(a);
+    collect();
+
+    assert_eq!(N_LIVE.load(Ordering::Relaxed), 2);
+    assert_eq!(N_DEAD.load(Ordering::Relaxed), 2);
+    assert_eq!(Gc::try_deref(&live), Some(&7));
+}
+
+#[test]
+fn is_dead() {
+    static N_DEAD: AtomicUsize = AtomicUsize::new(0);
+    static N_DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+    struct Node {
+        label: Gc<String>,
+        next: RefCell<Option<Gc<Node>>>,
+    }
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            assert!(!Gc::is_dead(&self.label));
+            assert_eq!(*self.label, "label");
+            let next = self.next.borrow();
+            let next = next.as_ref().unwrap();
+            if Gc::is_dead(next) {
+                N_DEAD.fetch_add(1, Ordering::Relaxed);
+            } else {
+                // a living neighbour can be used as normal
+                let _ = &next.label;
+            }
+            N_DROPPED.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.label.accept(visitor)?;
+            self.next.accept(visitor)
+        }
+    }
+
+    let label = Gc::new(String::from("label"));
+    let a = Gc::new(Node {
+        label: label.clone(),
+        next: RefCell::new(None),
+    });
+    let b = Gc::new(Node {
+        label: label.clone(),
+        next: RefCell::new(Some(a.clone())),
+    });
+    assert!(!Gc::is_dead(&a));
+    *a.next.borrow_mut() = Some(b);
+    drop(a);
+    collect();
+
+    assert_eq!(N_DROPPED.load(Ordering::Relaxed), 2);
+    assert_eq!(N_DEAD.load(Ordering::Relaxed), 2);
+    assert!(!Gc::is_dead(&label));
+}
+
+#[test]
+fn new_cyclic() {
+    static DROP_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    struct Node {
+        me: Weak<Node>,
+        cycle: RefCell<Option<Gc<Node>>>,
+    }
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            DROP_COUNT.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.me.accept(visitor)?;
+            self.cycle.accept(visitor)
+        }
+    }
+
+    let gc = Gc::new_cyclic(|me| {
+        assert!(me.upgrade().is_none());
+        Node {
+            me: me.clone(),
+            cycle: RefCell::new(None),
+        }
+    });
+    assert_eq!(Gc::strong_count(&gc), 1);
+    assert!(Gc::has_weak_refs(&gc));
+
+    let upgraded = gc.me.upgrade().unwrap();
+    assert!(std::ptr::eq(addr_of!(*upgraded), addr_of!(*gc)));
+    *gc.cycle.borrow_mut() = Some(upgraded);
+    assert_eq!(Gc::strong_count(&gc), 2);
+
+    let weak = Gc::downgrade(&gc);
+    drop(gc);
+    collect();
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 1);
+    assert!(weak.is_dangling());
+}
+
+#[test]
+#[cfg(panic = "unwind")]
+fn new_cyclic_panic() {
+    let result = std::panic::catch_unwind(|| {
+        Gc::<()>::new_cyclic(|me| {
+            let _clone = me.clone();
+            panic!("oops");
+        })
+    });
+    assert!(result.is_err());
+}
+
+#[test]
+#[cfg(panic = "unwind")]
+fn new_cyclic_panic_on_free() {
+    static N_FREED: AtomicUsize = AtomicUsize::new(0);
+
+    fn count_free(_: AllocationId) {
+        N_FREED.fetch_add(1, Ordering::Relaxed);
+    }
+
+    set_on_free(Some(count_free));
+    let result = std::panic::catch_unwind(|| {
+        Gc::<()>::new_cyclic(|me| {
+            let _clone = me.clone();
+            panic!("oops");
+        })
+    });
+    assert!(result.is_err());
+
+    // a `Weak` which escapes the panic must not report the allocation as freed either
+    let escaped = RefCell::new(None);
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        Gc::<()>::new_cyclic(|me| {
+            *escaped.borrow_mut() = Some(me.clone());
+            panic!("oops");
+        })
+    }));
+    assert!(result.is_err());
+    let escaped = escaped.into_inner().unwrap();
+    assert!(escaped.upgrade().is_none());
+    drop(escaped);
+    set_on_free(None);
+    assert_eq!(N_FREED.load(Ordering::Relaxed), 0);
+}
+
+#[test]
+/// Test that the hasher of a `HashMap` is traced, even when it is part of a cycle.
+fn hashmap_hasher() {
+    use std::{
+        collections::hash_map::DefaultHasher,
+        hash::{BuildHasher, Hasher},
+    };
+
+    static DROP_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    struct Salt {
+        value: u64,
+        owner: RefCell<Option<Gc<Owner>>>,
+    }
+
+    struct SaltedState {
+        salt: Gc<Salt>,
+    }
+
+    struct Owner {
+        map: RefCell<HashMap<u32, u32, SaltedState>>,
+    }
+
+    impl Drop for Salt {
+        fn drop(&mut self) {
+            DROP_COUNT.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    impl Drop for Owner {
+        fn drop(&mut self) {
+            DROP_COUNT.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    impl BuildHasher for SaltedState {
+        type Hasher = DefaultHasher;
+
+        fn build_hasher(&self) -> DefaultHasher {
+            let mut hasher = DefaultHasher::new();
+            hasher.write_u64(self.salt.value);
+            hasher
+        }
+    }
+
+    unsafe impl Collectable for Salt {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.owner.accept(visitor)
+        }
+    }
+
+    unsafe impl Collectable for SaltedState {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.salt.accept(visitor)
+        }
+    }
+
+    unsafe impl Collectable for Owner {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.map.accept(visitor)
+        }
+    }
+
+    let salt = Gc::new(Salt {
+        value: 0xdead_beef,
+        owner: RefCell::new(None),
+    });
+    let owner = Gc::new(Owner {
+        map: RefCell::new(HashMap::with_hasher(SaltedState { salt: salt.clone() })),
+    });
+    owner.map.borrow_mut().insert(1, 2);
+    *salt.owner.borrow_mut() = Some(owner.clone());
+
+    // the salt is only reachable through the hasher, so it must not be collected
+    drop(salt);
+    collect();
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 0);
+    assert_eq!(owner.map.borrow().get(&1), Some(&2));
+
+    drop(owner);
+    collect();
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 2);
+}
+
+#[test]
+fn eq() {
+    let gc1 = Gc::new(String::from("foo"));
+    let gc2 = Gc::new(String::from("foo"));
+    let gc3 = Gc::new(String::from("bar"));
+    assert!(gc1 == gc2);
+    assert!(gc1 != gc3);
+    assert!(gc1 == gc1.clone());
+
+    // pointer identity wins over the values' own notion of equality
+    let nan = Gc::new(f64::NAN);
+    assert!(nan == nan.clone());
+    assert!(nan != Gc::new(f64::NAN));
+    // and ordering agrees with it
+    assert_eq!(
+        nan.partial_cmp(&nan.clone()),
+        Some(std::cmp::Ordering::Equal)
+    );
+    assert_eq!(nan.partial_cmp(&Gc::new(f64::NAN)), None);
+}
+
+#[test]
+fn eq_while_collecting() {
+    thread_local! {
+        static PAIR: RefCell<Option<(Gc<u8>, Gc<u8>)>> = const { RefCell::new(None) };
+    }
+    static RESULTS: [AtomicBool; 4] = [
+        AtomicBool::new(true),
+        AtomicBool::new(false),
+        AtomicBool::new(false),
+        AtomicBool::new(false),
+    ];
+
+    PAIR.with(|p| *p.borrow_mut() = Some((Gc::new(1), Gc::new(1))));
+    make_garbage(|| {
+        PAIR.with(|p| {
+            let p = p.borrow();
+            let (a, b) = p.as_ref().unwrap();
+            RESULTS[0].store(a == b, Ordering::Relaxed);
+            RESULTS[1].store(a == &a.clone(), Ordering::Relaxed);
+            RESULTS[2].store(
+                a.partial_cmp(b) == Some(a.cmp(b)) && a.cmp(b) == b.cmp(a).reverse(),
+                Ordering::Relaxed,
+            );
+            RESULTS[3].store(
+                a.partial_cmp(&a.clone()) == Some(std::cmp::Ordering::Equal),
+                Ordering::Relaxed,
+            );
+        });
+    });
+    collect();
+    assert!(!RESULTS[0].load(Ordering::Relaxed));
+    assert!(RESULTS.iter().skip(1).all(|r| r.load(Ordering::Relaxed)));
+    PAIR.with(|p| {
+        let (a, b) = p.borrow_mut().take().unwrap();
+        assert!(a == b);
+    });
+}
+
+#[test]
+fn cmp_while_collecting() {
+    static DROPS: AtomicUsize = AtomicUsize::new(0);
+    static CONSISTENT: AtomicBool = AtomicBool::new(true);
+
+    struct Node {
+        id: u8,
+        links: RefCell<Vec<Gc<Node>>>,
+    }
+
+    impl PartialEq for Node {
+        fn eq(&self, other: &Self) -> bool {
+            self.id == other.id
+        }
+    }
+
+    impl Eq for Node {}
+
+    impl PartialOrd for Node {
+        fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Node {
+        fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+            self.id.cmp(&other.id)
+        }
+    }
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            // one of the two links is always the node being dropped, so comparing by value would
+            // panic
+            let links = self.links.borrow();
+            let (a, b) = (&links[0], &links[1]);
+            let ord = a.cmp(b);
+            let consistent = ord != std::cmp::Ordering::Equal
+                && a.partial_cmp(b) == Some(ord)
+                && b.cmp(a) == ord.reverse()
+                && (a == b) == (ord == std::cmp::Ordering::Equal);
+            if !consistent {
+                CONSISTENT.store(false, Ordering::Relaxed);
+            }
+            DROPS.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.links.accept(visitor)
+        }
+    }
+
+    let a = Gc::new(Node {
+        id: 0,
+        links: RefCell::new(Vec::new()),
+    });
+    let b = Gc::new(Node {
+        id: 0,
+        links: RefCell::new(Vec::new()),
+    });
+    *a.links.borrow_mut() = vec![a.clone(), b.clone()];
+    *b.links.borrow_mut() = vec![a.clone(), b.clone()];
+    drop((a, b));
+    collect();
+    assert_eq!(DROPS.load(Ordering::Relaxed), 2);
+    assert!(CONSISTENT.load(Ordering::Relaxed));
+}
+
+#[test]
+fn collect_on_drop() {
+    static DROP_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    struct Node {
+        children: RefCell<Vec<Gc<Node>>>,
+        parent: RefCell<Option<Gc<Node>>>,
+    }
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            DROP_COUNT.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.children.accept(visitor)?;
+            self.parent.accept(visitor)
+        }
+    }
+
+    let new_node = || {
+        Gc::new(Node {
+            children: RefCell::new(Vec::new()),
+            parent: RefCell::new(None),
+        })
+    };
+
+    {
+        let root = CollectOnDrop::new(new_node());
+        for _ in 0..3 {
+            let child = new_node();
+            *child.parent.borrow_mut() = Some(Gc::clone(&root));
+            root.children.borrow_mut().push(child);
+        }
+        collect();
+        assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 0);
+    }
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 4);
+
+    let root = CollectOnDrop::into_inner(CollectOnDrop::new(new_node()));
+    *root.parent.borrow_mut() = Some(root.clone());
+    assert_eq!(Gc::strong_count(&root), 2);
+    drop(root);
+    collect();
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 5);
+}
+
+#[test]
+fn write_string() {
+    fn write_through(w: &mut impl std::fmt::Write, n: u8, s: &str) -> std::fmt::Result {
+        write!(w, "{n}-{s}")
+    }
+
+    let mut buf = Gc::new(RefCell::new(String::new()));
+    let shared = buf.clone();
+    write_through(&mut buf, 1, "two").unwrap();
+    write!(shared, "-{:.1}", 3.0).unwrap();
+    assert_eq!(*(*buf).borrow(), "1-two-3.0");
+
+    // writing while the buffer is borrowed must fail rather than panic
+    let borrow = (*buf).borrow();
+    assert!(write!(shared, "!").is_err());
+    assert!(write_through(&mut shared.clone(), 4, "five").is_err());
+    drop(borrow);
+    assert_eq!(*(*shared).borrow(), "1-two-3.0");
+}
+
+#[test]
+fn fmt_forwarding() {
+    assert_eq!(format!("{}", Gc::new(42)), "42");
+    assert_eq!(format!("{:x}", Gc::new(255u8)), "ff");
+    assert_eq!(format!("{:#X}", Gc::new(255u8)), "0xFF");
+    assert_eq!(format!("{:08b}", Gc::new(5u8)), "00000101");
+    assert_eq!(format!("{:o}", Gc::new(8u8)), "10");
+    assert_eq!(format!("{:e}", Gc::new(1500.0)), "1.5e3");
+    assert_eq!(format!("{:>5}", Gc::new(String::from("ab"))), "   ab");
+    assert_eq!(format!("{:?}", Gc::new("ab")), "\"ab\"");
+    assert_eq!(format!("{:?}", Gc::new(vec![Gc::new(1)])), "[1]");
+}
+
+#[test]
+/// Test that cycles through the keys and values of hash maps and sets are collected.
+fn hash_collections() {
+    use std::{
+        collections::HashSet,
+        hash::{Hash, Hasher},
+    };
+
+    static DROP_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    /// A set element which is hashed by its name alone.
+    struct Named(&'static str, Gc<Node>);
+
+    struct Node {
+        by_name: RefCell<HashMap<String, Gc<Node>>>,
+        by_key: RefCell<HashMap<Named, u8>>,
+        set: RefCell<HashSet<Named>>,
+    }
+
+    impl PartialEq for Named {
+        fn eq(&self, other: &Self) -> bool {
+            self.0 == other.0
+        }
+    }
+
+    impl Eq for Named {}
+
+    impl Hash for Named {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            self.0.hash(state);
+        }
+    }
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            DROP_COUNT.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    unsafe impl Collectable for Named {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.1.accept(visitor)
+        }
+    }
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.by_name.accept(visitor)?;
+            self.by_key.accept(visitor)?;
+            self.set.accept(visitor)
+        }
+    }
+
+    let new_node = || {
+        Gc::new(Node {
+            by_name: RefCell::new(HashMap::new()),
+            by_key: RefCell::new(HashMap::new()),
+            set: RefCell::new(HashSet::new()),
+        })
+    };
+
+    // a cycle through a map value
+    let node = new_node();
+    node.by_name
+        .borrow_mut()
+        .insert(String::from("me"), node.clone());
+    drop(node);
+    collect();
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 1);
+
+    // a cycle through a map key
+    let node = new_node();
+    node.by_key
+        .borrow_mut()
+        .insert(Named("me", node.clone()), 0);
+    drop(node);
+    collect();
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 2);
+
+    // a cycle through a set element
+    let node = new_node();
+    node.set.borrow_mut().insert(Named("me", node.clone()));
+    drop(node);
+    collect();
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 3);
+}
+
+#[test]
+fn finalizer() {
+    static ORDER: AtomicUsize = AtomicUsize::new(0);
+    static DROPPED_AT: AtomicUsize = AtomicUsize::new(0);
+    static FINALIZED_AT: AtomicUsize = AtomicUsize::new(0);
+    static N_FINALIZED: AtomicUsize = AtomicUsize::new(0);
+
+    struct Node(RefCell<Option<Gc<Node>>>);
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            DROPPED_AT.store(ORDER.fetch_add(1, Ordering::Relaxed), Ordering::Relaxed);
+        }
+    }
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.0.accept(visitor)
+        }
+    }
+
+    let gc = Gc::new(Node(RefCell::new(None)));
+    *gc.0.borrow_mut() = Some(gc.clone());
+    register_finalizer(
+        &gc,
+        Box::new(|| {
+            // finalizers run outside of the collection, so they may use `Gc`s freely
+            assert!(!COLLECTING.with(Cell::get));
+            drop(Gc::new(()));
+            FINALIZED_AT.store(ORDER.fetch_add(1, Ordering::Relaxed), Ordering::Relaxed);
+            N_FINALIZED.fetch_add(1, Ordering::Relaxed);
+        }),
+    );
+    drop(gc);
+    assert_eq!(N_FINALIZED.load(Ordering::Relaxed), 0);
+    collect();
+    assert_eq!(N_FINALIZED.load(Ordering::Relaxed), 1);
+    assert!(DROPPED_AT.load(Ordering::Relaxed) < FINALIZED_AT.load(Ordering::Relaxed));
+    collect();
+    assert_eq!(N_FINALIZED.load(Ordering::Relaxed), 1);
+
+    // finalizers also run when the last reference is dropped, and not at all after `try_unwrap`
+    let gc = Gc::new(());
+    register_finalizer(
+        &gc,
+        Box::new(|| {
+            N_FINALIZED.fetch_add(1, Ordering::Relaxed);
+        }),
+    );
+    drop(gc);
+    assert_eq!(N_FINALIZED.load(Ordering::Relaxed), 2);
+
+    let gc = Gc::new(());
+    register_finalizer(
+        &gc,
+        Box::new(|| {
+            N_FINALIZED.fetch_add(1, Ordering::Relaxed);
+        }),
+    );
+    assert!(Gc::try_unwrap(gc).is_ok());
+    drop(Gc::new(()));
+    assert_eq!(N_FINALIZED.load(Ordering::Relaxed), 2);
+}
+
+#[test]
+fn finalizer_while_collecting() {
+    thread_local! {
+        static OTHER: RefCell<Option<Gc<u8>>> = const { RefCell::new(None) };
+    }
+    static FINALIZED_WHILE_COLLECTING: AtomicBool = AtomicBool::new(true);
+
+    let other = Gc::new(0);
+    register_finalizer(
+        &other,
+        Box::new(|| {
+            FINALIZED_WHILE_COLLECTING.store(COLLECTING.with(Cell::get), Ordering::Relaxed);
+        }),
+    );
+    OTHER.with(|o| *o.borrow_mut() = Some(other));
+    // the living `Gc` is dropped by the destructor of a collected value
+    make_garbage(|| OTHER.with(|o| drop(o.borrow_mut().take())));
+    collect();
+    assert!(!FINALIZED_WHILE_COLLECTING.load(Ordering::Relaxed));
+}
+
+#[test]
+/// Test that cycles through ordered maps and sets and through deques are collected.
+fn ordered_collections() {
+    use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+    static DROP_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    /// A set element which is ordered by its rank alone.
+    struct Ranked(u32, Gc<Node>);
+
+    struct Node {
+        children: RefCell<BTreeMap<u32, Gc<Node>>>,
+        by_rank: RefCell<BTreeMap<Ranked, u8>>,
+        ranked: RefCell<BTreeSet<Ranked>>,
+        queue: RefCell<VecDeque<Gc<Node>>>,
+    }
+
+    impl PartialEq for Ranked {
+        fn eq(&self, other: &Self) -> bool {
+            self.0 == other.0
+        }
+    }
+
+    impl Eq for Ranked {}
+
+    impl PartialOrd for Ranked {
+        fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Ranked {
+        fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+            self.0.cmp(&other.0)
+        }
+    }
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            DROP_COUNT.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    unsafe impl Collectable for Ranked {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.1.accept(visitor)
+        }
+    }
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.children.accept(visitor)?;
+            self.by_rank.accept(visitor)?;
+            self.ranked.accept(visitor)?;
+            self.queue.accept(visitor)
+        }
+    }
+
+    let new_node = || {
+        Gc::new(Node {
+            children: RefCell::new(BTreeMap::new()),
+            by_rank: RefCell::new(BTreeMap::new()),
+            ranked: RefCell::new(BTreeSet::new()),
+            queue: RefCell::new(VecDeque::new()),
+        })
+    };
+
+    // a cycle through a map value, spanning two nodes
+    let parent = new_node();
+    let child = new_node();
+    parent.children.borrow_mut().insert(0, child.clone());
+    child.children.borrow_mut().insert(0, parent.clone());
+    drop((parent, child));
+    collect();
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 2);
+
+    // a cycle through a map key
+    let node = new_node();
+    node.by_rank.borrow_mut().insert(Ranked(0, node.clone()), 0);
+    drop(node);
+    collect();
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 3);
+
+    // a cycle through a set element
+    let node = new_node();
+    node.ranked.borrow_mut().insert(Ranked(0, node.clone()));
+    drop(node);
+    collect();
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 4);
+
+    // a cycle through a deque
+    let node = new_node();
+    node.queue.borrow_mut().push_back(node.clone());
+    drop(node);
+    collect();
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 5);
+}
+
+#[test]
+fn cmp_inner() {
+    let gc = Gc::new(5);
+    assert!(gc == 5);
+    assert!(5 == gc);
+    assert!(gc != 6);
+    assert!(gc < 6);
+    assert!(4 < gc);
+    assert!(gc < Gc::new(6));
+    assert_eq!(Gc::new(1).cmp(&Gc::new(2)), std::cmp::Ordering::Less);
+
+    let s = Gc::new(String::from("foo"));
+    assert!(s == String::from("foo"));
+    assert!(String::from("foo") == s);
+
+    let mut gcs = [Gc::new(3), Gc::new(1), Gc::new(2)];
+    gcs.sort();
+    assert!(gcs.iter().map(|gc| **gc).eq([1, 2, 3]));
+}
+
+#[test]
+fn external_roots() {
+    use std::mem::ManuallyDrop;
+
+    thread_local! {
+        /// A stand-in for the stack of a virtual machine, holding pointers without a reference
+        /// count.
+        static VM_STACK: RefCell<Vec<ManuallyDrop<Gc<Node>>>> = const { RefCell::new(Vec::new()) };
+    }
+    static DROP_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    struct Node(RefCell<Option<Gc<Node>>>);
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            DROP_COUNT.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.0.accept(visitor)
+        }
+    }
+
+    fn report_vm_stack(roots: &mut ExternalRoots<'_>) {
+        VM_STACK.with(|stack| {
+            for gc in stack.borrow().iter() {
+                roots.visit(&**gc);
+            }
+        });
+    }
+
+    let gc = Gc::new(Node(RefCell::new(None)));
+    *gc.0.borrow_mut() = Some(gc.clone());
+    VM_STACK.with(|stack| {
+        stack
+            .borrow_mut()
+            .push(ManuallyDrop::new(Gc { ptr: gc.ptr }));
+    });
+    drop(gc);
+
+    // the only reference to the allocation from outside itself has no reference count
+    set_external_roots(Some(report_vm_stack));
+    collect();
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 0);
+    VM_STACK.with(|stack| assert!(stack.borrow()[0].0.borrow().is_some()));
+
+    VM_STACK.with(|stack| stack.borrow_mut().clear());
+    collect();
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 1);
+    set_external_roots(None);
+}
+
+#[test]
+/// Test that cycles through tuples are collected.
+fn tuples() {
+    static DROP_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    struct Expr {
+        pair: RefCell<Option<(Gc<Expr>, Gc<Expr>)>>,
+        triple: RefCell<Option<(u8, Gc<Expr>, String)>>,
+    }
+
+    impl Drop for Expr {
+        fn drop(&mut self) {
+            DROP_COUNT.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    unsafe impl Collectable for Expr {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.pair.accept(visitor)?;
+            self.triple.accept(visitor)
+        }
+    }
+
+    let new_expr = || {
+        Gc::new(Expr {
+            pair: RefCell::new(None),
+            triple: RefCell::new(None),
+        })
+    };
+
+    // a cycle through a 2-tuple
+    let lhs = new_expr();
+    let rhs = new_expr();
+    *lhs.pair.borrow_mut() = Some((rhs.clone(), lhs.clone()));
+    drop((lhs, rhs));
+    collect();
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 2);
+
+    // a cycle through a 3-tuple
+    let expr = new_expr();
+    *expr.triple.borrow_mut() = Some((0, expr.clone(), String::from("x")));
+    drop(expr);
+    collect();
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 3);
+
+    // a cycle through a tuple allocated directly in a `Gc`
+    let expr = new_expr();
+    let tuple: Gc<(Gc<Expr>, u8)> = Gc::new((expr.clone(), 7));
+    *expr.triple.borrow_mut() = Some((0, expr.clone(), String::new()));
+    drop(expr);
+    collect();
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 3);
+    assert_eq!(tuple.1, 7);
+    assert!(tuple.0.triple.borrow().is_some());
+    drop(tuple);
+    collect();
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 4);
+
+    // the largest supported tuple
+    let expr = new_expr();
+    *expr.triple.borrow_mut() = Some((0, expr.clone(), String::new()));
+    let big = Gc::new((0u8, 1u8, 2u8, 3u8, 4u8, 5u8, 6u8, 7u8, 8u8, 9u8, 10u8, expr));
+    drop(big);
+    collect();
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 5);
+}
+
+#[test]
+fn map() {
+    static DROPPED: AtomicBool = AtomicBool::new(false);
+
+    struct Record {
+        id: u32,
+        name: String,
+    }
+
+    impl Drop for Record {
+        fn drop(&mut self) {
+            DROPPED.store(true, Ordering::Relaxed);
+        }
+    }
+
+    unsafe impl Collectable for Record {
+        fn accept<V: Visitor>(&self, _: &mut V) -> Result<(), ()> {
+            Ok(())
+        }
+    }
+
+    let record = Gc::new(Record {
+        id: 7,
+        name: String::from("seven"),
+    });
+    let id = Gc::map(record.clone(), |record| &record.id);
+    assert_eq!(*id, 7);
+    assert_eq!(Gc::strong_count(&record), 2);
+
+    drop(record);
+    collect();
+    assert!(!DROPPED.load(Ordering::Relaxed));
+    assert_eq!(*id, 7);
+    assert_eq!(GcProjection::owner(&id).name, "seven");
+
+    drop(id);
+    collect();
+    assert!(DROPPED.load(Ordering::Relaxed));
+}
+
+#[test]
+fn split2() {
+    static DROP_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    struct Node {
+        next: RefCell<Option<GcProjection<(Node, u8), u8>>>,
+    }
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            DROP_COUNT.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.next.accept(visitor)
+        }
+    }
+
+    let gc = Gc::new((String::from("hello"), vec![1u8, 2]));
+    let weak = Gc::downgrade(&gc);
+    let (text, bytes) = Gc::split2(&gc);
+    assert_eq!(Gc::strong_count(&gc), 3);
+    drop(gc);
+
+    assert_eq!(text.len(), 5);
+    drop(text);
+    assert!(!weak.is_dangling());
+    assert_eq!(*bytes, [1, 2]);
+    assert_eq!(GcProjection::owner(&bytes).0, "hello");
+    drop(bytes);
+    assert!(weak.is_dangling());
+
+    // a cycle through a projection is collected
+    let gc = Gc::new((
+        Node {
+            next: RefCell::new(None),
+        },
+        0,
+    ));
+    let (_, number) = Gc::split2(&gc);
+    *gc.0.next.borrow_mut() = Some(number);
+    drop(gc);
+    collect();
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+/// Test that deterministic collection drops the members of a cycle in the same order every time.
+fn deterministic_drop_order() {
+    thread_local! {
+        static DROP_ORDER: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+    }
+
+    struct Labeled {
+        label: u8,
+        next: RefCell<Vec<Gc<Labeled>>>,
+    }
+
+    impl Drop for Labeled {
+        fn drop(&mut self) {
+            DROP_ORDER.with(|order| order.borrow_mut().push(self.label));
+        }
+    }
+
+    unsafe impl Collectable for Labeled {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.next.accept(visitor)
+        }
+    }
+
+    let run = || {
+        let nodes: Vec<Gc<Labeled>> = (0..8)
+            .map(|label| {
+                Gc::new(Labeled {
+                    label,
+                    next: RefCell::new(Vec::new()),
+                })
+            })
+            .collect();
+        // a ring, plus a few chords
+        for (i, node) in nodes.iter().enumerate() {
+            node.next
+                .borrow_mut()
+                .push(Gc::clone(&nodes[(i + 1) % nodes.len()]));
+        }
+        nodes[2].next.borrow_mut().push(Gc::clone(&nodes[6]));
+        nodes[5].next.borrow_mut().push(Gc::clone(&nodes[1]));
+        drop(nodes);
+        collect();
+        DROP_ORDER.with(RefCell::take)
+    };
+
+    // automatic collections depend on how many `Gc`s were dropped before each run, so only collect
+    // explicitly
+    set_collect_condition(|_| false);
+    set_deterministic(true);
+    let first = run();
+    assert_eq!(first.len(), 8);
+    for _ in 0..32 {
+        assert_eq!(run(), first);
+    }
+    set_deterministic(false);
+    set_collect_condition(default_collect_condition);
+}
+
+#[test]
+fn into_vec() {
+    static CLONE_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Debug, PartialEq)]
+    struct Counted(u8);
+
+    impl Clone for Counted {
+        fn clone(&self) -> Self {
+            CLONE_COUNT.fetch_add(1, Ordering::Relaxed);
+            Counted(self.0)
+        }
+    }
+
+    unsafe impl Collectable for Counted {
+        fn accept<V: Visitor>(&self, _: &mut V) -> Result<(), ()> {
+            Ok(())
+        }
+    }
+
+    let gc = Gc::new(vec![Counted(1), Counted(2)]);
+    let gc2 = gc.clone();
+    let Err(gc) = Gc::into_vec(gc) else {
+        panic!("`into_vec` must fail while the allocation is shared");
+    };
+
+    // shared: the elements are cloned, and the allocation is untouched
+    let shared: Vec<Counted> = gc.into_iter().collect();
+    assert_eq!(shared, [Counted(1), Counted(2)]);
+    assert_eq!(CLONE_COUNT.load(Ordering::Relaxed), 2);
+    assert_eq!(Gc::strong_count(&gc2), 1);
+    assert_eq!(gc2.len(), 2);
+
+    // unique: the elements are moved out
+    let weak = Gc::downgrade(&gc2);
+    let unique: Vec<Counted> = gc2.into_iter().collect();
+    assert_eq!(unique, [Counted(1), Counted(2)]);
+    assert_eq!(CLONE_COUNT.load(Ordering::Relaxed), 2);
+    assert!(weak.upgrade().is_none());
+
+    let gc = Gc::new(vec![Counted(3)]);
+    assert_eq!(Gc::into_vec(gc).ok(), Some(vec![Counted(3)]));
+}
+
+#[test]
+fn allocate_during_collection() {
+    static FRESH_DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+    struct Fresh(RefCell<Option<Gc<Fresh>>>);
+
+    unsafe impl Collectable for Fresh {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.0.accept(visitor)
+        }
+    }
+
+    impl Drop for Fresh {
+        fn drop(&mut self) {
+            FRESH_DROPPED.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    thread_local! {
+        static RESCUED: RefCell<Option<Gc<Fresh>>> = const { RefCell::new(None) };
+    }
+
+    struct Doomed(RefCell<Option<Gc<Doomed>>>);
+
+    unsafe impl Collectable for Doomed {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.0.accept(visitor)
+        }
+    }
+
+    impl Drop for Doomed {
+        fn drop(&mut self) {
+            // a new cycle, which is marked dirty while the collection is still underway
+            let fresh = Gc::new(Fresh(RefCell::new(None)));
+            *fresh.0.borrow_mut() = Some(fresh.clone());
+            RESCUED.with(|r| *r.borrow_mut() = Some(fresh.clone()));
+        }
+    }
+
+    let doomed = Gc::new(Doomed(RefCell::new(None)));
+    *doomed.0.borrow_mut() = Some(doomed.clone());
+    drop(doomed);
+    collect();
+
+    // the new allocation survived the collection which created it, and is usable
+    let fresh = RESCUED.with(|r| r.borrow().clone()).unwrap();
+    assert!(fresh.0.borrow().is_some());
+    assert_eq!(Gc::strong_count(&fresh), 3);
+    collect();
+    assert_eq!(FRESH_DROPPED.load(Ordering::Relaxed), 0);
+
+    // once it is unreachable, it is collected like any other
+    drop(fresh);
+    RESCUED.with(|r| r.borrow_mut().take());
+    collect();
+    assert_eq!(FRESH_DROPPED.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn tracked_drops() {
+    struct Cyclic(RefCell<Option<Gc<Cyclic>>>);
+
+    unsafe impl Collectable for Cyclic {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.0.accept(visitor)
+        }
+    }
+
+    // none of these ordinary drops may trip the debug assertions in `Gc::drop`
+    let gc = Gc::new(5);
+    let clones = Gc::clone_n(&gc, 3);
+    drop(gc);
+    drop(clones);
+
+    let a = Gc::new(Cyclic(RefCell::new(None)));
+    let b = Gc::new(Cyclic(RefCell::new(Some(a.clone()))));
+    *a.0.borrow_mut() = Some(b.clone());
+    let weak = Gc::downgrade(&a);
+    drop(a);
+    drop(b);
+    // the collector drops `Gc`s to zombies while freeing the cycle
+    collect();
+    assert!(weak.upgrade().is_none());
+
+    let any = Gc::into_any(Gc::new(String::from("erased")));
+    drop(any);
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic = "dropped a `Gc<u8>` to an allocation which was already freed"]
+fn double_drop() {
+    let mut gc = ManuallyDrop::new(Gc::new(1u8));
+    // the weak reference keeps the allocation's memory valid for the second drop
+    let _weak = Gc::downgrade(&gc);
+    unsafe {
+        ManuallyDrop::drop(&mut gc);
+        ManuallyDrop::drop(&mut gc);
+    }
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic = "dropped a `Gc<u8>` while no `Gc`s were living on this thread"]
+fn drop_foreign_gc() {
+    // the allocation is leaked by the other thread, so its memory stays valid
+    let addr = std::thread::spawn(|| Gc::into_gcbox_ptr(Gc::new(1u8)).as_ptr() as usize)
+        .join()
+        .unwrap();
+    drop(unsafe { Gc::from_gcbox_ptr(NonNull::new(addr as *mut GcBox<u8>).unwrap()) });
+}
+
+#[test]
+fn new_acyclic() {
+    static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+    struct Leaf;
+
+    unsafe impl Collectable for Leaf {
+        fn accept<V: Visitor>(&self, _: &mut V) -> Result<(), ()> {
+            Ok(())
+        }
+    }
+
+    impl crate::__private::AcyclicSealed for Leaf {}
+    impl Acyclic for Leaf {}
+
+    impl Drop for Leaf {
+        fn drop(&mut self) {
+            DROPPED.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    let gc = Gc::new_acyclic(Leaf);
+    let gc2 = gc.clone();
+    let weak = Gc::downgrade(&gc);
+    assert!(Gc::has_weak_refs(&gc));
+
+    // dropping a shared `Gc` does not leave any work for the collector
+    drop(gc);
+    assert!(!DUMPSTER.with(|d| d.is_dirty(gc2.ptr)));
+    assert_eq!(Gc::strong_count(&gc2), 1);
+    assert!(weak.upgrade().is_some());
+
+    // the weak count is unaffected by the flag
+    drop(weak);
+    assert!(!Gc::has_weak_refs(&gc2));
+    let mut gc2 = gc2;
+    assert!(Gc::get_mut(&mut gc2).is_some());
+
+    // the allocation is freed as soon as the last `Gc` is dropped, without a collection
+    let weak = Gc::downgrade(&gc2);
+    drop(gc2);
+    assert_eq!(DROPPED.load(Ordering::Relaxed), 1);
+    assert!(weak.upgrade().is_none());
+}
+
+#[test]
+fn checked_increment() {
+    assert_eq!(super::checked_increment(1, 1), Some(2));
+    assert_eq!(super::checked_increment(1, 0), Some(1));
+    assert_eq!(
+        super::checked_increment(usize::MAX - 2, 1),
+        Some(usize::MAX - 1)
+    );
+    // `usize::MAX` is reserved for static allocations
+    assert_eq!(super::checked_increment(usize::MAX - 1, 1), None);
+    assert_eq!(super::checked_increment(2, usize::MAX - 1), None);
+    assert_eq!(
+        super::checked_increment(usize::MAX - 1, usize::MAX - 1),
+        None
+    );
+}
+
+#[test]
+fn checked_weak_increment() {
+    // the lowest bit which is used as a flag
+    let lowest_flag = super::WEAK_FLAGS & super::WEAK_FLAGS.wrapping_neg();
+    assert_eq!(super::checked_weak_increment(1), Some(2));
+    assert_eq!(
+        super::checked_weak_increment(lowest_flag - 2),
+        Some(lowest_flag - 1)
+    );
+    assert_eq!(super::checked_weak_increment(lowest_flag - 1), None);
+}
+
+#[test]
+fn assert_unique() {
+    let gc = Gc::new(5);
+    Gc::assert_unique(&gc);
+    let other = gc.clone();
+    drop(other);
+    Gc::assert_unique(&gc);
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic = "expected a unique `Gc<i32>`, but its strong count is 2"]
+fn assert_unique_shared() {
+    let gc = Gc::new(5);
+    let _other = gc.clone();
+    Gc::assert_unique(&gc);
+}
+
+#[test]
+fn iterate_by_reference() {
+    let gc = Gc::new(vec![1u8, 2, 3, 4]);
+    let mut sum = 0;
+    for x in &gc {
+        sum += x;
+    }
+    assert_eq!(sum, 10);
+    // the `Gc` is only borrowed, so it can still be used
+    assert_eq!((&gc).into_iter().map(|&x| u32::from(x)).sum::<u32>(), 10);
+
+    let array = Gc::new([1u8, 2, 3, 4]);
+    assert_eq!((&array).into_iter().sum::<u8>(), 10);
+    assert_eq!(Gc::strong_count(&array), 1);
+}
+
+#[test]
+fn free_list() {
+    static DROP_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    struct Node {
+        data: [u64; 4],
+        next: RefCell<Option<Gc<Node>>>,
+    }
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            DROP_COUNT.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.next.accept(visitor)
+        }
+    }
+
+    let new_node = |x| {
+        Gc::new(Node {
+            data: [x; 4],
+            next: RefCell::new(None),
+        })
+    };
+
+    set_free_list_capacity(2);
+    let hits = free_list_hits();
+
+    // a freed allocation is reused, without any trace of its old contents
+    let gc = new_node(1);
+    let addr = std::ptr::from_ref::<Node>(&gc);
+    drop(gc);
+    let gc = new_node(2);
+    assert_eq!(free_list_hits(), hits + 1);
+    assert_eq!(std::ptr::from_ref::<Node>(&gc), addr);
+    assert_eq!(gc.data, [2; 4]);
+    assert!(gc.next.borrow().is_none());
+    assert_eq!(Gc::strong_count(&gc), 1);
+    assert!(!Gc::has_weak_refs(&gc));
+
+    // an allocation with a weak reference to it is not reused while that reference lives
+    let weak = Gc::downgrade(&gc);
+    drop(gc);
+    let gc = new_node(3);
+    assert_eq!(free_list_hits(), hits + 1);
+    assert!(weak.upgrade().is_none());
+    drop(weak);
+    drop(gc);
+
+    // collected cycles are recycled too, and later allocations still work normally
+    let drops = DROP_COUNT.load(Ordering::Relaxed);
+    let gc1 = new_node(4);
+    let gc2 = new_node(5);
+    *gc1.next.borrow_mut() = Some(gc2.clone());
+    *gc2.next.borrow_mut() = Some(gc1.clone());
+    drop((gc1, gc2));
+    collect();
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), drops + 2);
+
+    let hits = free_list_hits();
+    let gc1 = new_node(6);
+    let gc2 = new_node(7);
+    let gc3 = new_node(8);
+    assert_eq!(free_list_hits(), hits + 2);
+    assert_eq!([gc1.data[0], gc2.data[0], gc3.data[0]], [6, 7, 8]);
+    *gc3.next.borrow_mut() = Some(gc3.clone());
+    drop((gc1, gc2, gc3));
+    collect();
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), drops + 5);
+
+    set_free_list_capacity(0);
+    drop(new_node(9));
+    let hits = free_list_hits();
+    drop(new_node(10));
+    assert_eq!(free_list_hits(), hits);
+}
+
+#[test]
+/// Test that a collection only inspects the allocations which were recently dropped, and still
+/// finds new cycles among them.
+fn collect_recent_only() {
+    struct Node(RefCell<Option<Gc<Node>>>);
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.0.accept(visitor)
+        }
+    }
+
+    let heap: Vec<Gc<u64>> = (0..1000).map(Gc::new).collect();
+    for gc in &heap {
+        drop(gc.clone());
+    }
+    assert_eq!(collect_stats().n_scanned, 1000);
+
+    // a cycle created after the heap became stable
+    let a = Gc::new(Node(RefCell::new(None)));
+    let b = Gc::new(Node(RefCell::new(Some(a.clone()))));
+    let c = Gc::new(Node(RefCell::new(Some(b.clone()))));
+    *a.0.borrow_mut() = Some(c.clone());
+    drop((a, b, c));
+
+    let stats = collect_stats();
+    assert_eq!(stats.n_freed, 3);
+    assert_eq!(stats.n_scanned, 3);
+    assert_eq!(*heap[999], 999);
+}
+
+#[test]
+fn on_free() {
+    static N_FREED: AtomicUsize = AtomicUsize::new(0);
+
+    struct Node(RefCell<Option<Gc<Node>>>);
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.0.accept(visitor)
+        }
+    }
+
+    fn count_free(_: AllocationId) {
+        assert!(COLLECTING.with(Cell::get));
+        N_FREED.fetch_add(1, Ordering::Relaxed);
+    }
+
+    set_on_free(Some(count_free));
+
+    // the last `Gc` is dropped
+    let gc = Gc::new(1);
+    drop(gc.clone());
+    assert_eq!(N_FREED.load(Ordering::Relaxed), 0);
+    drop(gc);
+    assert_eq!(N_FREED.load(Ordering::Relaxed), 1);
+    assert!(!COLLECTING.with(Cell::get));
+
+    // the memory outlives the value while a `Weak` exists
+    let gc = Gc::new(2);
+    let weak = Gc::downgrade(&gc);
+    drop(gc);
+    assert_eq!(N_FREED.load(Ordering::Relaxed), 1);
+    drop(weak);
+    assert_eq!(N_FREED.load(Ordering::Relaxed), 2);
+
+    // a cycle is collected
+    let a = Gc::new(Node(RefCell::new(None)));
+    let b = Gc::new(Node(RefCell::new(Some(a.clone()))));
+    *a.0.borrow_mut() = Some(b);
+    drop(a);
+    collect();
+    assert_eq!(N_FREED.load(Ordering::Relaxed), 4);
+
+    set_on_free(None);
+    drop(Gc::new(3));
+    assert_eq!(N_FREED.load(Ordering::Relaxed), 4);
+}
+
+#[test]
+fn collect_bounded_eventually_collects() {
+    static DROP_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    struct Node(RefCell<Option<Gc<Node>>>);
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            DROP_COUNT.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.0.accept(visitor)
+        }
+    }
+
+    set_collect_condition(|_| false);
+
+    // living allocations which were marked dirty before the cycle, and so are traced first
+    let living: Vec<Gc<u32>> = (0..50).map(Gc::new).collect();
+    for gc in &living {
+        drop(gc.clone());
+    }
+
+    // a large cycle, every member of which is dirty
+    let first = Gc::new(Node(RefCell::new(None)));
+    let mut last = first.clone();
+    for _ in 1..100 {
+        let node = Gc::new(Node(RefCell::new(Some(last))));
+        last = node;
+    }
+    *first.0.borrow_mut() = Some(last);
+    drop(first);
+
+    let mut n_calls = 0;
+    let mut n_freed = 0;
+    while DUMPSTER.with(Dumpster::n_dirty) > 0 {
+        let stats = collect_bounded(10);
+        assert!(stats.n_freed == 0 || stats.n_freed == 100);
+        n_freed += stats.n_freed;
+        n_calls += 1;
+        assert!(n_calls <= 15, "bounded collections made no progress");
+    }
+    assert!(n_calls > 5);
+    assert_eq!(n_freed, 100);
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 100);
+    assert_eq!(DUMPSTER.with(Dumpster::n_bytes_dirty), 0);
+    assert_eq!(*living[49], 49);
+
+    set_collect_condition(default_collect_condition);
+}
+
+#[test]
+fn collect_bounded_small_budget() {
+    static DROP_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    struct Node(RefCell<Option<Gc<Node>>>);
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            DROP_COUNT.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.0.accept(visitor)
+        }
+    }
+
+    /// Make a garbage cycle of two nodes.
+    fn make_cycle() {
+        let a = Gc::new(Node(RefCell::new(None)));
+        let b = Gc::new(Node(RefCell::new(Some(a.clone()))));
+        *a.0.borrow_mut() = Some(b);
+    }
+
+    set_collect_condition(|_| false);
+    make_cycle();
+    make_cycle();
+    assert_eq!(DUMPSTER.with(Dumpster::n_dirty), 2);
+
+    let stats = collect_bounded(1);
+    assert_eq!(stats.n_freed, 2);
+    assert_eq!(stats.n_cycles, 1);
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 2);
+    assert_eq!(DUMPSTER.with(Dumpster::n_dirty), 1);
+
+    assert_eq!(collect_bounded(0), CollectStats::default());
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 2);
+
+    collect();
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 4);
+    set_collect_condition(default_collect_condition);
+}
+
+#[test]
+fn new_many() {
+    static DROP_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    struct Node {
+        id: usize,
+        next: RefCell<Option<Gc<Node>>>,
+    }
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            DROP_COUNT.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.next.accept(visitor)
+        }
+    }
+
+    let before = DUMPSTER.with(|d| d.n_refs_living.get());
+    let nodes = Gc::new_many((0..5).map(|id| Node {
+        id,
+        next: RefCell::new(None),
+    }));
+    assert_eq!(DUMPSTER.with(|d| d.n_refs_living.get()), before + 5);
+    assert_eq!(
+        nodes.iter().map(|n| n.id).collect::<Vec<_>>(),
+        [0, 1, 2, 3, 4]
+    );
+    assert!(nodes.iter().all(|n| Gc::strong_count(n) == 1));
+
+    // link the nodes into a ring, which only the collector can free
+    for (i, node) in nodes.iter().enumerate() {
+        *node.next.borrow_mut() = Some(nodes[(i + 1) % nodes.len()].clone());
+    }
+    let extra = nodes[0].clone();
+    drop(nodes);
+    collect();
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 0);
+    drop(extra);
+    collect();
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 5);
+    assert_eq!(DUMPSTER.with(|d| d.n_refs_living.get()), before);
+
+    // a panicking iterator leaves the bookkeeping consistent
+    let result = std::panic::catch_unwind(|| {
+        Gc::new_many((0..3).map(|id| {
+            assert!(id < 2, "out of nodes");
+            Node {
+                id,
+                next: RefCell::new(None),
+            }
+        }))
+    });
+    assert!(result.is_err());
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 7);
+    assert_eq!(DUMPSTER.with(|d| d.n_refs_living.get()), before);
+}
+
+#[test]
+fn save_and_restore_config() {
+    static DROP_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    struct Cycle(RefCell<Option<Gc<Cycle>>>);
+
+    impl Drop for Cycle {
+        fn drop(&mut self) {
+            DROP_COUNT.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    unsafe impl Collectable for Cycle {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.0.accept(visitor)
+        }
+    }
+
+    let default = get_config();
+
+    set_collect_condition(|_| false);
+    set_free_list_capacity(4);
+    set_leak_threshold(Some(1000));
+    let saved = get_config();
+
+    set_collect_condition(|_| true);
+    set_free_list_capacity(0);
+    set_leak_threshold(None);
+    set_deterministic(true);
+    set_config(saved);
+
+    let config = get_config();
+    assert_eq!(config.free_list_capacity, 4);
+    assert_eq!(config.leak_threshold, Some(1000));
+    assert!(!config.deterministic);
+
+    // the free list is back in use
+    let hits = free_list_hits();
+    drop(Gc::new(1u64));
+    drop(Gc::new(2u64));
+    assert_eq!(free_list_hits(), hits + 1);
+
+    // and so is the condition which never collects on its own
+    let gc = Gc::new(Cycle(RefCell::new(None)));
+    *gc.0.borrow_mut() = Some(gc.clone());
+    drop(gc);
+    for _ in 0..10 {
+        drop(Gc::new(()));
+    }
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 0);
+
+    set_config(default);
+    collect();
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 1);
+    assert_eq!(get_config().free_list_capacity, 0);
+}
+
+#[test]
+fn weak_cache() {
+    static N_MADE: AtomicUsize = AtomicUsize::new(0);
+
+    struct Node {
+        next: RefCell<Option<Gc<Node>>>,
+    }
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.next.accept(visitor)
+        }
+    }
+
+    let make = || {
+        N_MADE.fetch_add(1, Ordering::Relaxed);
+        Node {
+            next: RefCell::new(None),
+        }
+    };
+
+    let mut cache = WeakCache::new();
+    let gc1 = cache.get_or_insert_with("a", make);
+    let gc2 = cache.get_or_insert_with("a", make);
+    assert!(std::ptr::eq(addr_of!(*gc1), addr_of!(*gc2)));
+    assert_eq!(N_MADE.load(Ordering::Relaxed), 1);
+
+    // once the cached value is collected, it is rebuilt
+    *gc1.next.borrow_mut() = Some(gc1.clone());
+    drop((gc1, gc2));
+    collect();
+    assert!(cache.get(&"a").is_none());
+    let gc3 = cache.get_or_insert_with("a", make);
+    assert_eq!(N_MADE.load(Ordering::Relaxed), 2);
+    assert!(cache.get(&"a").is_some());
+
+    // dead entries are eventually pruned
+    for i in 0..100 {
+        drop(cache.get_or_insert_with(if i % 2 == 0 { "even" } else { "odd" }, make));
+    }
+    let mut numbers = WeakCache::new();
+    for i in 0..100 {
+        drop(numbers.get_or_insert_with(i, make));
+    }
+    assert!(numbers.len() < 100);
+    numbers.prune();
+    assert!(numbers.is_empty());
+    assert_eq!(cache.len(), 3);
+    drop(gc3);
+}
+
+#[test]
+fn hash_by_value_and_address() {
+    use std::{collections::HashMap, hash::Hasher};
+
+    // `Gc` keys are hashed and compared by value, so equal values collide
+    let mut by_value = HashMap::new();
+    by_value.insert(Gc::new(String::from("a")), 1);
+    by_value.insert(Gc::new(String::from("a")), 2);
+    by_value.insert(Gc::new(String::from("b")), 3);
+    assert_eq!(by_value.len(), 2);
+    assert_eq!(by_value[&Gc::new(String::from("a"))], 2);
+
+    // `ByAddress` keys are hashed and compared by allocation, so only clones collide
+    let a1 = Gc::new(String::from("a"));
+    let a2 = Gc::new(String::from("a"));
+    let mut by_address = HashMap::new();
+    by_address.insert(ByAddress(a1.clone()), 1);
+    by_address.insert(ByAddress(a2.clone()), 2);
+    by_address.insert(ByAddress(a1.clone()), 3);
+    assert_eq!(by_address.len(), 2);
+    assert_eq!(by_address[&ByAddress(a1.clone())], 3);
+    assert_eq!(by_address[&ByAddress(a2.clone())], 2);
+    assert!(!by_address.contains_key(&ByAddress(Gc::new(String::from("a")))));
+
+    // equal `ByAddress`es always hash equally
+    let hash = |key: &ByAddress<String>| {
+        let mut hasher = std::hash::DefaultHasher::new();
+        key.hash(&mut hasher);
+        hasher.finish()
+    };
+    assert_eq!(ByAddress(a1.clone()), ByAddress(a1.clone()));
+    assert_eq!(hash(&ByAddress(a1.clone())), hash(&ByAddress(a1)));
+}
+
+#[test]
+fn collect_stats_cycles() {
+    struct Node {
+        next: RefCell<Vec<Gc<Node>>>,
+    }
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.next.accept(visitor)
+        }
+    }
+
+    let new_node = || {
+        Gc::new(Node {
+            next: RefCell::new(Vec::new()),
+        })
+    };
+
+    set_collect_condition(|_| false);
+    collect();
+
+    // a single 2-cycle
+    let gc1 = new_node();
+    let gc2 = new_node();
+    gc1.next.borrow_mut().push(gc2.clone());
+    gc2.next.borrow_mut().push(gc1.clone());
+    drop((gc1, gc2));
+    let stats = collect_stats();
+    assert_eq!(stats.n_freed, 2);
+    assert_eq!(stats.n_cycles, 1);
+    assert!(stats.n_scanned >= 2);
+
+    // two separate cycles, one of which has a tail, plus a surviving allocation
+    let a = new_node();
+    a.next.borrow_mut().push(a.clone());
+    let b1 = new_node();
+    let b2 = new_node();
+    b1.next.borrow_mut().push(b2.clone());
+    b2.next.borrow_mut().push(b1.clone());
+    b2.next.borrow_mut().push(new_node());
+    let survivor = new_node();
+    drop(survivor.clone());
+    drop((a, b1, b2));
+    let stats = collect_stats();
+    assert_eq!(stats.n_freed, 4);
+    assert_eq!(stats.n_cycles, 2);
+    assert!(stats.n_scanned >= 5);
+
+    assert_eq!(collect_stats().n_freed, 0);
+    set_collect_condition(default_collect_condition);
+}
+
+#[test]
+fn collect_guard() {
+    static DROP_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    assert_eq!(size_of::<CollectGuard>(), 0);
+    {
+        let _guard = super::collect_on_drop();
+        for _ in 0..3 {
+            make_garbage(|| {
+                DROP_COUNT.fetch_add(1, Ordering::Relaxed);
+            });
+        }
+    }
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 3);
+
+    // a guard dropped during a collection does not start another one
+    {
+        let _guard = super::collect_on_drop();
+        make_garbage(|| {
+            drop(super::collect_on_drop());
+            DROP_COUNT.fetch_add(1, Ordering::Relaxed);
+        });
+    }
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 4);
+}
+
+#[test]
+/// Collect cycles through large `Vec`s whose destructors reallocate the `Vec`s of surviving
+/// allocations.
+/// Run this under Miri to check that the collector never reads a `Vec` while its contents are
+/// being dropped or reallocated.
+fn vec_cycle_stress() {
+    static DROP_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    struct Node {
+        children: RefCell<Vec<Gc<Node>>>,
+        /// A surviving node whose `Vec` is grown when this node is dropped.
+        survivor: Option<Gc<Node>>,
+    }
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            DROP_COUNT.fetch_add(1, Ordering::Relaxed);
+            if let Some(survivor) = &self.survivor {
+                // force a reallocation of a living `Vec` in the middle of the collection
+                let mut children = survivor.children.borrow_mut();
+                children.shrink_to_fit();
+                children.push(survivor.clone());
+            }
+        }
+    }
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.children.accept(visitor)?;
+            self.survivor.accept(visitor)
+        }
+    }
+
+    let (n_nodes, n_edges) = if cfg!(miri) { (16, 8) } else { (256, 64) };
+    fastrand::seed(520);
+
+    let survivor = Gc::new(Node {
+        children: RefCell::new(Vec::new()),
+        survivor: None,
+    });
+    for round in 0..4 {
+        let nodes: Vec<Gc<Node>> = (0..n_nodes)
+            .map(|i| {
+                Gc::new(Node {
+                    children: RefCell::new(Vec::with_capacity(n_edges)),
+                    survivor: (i % 4 == round).then(|| survivor.clone()),
+                })
+            })
+            .collect();
+        for node in &nodes {
+            let mut children = node.children.borrow_mut();
+            for _ in 0..n_edges {
+                children.push(nodes[fastrand::usize(0..n_nodes)].clone());
+            }
+        }
+        drop(nodes);
+        collect();
+        assert_eq!(DROP_COUNT.load(Ordering::Relaxed), (round + 1) * n_nodes);
+    }
+    assert_eq!(survivor.children.borrow().len(), n_nodes);
+
+    drop(survivor);
+    collect();
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 4 * n_nodes + 1);
+}
+
+#[test]
+fn gcbox_ptr() {
+    static DROP_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    struct Node {
+        next: RefCell<Option<Gc<Node>>>,
+    }
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            DROP_COUNT.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.next.accept(visitor)
+        }
+    }
+
+    let gc = Gc::new(Node {
+        next: RefCell::new(None),
+    });
+    *gc.next.borrow_mut() = Some(gc.clone());
+    let n_living = DUMPSTER.with(|d| d.n_refs_living.get());
+
+    // round-tripping through a pointer neither creates nor destroys a reference
+    let ptr = Gc::into_gcbox_ptr(gc);
+    assert_eq!(DUMPSTER.with(|d| d.n_refs_living.get()), n_living);
+    let gc = unsafe { Gc::from_gcbox_ptr(ptr) };
+    assert_eq!(Gc::strong_count(&gc), 2);
+    assert_eq!(Gc::as_gcbox_ptr(&gc), ptr);
+
+    let gc2 = unsafe { Gc::from_gcbox_ptr_cloned(ptr) };
+    assert_eq!(Gc::strong_count(&gc), 3);
+    assert_eq!(DUMPSTER.with(|d| d.n_refs_living.get()), n_living + 1);
+    drop(gc2);
+
+    // a reference held only as a pointer keeps the cycle alive
+    let ptr = Gc::into_gcbox_ptr(gc);
+    collect();
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 0);
+
+    drop(unsafe { Gc::from_gcbox_ptr(ptr) });
+    collect();
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn allocations_tracked() {
+    struct Node {
+        next: RefCell<Option<Gc<Node>>>,
+        _payload: [u8; 64],
+    }
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.next.accept(visitor)
+        }
+    }
+
+    const N: usize = 10;
+    let info = CollectInfo { _private: () };
+
+    set_collect_condition(|_| false);
+    collect();
+    assert_eq!(info.n_allocations_tracked(), 0);
+    assert_eq!(info.approximate_bytes_tracked(), 0);
+
+    // a ring of `N` nodes, each of which becomes tracked when the `Gc` made for it is dropped
+    let first = Gc::new(Node {
+        next: RefCell::new(None),
+        _payload: [0; 64],
+    });
+    let mut last = first.clone();
+    for _ in 1..N {
+        let node = Gc::new(Node {
+            next: RefCell::new(None),
+            _payload: [0; 64],
+        });
+        *last.next.borrow_mut() = Some(node.clone());
+        last = node;
+    }
+    *last.next.borrow_mut() = Some(first.clone());
+    drop((first, last));
+
+    assert_eq!(info.n_allocations_tracked(), N);
+    assert_eq!(
+        info.approximate_bytes_tracked(),
+        N * size_of::<GcBox<Node>>()
+    );
+
+    collect();
+    assert_eq!(info.n_allocations_tracked(), 0);
+    assert_eq!(info.approximate_bytes_tracked(), 0);
+
+    // an allocation freed by reference counting is no longer tracked
+    let gc = Gc::new(Node {
+        next: RefCell::new(None),
+        _payload: [0; 64],
+    });
+    drop(gc.clone());
+    assert_eq!(info.n_allocations_tracked(), 1);
+    drop(gc);
+    assert_eq!(info.n_allocations_tracked(), 0);
+    assert_eq!(info.approximate_bytes_tracked(), 0);
+    set_collect_condition(default_collect_condition);
+}
+
+#[test]
+fn deref_panic_names_type() {
+    struct Bad {
+        cycle: RefCell<Option<Gc<Bad>>>,
+    }
+
+    unsafe impl Collectable for Bad {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.cycle.accept(visitor)
+        }
+    }
+
+    impl Drop for Bad {
+        fn drop(&mut self) {
+            // the only `Gc` to this allocation points to itself, which is being collected
+            let _ = &**self.cycle.borrow().as_ref().unwrap();
+        }
+    }
+
+    let bad = Gc::new(Bad {
+        cycle: RefCell::new(None),
+    });
+    *bad.cycle.borrow_mut() = Some(bad.clone());
+    drop(bad);
+
+    let payload = std::panic::catch_unwind(collect).unwrap_err();
+    let message = payload.downcast_ref::<String>().unwrap();
+    assert!(message.contains("already-collected"));
+    assert!(message.contains(std::any::type_name::<Bad>()));
+}
+
+#[test]
+fn collect_at_most_every_interval() {
+    use std::{
+        rc::Rc,
+        time::{Duration, Instant},
+    };
+
+    static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+    let clock = Rc::new(Cell::new(Instant::now()));
+    let now = {
+        let clock = Rc::clone(&clock);
+        move || clock.get()
+    };
+    set_collect_condition_boxed(collect_at_most_every_with_clock(
+        Duration::from_secs(10),
+        now,
+    ));
+
+    // within the interval, no amount of churn starts a collection
+    make_garbage(|| {
+        DROPPED.fetch_add(1, Ordering::Relaxed);
+    });
+    clock.set(clock.get() + Duration::from_secs(9));
+    for _ in 0..100 {
+        drop(Gc::new(()));
+    }
+    assert_eq!(DROPPED.load(Ordering::Relaxed), 0);
+
+    // the first drop after the interval collects
+    clock.set(clock.get() + Duration::from_secs(1));
+    drop(Gc::new(()));
+    assert_eq!(DROPPED.load(Ordering::Relaxed), 1);
+
+    // and the interval starts over
+    make_garbage(|| {
+        DROPPED.fetch_add(1, Ordering::Relaxed);
+    });
+    clock.set(clock.get() + Duration::from_secs(5));
+    drop(Gc::new(()));
+    assert_eq!(DROPPED.load(Ordering::Relaxed), 1);
+    clock.set(clock.get() + Duration::from_secs(5));
+    drop(Gc::new(()));
+    assert_eq!(DROPPED.load(Ordering::Relaxed), 2);
+
+    set_collect_condition(default_collect_condition);
+}
+
+#[test]
+fn downcast() {
+    let gc = Gc::new(5u32);
+    let any_gc: Gc<dyn AnyCollectable> = Gc::into_any(gc.clone());
+
+    let any_gc = Gc::downcast::<u8>(any_gc).unwrap_err();
+    assert_eq!(any_gc.downcast_ref::<u32>(), Some(&5));
+
+    let downcast: Gc<u32> = Gc::downcast(any_gc).unwrap();
+    assert_eq!(Gc::as_gcbox_ptr(&gc), Gc::as_gcbox_ptr(&downcast));
+    assert_eq!(Gc::strong_count(&gc), 2);
+}
+
+#[test]
+fn fold_reachable() {
+    struct Node {
+        weight: u32,
+        edges: RefCell<Vec<Gc<Node>>>,
+    }
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.edges.accept(visitor)
+        }
+    }
+
+    let new_node = |weight| {
+        Gc::new(Node {
+            weight,
+            edges: RefCell::new(Vec::new()),
+        })
+    };
+
+    // a diamond with a cycle back to the top, plus an unrelated leaf hanging off of `b`
+    let a = new_node(1);
+    let b = new_node(2);
+    let c = new_node(4);
+    let d = new_node(8);
+    let leaf = Gc::new(16u32);
+    a.edges.borrow_mut().extend([b.clone(), c.clone()]);
+    b.edges.borrow_mut().push(d.clone());
+    c.edges.borrow_mut().push(d.clone());
+    d.edges.borrow_mut().push(a.clone());
+
+    let count = Gc::fold_reachable(&a, 0, |n, _, _| n + 1);
+    assert_eq!(count, 4);
+
+    let sum = Gc::fold_reachable(&a, 0, |sum, value, _| {
+        sum + value.downcast_ref::<Node>().map_or(0, |node| node.weight)
+    });
+    assert_eq!(sum, 15);
+
+    // each allocation is identified by its address
+    let ids = Gc::fold_reachable(&b, Vec::new(), |mut ids, _, id| {
+        ids.push(id);
+        ids
+    });
+    let expected: Vec<usize> = [&b, &d, &a, &c]
+        .iter()
+        .map(|gc| Gc::as_gcbox_ptr(gc).as_ptr() as usize)
+        .collect();
+    assert_eq!(ids, expected);
+
+    // the folding function may cut edges without freeing what is still to be visited
+    let visited = Gc::fold_reachable(&a, 0, |n, value, _| {
+        if let Some(node) = value.downcast_ref::<Node>() {
+            node.edges.borrow_mut().clear();
+        }
+        n + 1
+    });
+    assert_eq!(visited, 1);
+    assert!(Gc::fold_reachable(&leaf, true, |_, value, _| value.is::<u32>()));
+}
+
+#[test]
+fn from_str_and_slice() {
+    static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Clone)]
+    struct Counted(u8);
+
+    unsafe impl Collectable for Counted {
+        fn accept<V: Visitor>(&self, _: &mut V) -> Result<(), ()> {
+            Ok(())
+        }
+    }
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            DROPPED.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    let s = Gc::from_str("hello");
+    assert_eq!(s.len(), 5);
+    assert_eq!(&*s, "hello");
+    assert_eq!(&*Gc::<str>::from(""), "");
+    let s2 = s.clone();
+    drop(s);
+    assert_eq!(&*s2, "hello");
+
+    let wide: Gc<[u64]> = Gc::from(&[1u64, 2, 3][..]);
+    assert_eq!(*wide, [1, 2, 3]);
+    assert_eq!(
+        Gc::allocation_layout(&wide),
+        Layout::for_value(unsafe { Gc::as_gcbox_ptr(&wide).as_ref() })
+    );
+
+    let counted = Gc::from_slice(&[Counted(1), Counted(2)]);
+    assert_eq!(DROPPED.load(Ordering::Relaxed), 2); // the originals
+    assert_eq!(counted[1].0, 2);
+    drop(counted);
+    assert_eq!(DROPPED.load(Ordering::Relaxed), 4);
+}
+
+#[test]
+fn from_slice_clone_panic() {
+    static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+    struct Bomb(bool);
+
+    unsafe impl Collectable for Bomb {
+        fn accept<V: Visitor>(&self, _: &mut V) -> Result<(), ()> {
+            Ok(())
+        }
+    }
+
+    impl Clone for Bomb {
+        fn clone(&self) -> Self {
+            assert!(!self.0, "boom");
+            Bomb(self.0)
+        }
+    }
+
+    impl Drop for Bomb {
+        fn drop(&mut self) {
+            DROPPED.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    let bombs = [Bomb(false), Bomb(false), Bomb(true)];
+    assert!(std::panic::catch_unwind(|| Gc::from_slice(&bombs)).is_err());
+    // the two clones made before the panic were dropped
+    assert_eq!(DROPPED.load(Ordering::Relaxed), 2);
+}
+
+#[test]
+fn clone_n() {
+    static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+    struct Parent;
+
+    unsafe impl Collectable for Parent {
+        fn accept<V: Visitor>(&self, _: &mut V) -> Result<(), ()> {
+            Ok(())
+        }
+    }
+
+    impl Drop for Parent {
+        fn drop(&mut self) {
+            DROPPED.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    let info = CollectInfo { _private: () };
+    let n_existing = info.n_gcs_existing();
+    let parent = Gc::new(Parent);
+    let mut handles = Gc::clone_n(&parent, 1000);
+    assert_eq!(handles.len(), 1000);
+    assert_eq!(Gc::strong_count(&parent), 1001);
+    assert_eq!(info.n_gcs_existing(), n_existing + 1001);
+    assert!(Gc::clone_n(&parent, 0).is_empty());
+
+    // the handles can be dropped in any order, independently of the original
+    drop(parent);
+    handles.drain(..500);
+    assert_eq!(Gc::strong_count(&handles[0]), 500);
+    while handles.len() > 1 {
+        handles.swap_remove(handles.len() / 2);
+    }
+    assert_eq!(DROPPED.load(Ordering::Relaxed), 0);
+    drop(handles);
+    assert_eq!(DROPPED.load(Ordering::Relaxed), 1);
+    assert_eq!(info.n_gcs_existing(), n_existing);
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn serde_round_trip() {
+    let gc = Gc::new(vec![1u32, 2, 3]);
+    let json = serde_json::to_string(&gc).unwrap();
+    assert_eq!(json, "[1,2,3]");
+
+    let back: Gc<Vec<u32>> = serde_json::from_str(&json).unwrap();
+    assert_eq!(*back, [1, 2, 3]);
+    assert_eq!(Gc::strong_count(&back), 1);
+
+    // a shared allocation is written once per `Gc`, and comes back as separate allocations
+    let pair = (gc.clone(), gc);
+    let json = serde_json::to_string(&pair).unwrap();
+    assert_eq!(json, "[[1,2,3],[1,2,3]]");
+    let (a, b): (Gc<Vec<u32>>, Gc<Vec<u32>>) = serde_json::from_str(&json).unwrap();
+    assert_eq!(a, b);
+    assert_ne!(Gc::as_gcbox_ptr(&a), Gc::as_gcbox_ptr(&b));
+}
+
+#[test]
+fn from_value_and_box() {
+    static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+    struct Counted(u32);
+
+    unsafe impl Collectable for Counted {
+        fn accept<V: Visitor>(&self, _: &mut V) -> Result<(), ()> {
+            Ok(())
+        }
+    }
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            DROPPED.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    let gc: Gc<String> = "hi".to_string().into();
+    assert_eq!(*gc, "hi");
+
+    let from_value: Gc<Counted> = Counted(1).into();
+    let from_box: Gc<Counted> = Gc::from(Box::new(Counted(2)));
+    assert_eq!(from_value.0, 1);
+    assert_eq!(from_box.0, 2);
+    // moving out of the box must not drop the value
+    assert_eq!(DROPPED.load(Ordering::Relaxed), 0);
+
+    drop(from_value);
+    assert_eq!(DROPPED.load(Ordering::Relaxed), 1);
+    drop(from_box);
+    assert_eq!(DROPPED.load(Ordering::Relaxed), 2);
+}
+
+#[test]
+#[cfg(feature = "petgraph")]
+fn petgraph_cycle() {
+    use petgraph::{stable_graph::StableGraph, Graph};
+
+    static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+    struct Owner {
+        graph: RefCell<Graph<Option<Gc<Owner>>, ()>>,
+        stable: RefCell<StableGraph<(), Option<Gc<Owner>>>>,
+    }
+
+    unsafe impl Collectable for Owner {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.graph.accept(visitor)?;
+            self.stable.accept(visitor)
+        }
+    }
+
+    impl Drop for Owner {
+        fn drop(&mut self) {
+            DROPPED.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    let new_owner = || {
+        Gc::new(Owner {
+            graph: RefCell::new(Graph::new()),
+            stable: RefCell::new(StableGraph::new()),
+        })
+    };
+
+    // a cycle through a node weight
+    let owner = new_owner();
+    let a = owner.graph.borrow_mut().add_node(None);
+    let b = owner.graph.borrow_mut().add_node(Some(owner.clone()));
+    owner.graph.borrow_mut().add_edge(a, b, ());
+    drop(owner);
+    collect();
+    assert_eq!(DROPPED.load(Ordering::Relaxed), 1);
+
+    // a cycle through an edge weight
+    let owner = new_owner();
+    let a = owner.stable.borrow_mut().add_node(());
+    let b = owner.stable.borrow_mut().add_node(());
+    owner
+        .stable
+        .borrow_mut()
+        .add_edge(a, b, Some(owner.clone()));
+    drop(owner);
+    collect();
+    assert_eq!(DROPPED.load(Ordering::Relaxed), 2);
+}
+
+#[test]
+#[cfg(feature = "metrics")]
+fn collection_latencies() {
+    thread_local! {
+        static N_REPORTED: Cell<usize> = const { Cell::new(0) };
+    }
+
+    set_collect_latency_hook(Some(|_| N_REPORTED.with(|n| n.set(n.get() + 1))));
+    #[cfg(debug_assertions)]
+    let before = super::collection_latencies().len();
+
+    for _ in 0..5 {
+        collect();
+    }
+    set_collect_latency_hook(None);
+    collect();
+
+    assert_eq!(N_REPORTED.with(Cell::get), 5);
+    #[cfg(debug_assertions)]
+    assert_eq!(super::collection_latencies().len(), before + 6);
+}
+
+#[test]
+#[cfg(feature = "allocator_api")]
+fn new_in() {
+    use std::{
+        alloc::{AllocError, Allocator, Global, Layout},
+        ptr::NonNull,
+    };
+
+    static N_ALLOCS: AtomicUsize = AtomicUsize::new(0);
+    static N_DEALLOCS: AtomicUsize = AtomicUsize::new(0);
+
+    /// An allocator which counts its allocations and deallocations.
+    struct Counting;
+
+    unsafe impl Allocator for Counting {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            N_ALLOCS.fetch_add(1, Ordering::Relaxed);
+            Global.allocate(layout)
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            N_DEALLOCS.fetch_add(1, Ordering::Relaxed);
+            Global.deallocate(ptr, layout);
+        }
+    }
+
+    struct Cycle(RefCell<Option<Gc<Cycle>>>);
+
+    unsafe impl Collectable for Cycle {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.0.accept(visitor)
+        }
+    }
+
+    // freed as soon as the last `Gc` is dropped
+    let gc = Gc::new_in(1u64, Counting);
+    let gc2 = gc.clone();
+    drop(gc);
+    assert_eq!(*gc2, 1);
+    drop(gc2);
+    assert_eq!(N_DEALLOCS.load(Ordering::Relaxed), 1);
+
+    // kept around by a `Weak` after the value is dropped
+    let gc = Gc::new_in(String::from("weak"), Counting);
+    let weak = Gc::downgrade(&gc);
+    drop(gc);
+    assert_eq!(N_DEALLOCS.load(Ordering::Relaxed), 1);
+    drop(weak);
+    assert_eq!(N_DEALLOCS.load(Ordering::Relaxed), 2);
+
+    // freed by the collector
+    let gc = Gc::new_in(Cycle(RefCell::new(None)), Counting);
+    *gc.0.borrow_mut() = Some(Gc::new_in(Cycle(RefCell::new(Some(gc.clone()))), Counting));
+    drop(gc);
+    collect();
+
+    // moved out of the allocation
+    let gc = Gc::new_in([0u8; 3], Counting);
+    assert_eq!(Gc::try_unwrap(gc).ok(), Some([0; 3]));
+
+    assert_eq!(N_ALLOCS.load(Ordering::Relaxed), 5);
+    assert_eq!(N_DEALLOCS.load(Ordering::Relaxed), 5);
+}
+
+#[test]
+fn get_mut_slice() {
+    let mut gc: Gc<[u32]> = Gc::from_slice(&[1, 2, 3]);
+    for x in Gc::get_mut_slice(&mut gc).unwrap() {
+        *x *= 10;
+    }
+    assert_eq!(*gc, [10, 20, 30]);
+
+    let gc2 = gc.clone();
+    assert!(Gc::get_mut_slice(&mut gc).is_none());
+    drop(gc2);
+
+    let weak = Gc::downgrade(&gc);
+    assert!(Gc::get_mut_slice(&mut gc).is_none());
+    drop(weak);
+    Gc::get_mut_slice(&mut gc).unwrap().swap(0, 2);
+    assert_eq!(*gc, [30, 20, 10]);
+}
+
+#[test]
+fn as_ptr() {
+    let gc = Gc::new(String::from("identity"));
+    let gc2 = gc.clone();
+    assert_eq!(Gc::as_ptr(&gc), Gc::as_ptr(&gc2));
+    assert_eq!(Gc::as_ptr(&gc), addr_of!(*gc));
+    assert_ne!(
+        Gc::as_ptr(&gc),
+        Gc::as_ptr(&Gc::new(String::from("identity")))
+    );
+
+    let slice: Gc<[u8]> = Gc::from_slice(&[1, 2, 3]);
+    assert_eq!(Gc::as_ptr(&slice), addr_of!(*slice));
+}
+
+#[test]
+#[cfg(feature = "track-allocations")]
+fn alloc_events() {
+    use std::alloc::Layout;
+
+    thread_local! {
+        static EVENTS: RefCell<Vec<AllocEvent>> = const { RefCell::new(Vec::new()) };
+    }
+
+    struct Node(RefCell<Option<Gc<Node>>>);
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.0.accept(visitor)
+        }
+    }
+
+    set_alloc_event_sink(Some(|event| EVENTS.with(|e| e.borrow_mut().push(event))));
+    let take_events = || EVENTS.with(RefCell::take);
+
+    let gc = Gc::new(7u64);
+    let id = Gc::allocation_id(&gc);
+    let size = Gc::allocation_layout(&gc).size();
+    assert_eq!(size, Layout::new::<GcBox<u64>>().size());
+    assert_eq!(
+        take_events(),
+        [AllocEvent {
+            kind: AllocEventKind::Created,
+            id,
+            size,
+            type_name: "u64",
+        }]
+    );
+    drop(gc);
+    assert_eq!(
+        take_events(),
+        [AllocEvent {
+            kind: AllocEventKind::Freed,
+            id,
+            size,
+            type_name: "u64",
+        }]
+    );
+
+    // slices report their full size, and cycles are reported when they are collected
+    let slice: Gc<[u32]> = Gc::from_slice(&[1, 2, 3]);
+    assert_eq!(take_events()[0].size, Gc::allocation_layout(&slice).size());
+    drop(slice);
+    assert_eq!(take_events()[0].kind, AllocEventKind::Freed);
+
+    let node = Gc::new(Node(RefCell::new(None)));
+    *node.0.borrow_mut() = Some(node.clone());
+    drop(node);
+    assert_eq!(take_events().len(), 1);
+    collect();
+    let events = take_events();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind, AllocEventKind::Freed);
+    assert_eq!(events[0].size, Layout::new::<GcBox<Node>>().size());
+
+    set_alloc_event_sink(None);
+    drop(Gc::new(()));
+    assert!(take_events().is_empty());
+}
+
+#[test]
+fn drop_subgraph() {
+    static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+    struct Node(RefCell<Vec<Gc<Node>>>);
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            DROPS.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.0.accept(visitor)
+        }
+    }
+
+    /// Build a ring of `n` nodes, returning a reference to one of them.
+    fn ring(n: usize) -> Gc<Node> {
+        let first = Gc::new(Node(RefCell::new(Vec::new())));
+        let mut last = first.clone();
+        for _ in 1..n {
+            let node = Gc::new(Node(RefCell::new(Vec::new())));
+            last.0.borrow_mut().push(node.clone());
+            last = node;
+        }
+        last.0.borrow_mut().push(first.clone());
+        first
+    }
+
+    collect();
+    set_collect_condition(|_| false);
+    let before = DUMPSTER.with(|d| d.n_refs_living.get());
+
+    let root = ring(5);
+    assert!(super::drop_subgraph(root));
+    assert_eq!(DROPS.load(Ordering::Relaxed), 5);
+    assert_eq!(DUMPSTER.with(|d| d.n_refs_living.get()), before);
+
+    let root = ring(5);
+    let outside = root.0.borrow()[0].clone();
+    assert!(!super::drop_subgraph(root));
+    assert_eq!(DROPS.load(Ordering::Relaxed), 5);
+    assert_eq!(outside.0.borrow().len(), 1);
+
+    // the subgraph is still collected normally once the outside reference is gone
+    drop(outside);
+    collect();
+    assert_eq!(DROPS.load(Ordering::Relaxed), 10);
+
+    set_collect_condition(default_collect_condition);
+}
+
+#[test]
+/// Check that `drop_subgraph` does not free a graph which an external root still refers to.
+fn drop_subgraph_external_roots() {
+    use std::mem::ManuallyDrop;
+
+    thread_local! {
+        /// A stand-in for the stack of a virtual machine, holding pointers without a reference
+        /// count.
+        static VM_STACK: RefCell<Vec<ManuallyDrop<Gc<Node>>>> = const { RefCell::new(Vec::new()) };
+    }
+    static DROP_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    struct Node(RefCell<Option<Gc<Node>>>);
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            DROP_COUNT.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.0.accept(visitor)
+        }
+    }
+
+    fn report_vm_stack(roots: &mut ExternalRoots<'_>) {
+        VM_STACK.with(|stack| {
+            for gc in stack.borrow().iter() {
+                roots.visit(&**gc);
+            }
+        });
+    }
+
+    let gc = Gc::new(Node(RefCell::new(None)));
+    *gc.0.borrow_mut() = Some(gc.clone());
+    VM_STACK.with(|stack| {
+        stack
+            .borrow_mut()
+            .push(ManuallyDrop::new(Gc { ptr: gc.ptr }));
+    });
+
+    set_external_roots(Some(report_vm_stack));
+    assert!(!super::drop_subgraph(gc));
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 0);
+    VM_STACK.with(|stack| assert!(stack.borrow()[0].0.borrow().is_some()));
+
+    VM_STACK.with(|stack| stack.borrow_mut().clear());
+    collect();
+    assert_eq!(DROP_COUNT.load(Ordering::Relaxed), 1);
+    set_external_roots(None);
+}
+
+#[test]
+fn gc_fn_cycle() {
+    static DROPPED: AtomicBool = AtomicBool::new(false);
+
+    type Callback = crate::GcFn<Gc<Widget>, Box<dyn Fn(&Gc<Widget>, usize) -> usize>>;
+
+    struct Widget {
+        size: usize,
+        callbacks: RefCell<Vec<Callback>>,
+    }
+
+    impl Drop for Widget {
+        fn drop(&mut self) {
+            DROPPED.store(true, Ordering::Relaxed);
+        }
+    }
+
+    unsafe impl Collectable for Widget {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.callbacks.accept(visitor)
+        }
+    }
+
+    let widget = Gc::new(Widget {
+        size: 3,
+        callbacks: RefCell::new(Vec::new()),
+    });
+    widget.callbacks.borrow_mut().push(crate::GcFn::new(
+        widget.clone(),
+        Box::new(|widget, n| widget.size * n),
+    ));
+    assert_eq!(widget.callbacks.borrow()[0].call(2), 6);
+    assert_eq!(Gc::strong_count(widget.callbacks.borrow()[0].captures()), 2);
+
+    drop(widget);
+    collect();
+    assert!(DROPPED.load(Ordering::Relaxed));
+}
+
+#[test]
+fn drain_on_thread_exit() {
+    static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+    struct Node {
+        next: RefCell<Option<Gc<Node>>>,
+    }
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            DROPPED.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.next.accept(visitor)
+        }
+    }
+
+    /// Make a cycle of two nodes which only the collector can free.
+    fn make_cycle() {
+        set_collect_condition(|_| false);
+        let a = Gc::new(Node {
+            next: RefCell::new(None),
+        });
+        let b = Gc::new(Node {
+            next: RefCell::new(Some(a.clone())),
+        });
+        *a.next.borrow_mut() = Some(b);
+    }
+
+    std::thread::spawn(make_cycle).join().unwrap();
+    assert_eq!(DROPPED.load(Ordering::Relaxed), 2);
+
+    // a panicking thread must not strand its allocations either
+    #[cfg(panic = "unwind")]
+    {
+        let result = std::thread::spawn(|| {
+            make_cycle();
+            panic!("oops");
+        })
+        .join();
+        assert!(result.is_err());
+        assert_eq!(DROPPED.load(Ordering::Relaxed), 4);
+
+        // nor may a panicking destructor abort the process at thread exit
+        std::thread::spawn(|| {
+            set_collect_condition(|_| false);
+            make_garbage(|| panic!("oops"));
+        })
+        .join()
+        .unwrap();
+    }
+}
+
+#[test]
+fn created_since_last_collect() {
+    let info = CollectInfo { _private: () };
+    set_collect_condition(|_| false);
+
+    collect();
+    assert_eq!(info.n_gcs_created_since_last_collect(), 0);
+    let gc = Gc::new(1u8);
+    assert_eq!(info.n_gcs_created_since_last_collect(), 1);
+    let gc2 = gc.clone();
+    assert_eq!(info.n_gcs_created_since_last_collect(), 2);
+    let gcs = Gc::new_many([1u8, 2, 3]);
+    assert_eq!(info.n_gcs_created_since_last_collect(), 5);
+
+    // dropping does not undo creation
+    drop((gc, gc2, gcs));
+    assert_eq!(info.n_gcs_created_since_last_collect(), 5);
+    collect();
+    assert_eq!(info.n_gcs_created_since_last_collect(), 0);
+
+    set_collect_condition(default_collect_condition);
+}
+
+#[test]
+fn last_collect_duration() {
+    use std::time::Duration;
+
+    let info = CollectInfo { _private: () };
+    set_collect_condition(|_| false);
+
+    make_garbage(|| std::thread::sleep(Duration::from_millis(20)));
+    collect();
+    assert!(info.last_collect_duration() >= Duration::from_millis(20));
+    collect();
+    assert!(info.last_collect_duration() < Duration::from_millis(20));
+
+    set_collect_condition(default_collect_condition);
+}
+
+#[test]
+fn once_cell() {
+    use std::cell::OnceCell;
+
+    static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+    struct Node {
+        cached: OnceCell<Gc<Node>>,
+    }
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            DROPPED.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.cached.accept(visitor)
+        }
+    }
+
+    let new_node = || {
+        Gc::new(Node {
+            cached: OnceCell::new(),
+        })
+    };
+
+    // an uninitialized cell holds nothing
+    let a = new_node();
+    let b = new_node();
+    drop(b.clone());
+    collect();
+    assert_eq!(DROPPED.load(Ordering::Relaxed), 0);
+
+    // a cycle through initialized cells is collected
+    a.cached.set(b.clone()).ok().unwrap();
+    b.cached.set(a.clone()).ok().unwrap();
+    drop((a, b));
+    collect();
+    assert_eq!(DROPPED.load(Ordering::Relaxed), 2);
+}
+
+#[test]
+#[cfg(feature = "lazy-cell")]
+fn lazy_cell() {
+    use std::cell::LazyCell;
+
+    static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+    /// The initializer of a [`Node`]'s lazy cell.
+    type Init = Box<dyn FnOnce() -> Option<Gc<Node>>>;
+
+    struct Node {
+        next: RefCell<Option<Gc<Node>>>,
+        lazy: LazyCell<Option<Gc<Node>>, Init>,
+    }
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            DROPPED.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.next.accept(visitor)?;
+            self.lazy.accept(visitor)
+        }
+    }
+
+    let new_node = |init: Init| {
+        Gc::new(Node {
+            next: RefCell::new(None),
+            lazy: LazyCell::new(init),
+        })
+    };
+
+    let a = new_node(Box::new(|| None));
+    let a2 = a.clone();
+    let b = new_node(Box::new(move || Some(a2)));
+    *a.next.borrow_mut() = Some(b.clone());
+
+    // the initializer of `b` holds a `Gc` to `a` which cannot be seen, so `a` is a root
+    let weak = Gc::downgrade(&b);
+    drop((a, b));
+    collect();
+    assert_eq!(DROPPED.load(Ordering::Relaxed), 0);
+
+    // once forced, the cell is visited and the cycle can be collected
+    let b = weak.upgrade().unwrap();
+    assert!(b.lazy.is_some());
+    drop(b);
+    collect();
+    assert_eq!(DROPPED.load(Ordering::Relaxed), 2);
+}
+
+#[test]
+fn gc_scope() {
+    static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+    struct Node {
+        next: RefCell<Option<Gc<Node>>>,
+    }
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            DROPPED.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    unsafe impl Collectable for Node {
+        fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.next.accept(visitor)
+        }
+    }
+
+    set_collect_condition(|_| false);
+    let before = DUMPSTER.with(|d| d.n_refs_living.get());
+
+    let nodes: Vec<Gc<Node>> = with_scope(|scope| {
+        let nodes: Vec<Gc<Node>> = (0..10)
+            .map(|_| {
+                scope.new_gc(Node {
+                    next: RefCell::new(None),
+                })
+            })
+            .collect();
+        for (i, node) in nodes.iter().enumerate() {
+            *node.next.borrow_mut() = Some(scope.clone_gc(&nodes[(i + 1) % nodes.len()]));
+        }
+        nodes
+    });
+    assert_eq!(DUMPSTER.with(|d| d.n_refs_living.get()), before + 20);
+    assert!(nodes.iter().all(|node| Gc::strong_count(node) == 2));
+    #[cfg(feature = "debug-allocations")]
+    assert!(nodes
+        .iter()
+        .all(|node| DUMPSTER.with(|d| d.is_allocated(node.ptr))));
+
+    drop(nodes);
+    collect();
+    assert_eq!(DROPPED.load(Ordering::Relaxed), 10);
+    assert_eq!(DUMPSTER.with(|d| d.n_refs_living.get()), before);
+
+    set_collect_condition(default_collect_condition);
+}
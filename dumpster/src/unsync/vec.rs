@@ -0,0 +1,266 @@
+/*
+   dumpster, a cycle-tracking garbage collector for Rust.
+   Copyright (C) 2023 Clayton Ramsey.
+
+   This program is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   This program is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Garbage-collected growable arrays.
+
+use alloc::vec::Vec;
+use core::cell::{Ref, RefCell};
+
+use crate::{Collectable, Visitor};
+
+use super::{collect::DUMPSTER, Gc};
+
+/// A garbage-collected, growable array.
+///
+/// A `GcVec` is a handle to a single garbage-collected allocation containing a vector, much like a
+/// `Gc<RefCell<Vec<T>>>`.
+/// Cloning a `GcVec` creates another handle to the same vector; it does not copy the elements.
+///
+/// Every mutation through a `GcVec` marks its allocation as dirty exactly once, no matter how many
+/// garbage-collected pointers it holds, so the next collection will examine the new contents.
+/// When the vector is traced by the collector, each of its elements is visited.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::unsync::{Gc, GcVec};
+///
+/// let children: GcVec<Gc<u32>> = GcVec::new();
+/// children.push(Gc::new(1));
+/// children.push(Gc::new(2));
+///
+/// assert_eq!(children.len(), 2);
+/// assert_eq!(**children.get(1).unwrap(), 2);
+/// assert_eq!(*children.pop().unwrap(), 2);
+/// ```
+pub struct GcVec<T: Collectable + 'static> {
+    /// The allocation holding the elements of the vector.
+    inner: Gc<RefCell<Vec<T>>>,
+}
+
+impl<T: Collectable + 'static> GcVec<T> {
+    #[must_use]
+    /// Construct a new, empty `GcVec`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::GcVec;
+    ///
+    /// let v: GcVec<u8> = GcVec::new();
+    /// assert!(v.is_empty());
+    /// ```
+    pub fn new() -> GcVec<T> {
+        GcVec::with_capacity(0)
+    }
+
+    #[must_use]
+    /// Construct a new, empty `GcVec` with space for at least `capacity` elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::GcVec;
+    ///
+    /// let v: GcVec<u8> = GcVec::with_capacity(10);
+    /// assert!(v.is_empty());
+    /// ```
+    pub fn with_capacity(capacity: usize) -> GcVec<T> {
+        GcVec {
+            inner: Gc::new(RefCell::new(Vec::with_capacity(capacity))),
+        }
+    }
+
+    #[must_use]
+    /// Get the number of elements in this vector.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the vector is currently being mutated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::GcVec;
+    ///
+    /// let v = GcVec::new();
+    /// v.push(1);
+    /// assert_eq!(v.len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.inner.borrow().len()
+    }
+
+    #[must_use]
+    /// Determine whether this vector contains no elements.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the vector is currently being mutated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::GcVec;
+    ///
+    /// let v = GcVec::new();
+    /// assert!(v.is_empty());
+    /// v.push(1);
+    /// assert!(!v.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.inner.borrow().is_empty()
+    }
+
+    /// Append `value` to the end of this vector.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the vector is currently borrowed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::GcVec;
+    ///
+    /// let v = GcVec::new();
+    /// v.push(3);
+    /// assert_eq!(*v.get(0).unwrap(), 3);
+    /// ```
+    pub fn push(&self, value: T) {
+        self.inner.borrow_mut().push(value);
+        self.notify_mutated();
+    }
+
+    #[must_use]
+    /// Remove the last element of this vector and return it, or `None` if it is empty.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the vector is currently borrowed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::GcVec;
+    ///
+    /// let v = GcVec::new();
+    /// v.push(3);
+    /// assert_eq!(v.pop(), Some(3));
+    /// assert_eq!(v.pop(), None);
+    /// ```
+    pub fn pop(&self) -> Option<T> {
+        self.inner.borrow_mut().pop()
+    }
+
+    /// Replace the element at `index` with `value`, returning the old element.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `index` is out of bounds or if the vector is currently borrowed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::GcVec;
+    ///
+    /// let v = GcVec::new();
+    /// v.push(3);
+    /// assert_eq!(v.set(0, 4), 3);
+    /// assert_eq!(*v.get(0).unwrap(), 4);
+    /// ```
+    pub fn set(&self, index: usize, value: T) -> T {
+        let old = core::mem::replace(&mut self.inner.borrow_mut()[index], value);
+        self.notify_mutated();
+        old
+    }
+
+    #[must_use]
+    /// Get a reference to the element at `index`, or `None` if it is out of bounds.
+    ///
+    /// The vector cannot be mutated while the returned reference is alive.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the vector is currently being mutated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::GcVec;
+    ///
+    /// let v = GcVec::new();
+    /// v.push(3);
+    /// assert_eq!(*v.get(0).unwrap(), 3);
+    /// assert!(v.get(1).is_none());
+    /// ```
+    pub fn get(&self, index: usize) -> Option<Ref<'_, T>> {
+        Ref::filter_map(self.inner.borrow(), |v| v.get(index)).ok()
+    }
+
+    #[must_use]
+    /// Get a reference to all the elements of this vector as a slice.
+    ///
+    /// The vector cannot be mutated while the returned reference is alive.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the vector is currently being mutated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::GcVec;
+    ///
+    /// let v = GcVec::new();
+    /// v.push(1);
+    /// v.push(2);
+    /// assert_eq!(*v.as_slice(), [1, 2]);
+    /// ```
+    pub fn as_slice(&self) -> Ref<'_, [T]> {
+        Ref::map(self.inner.borrow(), Vec::as_slice)
+    }
+
+    /// Notify the garbage collector that the contents of this vector have changed.
+    fn notify_mutated(&self) {
+        DUMPSTER.with(|d| d.mark_dirty(self.inner.ptr));
+    }
+}
+
+impl<T: Collectable + 'static> Clone for GcVec<T> {
+    /// Create another handle to the same vector.
+    /// This does not copy the elements.
+    fn clone(&self) -> Self {
+        GcVec {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T: Collectable + 'static> Default for GcVec<T> {
+    fn default() -> Self {
+        GcVec::new()
+    }
+}
+
+unsafe impl<T: Collectable + 'static> Collectable for GcVec<T> {
+    #[inline]
+    fn accept<V: Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+        self.inner.accept(visitor)
+    }
+}
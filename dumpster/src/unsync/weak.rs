@@ -0,0 +1,310 @@
+/*
+   dumpster, a cycle-tracking garbage collector for Rust.
+   Copyright (C) 2023 Clayton Ramsey.
+
+   This program is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   This program is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Weak references to garbage-collected values.
+
+use alloc::vec::Vec;
+use core::{
+    alloc::Layout,
+    cell::{Cell, RefCell},
+    fmt::Debug,
+    mem::{forget, ManuallyDrop},
+    ptr::{addr_of_mut, NonNull},
+};
+
+use crate::{Collectable, Visitor};
+
+use super::{allocate, collect::DUMPSTER, deallocate, Dumpster, Gc, GcBox};
+
+/// A non-owning reference to a garbage-collected allocation.
+///
+/// A `Weak` does not keep the value it points to alive: once every [`Gc`] to the value is gone (or
+/// the value is collected as part of an unreachable cycle), [`Weak::upgrade`] returns `None`.
+/// The memory of the allocation itself is kept around until every `Weak` to it is dropped.
+///
+/// `Weak`s are invisible to the garbage collector, so they never keep a cycle alive.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::unsync::Gc;
+///
+/// let gc = Gc::new(5);
+/// let weak = Gc::downgrade(&gc);
+/// assert_eq!(weak.upgrade().as_deref(), Some(&5));
+///
+/// drop(gc);
+/// assert!(weak.upgrade().is_none());
+/// ```
+pub struct Weak<T: Collectable + ?Sized + 'static> {
+    /// A pointer to the allocation.
+    ptr: NonNull<GcBox<T>>,
+}
+
+impl<T: Collectable + ?Sized> Gc<T> {
+    #[must_use]
+    /// Create a new [`Weak`] pointer to the allocation that `this` points to.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    ///
+    /// let gc = Gc::new(());
+    /// let weak = Gc::downgrade(&gc);
+    /// assert!(weak.upgrade().is_some());
+    /// ```
+    pub fn downgrade(this: &Gc<T>) -> Weak<T> {
+        let box_ref = unsafe { this.ptr.as_ref() };
+        box_ref.add_weak();
+        Weak { ptr: this.ptr }
+    }
+
+    /// Construct a new garbage-collected allocation whose value may refer to the allocation itself.
+    ///
+    /// `data_fn` is given a [`Weak`] to the allocation before its value exists, and returns the
+    /// value to store in it.
+    /// Upgrading the `Weak` within `data_fn` will return `None`, but it (and any clones of it) may
+    /// be stored in the value and upgraded once `new_cyclic` has returned.
+    ///
+    /// If `data_fn` panics, the allocation is freed and the panic is propagated.
+    /// Since the allocation never held a value, it is not reported to the function set by
+    /// [`set_on_free`](super::set_on_free) or to the allocation event sink.
+    /// If a clone of the `Weak` given to `data_fn` outlives the panic, the allocation is leaked
+    /// instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::{
+    ///     unsync::{Gc, Weak},
+    ///     Collectable,
+    /// };
+    ///
+    /// #[derive(Collectable)]
+    /// struct Node {
+    ///     me: Weak<Node>,
+    /// }
+    ///
+    /// let node = Gc::new_cyclic(|me| {
+    ///     assert!(me.upgrade().is_none());
+    ///     Node { me: me.clone() }
+    /// });
+    /// assert!(std::ptr::eq(&*node.me.upgrade().unwrap(), &*node));
+    /// ```
+    pub fn new_cyclic<F>(data_fn: F) -> Gc<T>
+    where
+        F: FnOnce(&Weak<T>) -> T,
+        T: Sized,
+    {
+        let ptr = allocate(Layout::new::<GcBox<T>>()).cast::<GcBox<T>>();
+        unsafe {
+            // the value is uninitialized, so the allocation starts out as a zombie which cannot be
+            // upgraded to, and whose only reference is `weak`
+            addr_of_mut!((*ptr.as_ptr()).ref_count).write(Cell::new(0));
+            addr_of_mut!((*ptr.as_ptr()).weak).write(Cell::new(1));
+        }
+        let weak = ManuallyDrop::new(Weak { ptr });
+
+        // if `data_fn` panics, the guard frees the allocation
+        let guard = FreeUninit(ptr);
+        let value = data_fn(&weak);
+        forget(guard);
+
+        unsafe {
+            addr_of_mut!((*ptr.as_ptr()).value).write(value);
+            ptr.as_ref().ref_count.set(1);
+        }
+        // the weak reference held by `weak` becomes the one held by the new strong reference
+        DUMPSTER.with(|d| {
+            d.notify_created_gc();
+            #[cfg(any(feature = "debug-allocations", feature = "track-allocations"))]
+            d.notify_allocated(ptr);
+        });
+        Gc { ptr }
+    }
+
+    #[must_use]
+    /// Determine whether any [`Weak`] pointers currently point to the same allocation as `this`.
+    ///
+    /// Weak pointers to allocations created by a [`crate::unsync::StaticGc`] are not tracked, so
+    /// this always returns `false` for them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    ///
+    /// let gc = Gc::new(());
+    /// assert!(!Gc::has_weak_refs(&gc));
+    ///
+    /// let weak = Gc::downgrade(&gc);
+    /// assert!(Gc::has_weak_refs(&gc));
+    ///
+    /// drop(weak);
+    /// assert!(!Gc::has_weak_refs(&gc));
+    /// ```
+    pub fn has_weak_refs(this: &Gc<T>) -> bool {
+        let box_ref = unsafe { this.ptr.as_ref() };
+        // the strong references collectively hold one weak reference of their own
+        !box_ref.is_static() && box_ref.weak_count() > 1
+    }
+}
+
+impl<T: Collectable + ?Sized> Weak<T> {
+    #[must_use]
+    /// Determine whether the value that this `Weak` points to has been dropped.
+    ///
+    /// Once this returns `true`, it will never return `false` again, and [`Weak::upgrade`] will
+    /// always return `None`.
+    /// This also returns `true` while the value is being dropped by the garbage collector.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    ///
+    /// let gc = Gc::new(());
+    /// let weak = Gc::downgrade(&gc);
+    /// assert!(!weak.is_dangling());
+    ///
+    /// drop(gc);
+    /// assert!(weak.is_dangling());
+    /// ```
+    pub fn is_dangling(&self) -> bool {
+        unsafe { self.ptr.as_ref() }.is_zombie()
+    }
+
+    #[must_use]
+    /// Attempt to create a [`Gc`] to the value that this `Weak` points to.
+    ///
+    /// Returns `None` if the value has already been dropped, or is being dropped by the garbage
+    /// collector.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use dumpster::unsync::Gc;
+    ///
+    /// let gc = Gc::new(1);
+    /// let weak = Gc::downgrade(&gc);
+    /// assert_eq!(*weak.upgrade().unwrap(), 1);
+    ///
+    /// drop(gc);
+    /// assert!(weak.upgrade().is_none());
+    /// ```
+    pub fn upgrade(&self) -> Option<Gc<T>> {
+        let box_ref = unsafe { self.ptr.as_ref() };
+        if box_ref.is_zombie() {
+            return None;
+        }
+        box_ref.add_strong(1);
+        DUMPSTER.with(Dumpster::notify_created_gc);
+        Some(Gc { ptr: self.ptr })
+    }
+}
+
+impl<T: Collectable + ?Sized> Clone for Weak<T> {
+    /// Create another `Weak` pointer to the same allocation.
+    fn clone(&self) -> Self {
+        let box_ref = unsafe { self.ptr.as_ref() };
+        box_ref.add_weak();
+        Weak { ptr: self.ptr }
+    }
+}
+
+/// A guard which frees the allocation made by [`Gc::new_cyclic`] if its `data_fn` panics.
+///
+/// Unlike dropping a [`Weak`], this never reports the allocation as freed, since it was never
+/// reported as created.
+struct FreeUninit<T: Collectable + 'static>(NonNull<GcBox<T>>);
+
+impl<T: Collectable + 'static> Drop for FreeUninit<T> {
+    fn drop(&mut self) {
+        // a clone of the `Weak` which outlived `data_fn` would report the allocation as freed once
+        // it is dropped, so the reference held by the guard is leaked to keep the allocation
+        // alive instead
+        if unsafe { self.0.as_ref() }.weak_count() == 1 {
+            unsafe { deallocate(self.0.cast(), Layout::new::<GcBox<T>>()) };
+        }
+    }
+}
+
+impl<T: Collectable + ?Sized> Drop for Weak<T> {
+    /// Destroy this weak pointer, freeing the allocation if it was the last reference of any kind.
+    fn drop(&mut self) {
+        let box_ref = unsafe { self.ptr.as_ref() };
+        if box_ref.is_static() {
+            return;
+        }
+        // while the value is alive, the strong references hold a weak reference of their own, so
+        // this can only free the allocation once the value is gone
+        unsafe { GcBox::release_weak(self.ptr) };
+    }
+}
+
+unsafe impl<T: Collectable + ?Sized> Collectable for Weak<T> {
+    #[inline]
+    fn accept<V: Visitor>(&self, _: &mut V) -> Result<(), ()> {
+        // weak references never keep anything alive, so the collector need not know about them
+        Ok(())
+    }
+}
+
+/// An extension trait for lists of [`Weak`]s, as used by the observer pattern.
+///
+/// A subject can hold its observers in a `RefCell<Vec<Weak<Observer>>>` without keeping them alive
+/// or forming cycles with them, since `Weak`s are never traced by the collector.
+/// The list then only needs to be cleaned up now and then to forget observers which are gone.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::unsync::{Gc, WeakVecExt};
+/// use std::cell::RefCell;
+///
+/// let observers = RefCell::new(Vec::new());
+/// let alive = Gc::new(1);
+/// let gone = Gc::new(2);
+/// observers.borrow_mut().push(Gc::downgrade(&alive));
+/// observers.borrow_mut().push(Gc::downgrade(&gone));
+///
+/// drop(gone);
+/// observers.prune_dead();
+/// assert_eq!(observers.borrow().len(), 1);
+/// ```
+pub trait WeakVecExt {
+    /// Remove every [`Weak`] whose value has been dropped from the list.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the list is currently borrowed.
+    fn prune_dead(&self);
+}
+
+impl<T: Collectable + ?Sized> WeakVecExt for RefCell<Vec<Weak<T>>> {
+    fn prune_dead(&self) {
+        self.borrow_mut().retain(|weak| !weak.is_dangling());
+    }
+}
+
+impl<T: Collectable + ?Sized> Debug for Weak<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "(Weak)")
+    }
+}
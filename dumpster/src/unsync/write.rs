@@ -0,0 +1,75 @@
+/*
+   dumpster, a cycle-tracking garbage collector for Rust.
+   Copyright (C) 2023 Clayton Ramsey.
+
+   This program is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   This program is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with this program.  If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Formatted writing into garbage-collected string buffers.
+
+use alloc::string::String;
+use core::{
+    cell::RefCell,
+    fmt::{self, Write},
+};
+
+use super::Gc;
+
+impl Write for Gc<RefCell<String>> {
+    /// Append `s` to the string that this `Gc` points to.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the string is currently borrowed.
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.try_borrow_mut().map_err(|_| fmt::Error)?.push_str(s);
+        Ok(())
+    }
+}
+
+/// An extension trait for writing formatted text into a shared garbage-collected string buffer.
+///
+/// [`Gc<RefCell<String>>`](Gc) implements [`fmt::Write`], but that requires a mutable binding.
+/// Since every `Gc` to the buffer can append to it, this trait makes [`write!`] and [`writeln!`]
+/// work through a shared reference as well.
+///
+/// # Examples
+///
+/// ```
+/// use dumpster::unsync::{Gc, GcStringExt};
+/// use std::cell::RefCell;
+///
+/// let buf = Gc::new(RefCell::new(String::new()));
+/// let other = buf.clone();
+/// write!(buf, "{} + {}", 1, 2).unwrap();
+/// writeln!(other, " = {}", 3).unwrap();
+///
+/// assert_eq!(*buf.borrow(), "1 + 2 = 3\n");
+/// ```
+pub trait GcStringExt {
+    /// Write formatted text into the buffer.
+    ///
+    /// This is usually called through the [`write!`] macro rather than directly.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the buffer is currently borrowed.
+    fn write_fmt(&self, args: fmt::Arguments<'_>) -> fmt::Result;
+}
+
+impl GcStringExt for Gc<RefCell<String>> {
+    fn write_fmt(&self, args: fmt::Arguments<'_>) -> fmt::Result {
+        Write::write_fmt(&mut self.clone(), args)
+    }
+}
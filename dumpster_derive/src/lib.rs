@@ -20,30 +20,35 @@
 #![warn(clippy::cargo)]
 #![allow(clippy::multiple_crate_versions)]
 
-use proc_macro2::TokenStream;
-use quote::{format_ident, quote, quote_spanned};
+use proc_macro2::{TokenStream, TokenTree};
+use quote::{format_ident, quote, quote_spanned, ToTokens};
 use syn::{
-    parse_macro_input, parse_quote, spanned::Spanned, Data, DeriveInput, Fields, GenericParam,
-    Generics, Ident, Index,
+    parse_macro_input, parse_quote, punctuated::Punctuated, spanned::Spanned, Data, DeriveInput,
+    Field, Fields, Generics, Ident, Index, LitStr, Path, Token, WherePredicate,
 };
 
-#[proc_macro_derive(Collectable)]
+#[proc_macro_derive(Collectable, attributes(collectable))]
 pub fn derive_collectable(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
     // name of the type being implemented
     let name = &input.ident;
 
+    let attrs = match ContainerAttrs::parse(&input) {
+        Ok(attrs) => attrs,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
     // generic parameters of the type being implemented
-    let generics = add_trait_bounds(input.generics);
+    let generics = add_trait_bounds(input.generics, &input.data, &attrs);
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
-    let do_visitor = delegate_methods(name, &input.data);
+    let do_visitor = delegate_methods(name, &input.data, &attrs);
 
     let generated = quote! {
-        unsafe impl #impl_generics dumpster::Collectable for #name #ty_generics #where_clause {
+        unsafe impl #impl_generics ::dumpster::Collectable for #name #ty_generics #where_clause {
             #[inline]
-            fn accept<V: dumpster::Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            fn accept<V: ::dumpster::Visitor>(&self, visitor: &mut V) -> ::core::result::Result<(), ()> {
                 #do_visitor
             }
         }
@@ -52,48 +57,198 @@ pub fn derive_collectable(input: proc_macro::TokenStream) -> proc_macro::TokenSt
     generated.into()
 }
 
-/// Collect the trait bounds for some generic expression.
-fn add_trait_bounds(mut generics: Generics) -> Generics {
-    for param in &mut generics.params {
-        if let GenericParam::Type(ref mut type_param) = *param {
-            type_param.bounds.push(parse_quote!(heapsize::HeapSize));
+#[proc_macro_derive(Acyclic)]
+pub fn derive_acyclic(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+
+    // bounding every type parameter which appears in a field, rather than the field types
+    // themselves, keeps recursive types from making the trait solver overflow
+    let field_types: Vec<&syn::Type> = fields(&input.data).map(|field| &field.ty).collect();
+    let used_params: Vec<Ident> = input
+        .generics
+        .type_params()
+        .map(|param| param.ident.clone())
+        .filter(|ident| {
+            field_types
+                .iter()
+                .any(|ty| mentions(ty.to_token_stream(), ident))
+        })
+        .collect();
+    let mut generics = input.generics.clone();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+    let where_clause = generics.make_where_clause();
+    for ident in used_params {
+        where_clause
+            .predicates
+            .push(parse_quote!(#ident: ::dumpster::Acyclic));
+    }
+    where_clause
+        .predicates
+        .push(parse_quote!(#name #ty_generics: ::dumpster::Collectable));
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+
+    // then every field must be acyclic under those bounds; the function takes a `Self` so that
+    // the bounds implied by the type's definition are available
+    let assertions = field_types.iter().map(|ty| {
+        quote_spanned! {ty.span() => assert_acyclic::<#ty>();}
+    });
+
+    let generated = quote! {
+        impl #impl_generics ::dumpster::__private::AcyclicSealed for #name #ty_generics
+            #where_clause {}
+        impl #impl_generics ::dumpster::Acyclic for #name #ty_generics #where_clause {}
+
+        const _: () = {
+            fn assert_acyclic<T: ::dumpster::Acyclic + ?::core::marker::Sized>() {}
+
+            #[allow(dead_code)]
+            fn assert_fields_acyclic #impl_generics (_: &#name #ty_generics) #where_clause {
+                #(#assertions)*
+            }
+        };
+    };
+
+    generated.into()
+}
+
+/// The contents of the `#[collectable(...)]` attributes on the type being derived.
+struct ContainerAttrs {
+    /// The bounds given with `#[collectable(bound = "...")]`, if any.
+    bound: Option<Punctuated<WherePredicate, Token![,]>>,
+    /// The field given with `#[collectable(unsafe_union_field = "...")]`, if any.
+    union_field: Option<Ident>,
+    /// Whether the type was marked with `#[collectable(unsafe_skip)]`.
+    skip: bool,
+}
+
+impl ContainerAttrs {
+    /// Parse the `collectable` attributes on a type.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if any `collectable` attribute is malformed, or if an
+    /// attribute which only applies to unions is used on another kind of type.
+    fn parse(input: &DeriveInput) -> syn::Result<ContainerAttrs> {
+        let mut attrs = ContainerAttrs {
+            bound: None,
+            union_field: None,
+            skip: false,
+        };
+        let is_union = matches!(input.data, Data::Union(_));
+        for attr in input
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("collectable"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("bound") {
+                    let predicates: LitStr = meta.value()?.parse()?;
+                    attrs.bound = Some(predicates.parse_with(Punctuated::parse_terminated)?);
+                    Ok(())
+                } else if is_union && meta.path.is_ident("unsafe_union_field") {
+                    let field: LitStr = meta.value()?.parse()?;
+                    attrs.union_field = Some(field.parse()?);
+                    Ok(())
+                } else if is_union && meta.path.is_ident("unsafe_skip") {
+                    attrs.skip = true;
+                    Ok(())
+                } else if is_union {
+                    Err(meta.error(
+                        "unrecognized `collectable` attribute; expected `bound`, \
+                         `unsafe_union_field`, or `unsafe_skip`",
+                    ))
+                } else {
+                    Err(meta.error("unrecognized `collectable` attribute; expected `bound`"))
+                }
+            })?;
         }
+        Ok(attrs)
+    }
+}
+
+/// Add the bounds required for the derived implementation to the generics of a type.
+///
+/// If `bound` was given with `#[collectable(bound = "...")]`, those predicates are used as-is.
+/// Otherwise, every type parameter which appears in the type of a visited field must be
+/// `Collectable`, much like the bounds inferred by `serde`.
+fn add_trait_bounds(mut generics: Generics, data: &Data, attrs: &ContainerAttrs) -> Generics {
+    if let Some(ref bound) = attrs.bound {
+        generics
+            .make_where_clause()
+            .predicates
+            .extend(bound.iter().cloned());
+        return generics;
+    }
+
+    let field_types: Vec<TokenStream> = fields(data)
+        .filter(|field| match data {
+            Data::Union(_) => !attrs.skip && field.ident == attrs.union_field,
+            _ => FieldAttrs::parse(field).map_or(true, |attrs| attrs.visits_with_accept()),
+        })
+        .map(|field| field.ty.to_token_stream())
+        .collect();
+    let used_params: Vec<Ident> = generics
+        .type_params()
+        .map(|param| param.ident.clone())
+        .filter(|ident| field_types.iter().any(|ty| mentions(ty.clone(), ident)))
+        .collect();
+
+    let where_clause = generics.make_where_clause();
+    for ident in used_params {
+        where_clause
+            .predicates
+            .push(parse_quote!(#ident: ::dumpster::Collectable));
     }
     generics
 }
 
+/// Get every field of every variant of a data type.
+fn fields(data: &Data) -> Box<dyn Iterator<Item = &Field> + '_> {
+    match data {
+        Data::Struct(data) => Box::new(data.fields.iter()),
+        Data::Enum(data) => Box::new(data.variants.iter().flat_map(|var| var.fields.iter())),
+        Data::Union(data) => Box::new(data.fields.named.iter()),
+    }
+}
+
+/// Determine whether a stream of tokens (such as a type) mentions `ident` anywhere.
+fn mentions(tokens: TokenStream, ident: &Ident) -> bool {
+    tokens.into_iter().any(|token| match token {
+        TokenTree::Ident(ref i) => i == ident,
+        TokenTree::Group(group) => mentions(group.stream(), ident),
+        TokenTree::Punct(_) | TokenTree::Literal(_) => false,
+    })
+}
+
 #[allow(clippy::too_many_lines)]
 /// Generate method implementations for [`Collectable`] for some data type.
-fn delegate_methods(name: &Ident, data: &Data) -> TokenStream {
+fn delegate_methods(name: &Ident, data: &Data, attrs: &ContainerAttrs) -> TokenStream {
     match data {
         Data::Struct(data) => match data.fields {
             Fields::Named(ref f) => {
                 let delegate_visit = f.named.iter().map(|f| {
                     let name = &f.ident;
-                    quote_spanned! {f.span() =>
-                        dumpster::Collectable::accept(
-                            &self.#name,
-                            visitor
-                        )?;
+                    match FieldAttrs::parse(f) {
+                        Ok(attrs) => attrs.visit(f, &quote! { &self.#name }),
+                        Err(e) => e.to_compile_error(),
                     }
                 });
 
-                quote! { #(#delegate_visit)* Ok(()) }
+                quote! { #(#delegate_visit)* ::core::result::Result::Ok(()) }
             }
             Fields::Unnamed(ref f) => {
                 let delegate_visit = f.unnamed.iter().enumerate().map(|(i, f)| {
                     let index = Index::from(i);
-                    quote_spanned! {f.span() =>
-                        dumpster::Collectable::accept(
-                            &self.#index,
-                            visitor
-                        )?;
+                    match FieldAttrs::parse(f) {
+                        Ok(attrs) => attrs.visit(f, &quote! { &self.#index }),
+                        Err(e) => e.to_compile_error(),
                     }
                 });
 
-                quote! { #(#delegate_visit)* Ok(()) }
+                quote! { #(#delegate_visit)* ::core::result::Result::Ok(()) }
             }
-            Fields::Unit => quote! { Ok(()) },
+            Fields::Unit => quote! { ::core::result::Result::Ok(()) },
         },
         Data::Enum(e) => {
             let mut delegate_visit = TokenStream::new();
@@ -106,70 +261,87 @@ fn delegate_methods(name: &Ident, data: &Data) -> TokenStream {
                         let mut execution_visit = TokenStream::new();
                         let mut execution_destroy = TokenStream::new();
                         for (i, name) in n.named.iter().enumerate() {
-                            let field_name = format_ident!("field{i}");
                             let field_ident = name.ident.as_ref().unwrap();
+                            let attrs = match FieldAttrs::parse(name) {
+                                Ok(attrs) => attrs,
+                                Err(e) => return e.to_compile_error(),
+                            };
+                            let skip = attrs.skip;
+                            let field_name = format_ident!("field{i}");
+                            let pattern = if skip {
+                                quote! { _ }
+                            } else {
+                                quote! { #field_name }
+                            };
                             if i == 0 {
                                 binding.extend(quote! {
-                                    #field_ident: #field_name
+                                    #field_ident: #pattern
                                 });
                             } else {
                                 binding.extend(quote! {
-                                    , #field_ident: #field_name
+                                    , #field_ident: #pattern
                                 });
                             }
+                            if skip {
+                                continue;
+                            }
 
-                            execution_visit.extend(quote! {
-                                dumpster::Collectable::accept(
-                                    #field_name,
-                                    visitor
-                                )?;
-                            });
+                            execution_visit.extend(attrs.visit(name, &quote! { #field_name }));
 
                             execution_destroy.extend(quote! {
-                                dumpster::Collectable::destroy_gcs(
+                                ::dumpster::Collectable::destroy_gcs(
                                     #field_name, destroyer
                                 );
                             });
                         }
 
                         delegate_visit.extend(
-                            quote! {#name::#var_name{#binding} => {#execution_visit Ok(())},},
+                            quote! {#name::#var_name{#binding} => {#execution_visit ::core::result::Result::Ok(())},},
                         );
                     }
                     Fields::Unnamed(u) => {
                         let mut binding = TokenStream::new();
                         let mut execution_visit = TokenStream::new();
                         let mut execution_destroy = TokenStream::new();
-                        for (i, _) in u.unnamed.iter().enumerate() {
+                        for (i, field) in u.unnamed.iter().enumerate() {
+                            let attrs = match FieldAttrs::parse(field) {
+                                Ok(attrs) => attrs,
+                                Err(e) => return e.to_compile_error(),
+                            };
+                            let skip = attrs.skip;
                             let field_name = format_ident!("field{i}");
+                            let pattern = if skip {
+                                quote! { _ }
+                            } else {
+                                quote! { #field_name }
+                            };
                             if i == 0 {
                                 binding.extend(quote! {
-                                    #field_name
+                                    #pattern
                                 });
                             } else {
                                 binding.extend(quote! {
-                                    , #field_name
+                                    , #pattern
                                 });
                             }
+                            if skip {
+                                continue;
+                            }
 
-                            execution_visit.extend(quote! {
-                                dumpster::Collectable::accept(
-                                    #field_name,
-                                    visitor
-                                )?;
-                            });
+                            execution_visit.extend(attrs.visit(field, &quote! { #field_name }));
 
                             execution_destroy.extend(quote! {
-                                dumpster::Collectable::destroy_gcs(#field_name, destroyer);
+                                ::dumpster::Collectable::destroy_gcs(#field_name, destroyer);
                             });
                         }
 
                         delegate_visit.extend(
-                            quote! {#name::#var_name(#binding) => {#execution_visit Ok(())},},
+                            quote! {#name::#var_name(#binding) => {#execution_visit ::core::result::Result::Ok(())},},
                         );
                     }
                     Fields::Unit => {
-                        delegate_visit.extend(quote! {#name::#var_name => Ok(()),});
+                        delegate_visit
+                            .extend(quote! {#name::#var_name => ::core::result::Result::Ok(()),});
                     }
                 }
             }
@@ -177,8 +349,108 @@ fn delegate_methods(name: &Ident, data: &Data) -> TokenStream {
             quote! {match self {#delegate_visit}}
         }
         Data::Union(u) => {
-            quote_spanned! {
-                u.union_token.span => compile_error!("`Collectable` must be manually implemented for unions");
+            if let Some(ref field) = attrs.union_field {
+                if !u
+                    .fields
+                    .named
+                    .iter()
+                    .any(|f| f.ident.as_ref() == Some(field))
+                {
+                    return syn::Error::new(field.span(), "no such field in this union")
+                        .to_compile_error();
+                }
+                // SAFETY: the user has promised that this field is always the active one
+                quote! {
+                    ::dumpster::Collectable::accept(unsafe { &self.#field }, visitor)?;
+                    ::core::result::Result::Ok(())
+                }
+            } else if attrs.skip {
+                quote! { ::core::result::Result::Ok(()) }
+            } else {
+                quote_spanned! {
+                    u.union_token.span => compile_error!(
+                        "`Collectable` can only be derived for a union marked with either \
+                        `#[collectable(unsafe_union_field = \"...\")]` or \
+                        `#[collectable(unsafe_skip)]`"
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// The contents of the `#[collectable(...)]` attributes on a field.
+struct FieldAttrs {
+    /// Whether the field was marked with `#[collectable(unsafe_skip)]`, meaning that it must not
+    /// be visited by the generated implementation.
+    skip: bool,
+    /// The function given with `#[collectable(trace_with = "...")]`, if any, which visits the
+    /// field in place of its own `Collectable` implementation.
+    trace_with: Option<Path>,
+}
+
+impl FieldAttrs {
+    /// Parse the `collectable` attributes on a field.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if any `collectable` attribute is malformed, or if a
+    /// field is marked with both `unsafe_skip` and `trace_with`.
+    fn parse(field: &Field) -> syn::Result<FieldAttrs> {
+        let mut attrs = FieldAttrs {
+            skip: false,
+            trace_with: None,
+        };
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("collectable"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("unsafe_skip") {
+                    attrs.skip = true;
+                    Ok(())
+                } else if meta.path.is_ident("trace_with") {
+                    let path: LitStr = meta.value()?.parse()?;
+                    attrs.trace_with = Some(path.parse()?);
+                    Ok(())
+                } else {
+                    Err(meta.error(
+                        "unrecognized `collectable` attribute; expected `unsafe_skip` or \
+                         `trace_with`",
+                    ))
+                }
+            })?;
+        }
+        if attrs.skip && attrs.trace_with.is_some() {
+            return Err(syn::Error::new(
+                field.span(),
+                "a field cannot be marked with both `unsafe_skip` and `trace_with`",
+            ));
+        }
+        Ok(attrs)
+    }
+
+    /// Determine whether the field is visited through its own `Collectable` implementation, so
+    /// that its type must implement `Collectable`.
+    fn visits_with_accept(&self) -> bool {
+        !self.skip && self.trace_with.is_none()
+    }
+
+    /// Generate the code which visits `field`, given an expression `access` for a reference to it.
+    fn visit(&self, field: &Field, access: &TokenStream) -> TokenStream {
+        if self.skip {
+            TokenStream::new()
+        } else if let Some(ref trace_with) = self.trace_with {
+            quote_spanned! {trace_with.span() =>
+                #trace_with(#access, visitor)?;
+            }
+        } else {
+            quote_spanned! {field.span() =>
+                ::dumpster::Collectable::accept(
+                    #access,
+                    visitor
+                )?;
             }
         }
     }
@@ -21,17 +21,25 @@
 #![cfg(test)]
 
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    marker::PhantomData,
+    mem::ManuallyDrop,
     sync::atomic::{AtomicU8, AtomicUsize, Ordering},
 };
 
-use dumpster::unsync::{collect, Gc};
+use dumpster::{
+    unsync::{collect, Gc},
+    Visitor,
+};
 use dumpster_derive::Collectable;
 
 #[derive(Collectable)]
+#[allow(unused)]
 struct Empty;
 
 #[derive(Collectable)]
+#[allow(unused)]
 struct UnitTuple();
 
 #[derive(Collectable)]
@@ -160,3 +168,549 @@ fn parallel_loop() {
     assert_eq!(COUNT_3.load(Ordering::Relaxed), 1);
     assert_eq!(COUNT_4.load(Ordering::Relaxed), 1);
 }
+
+#[test]
+/// Test cycles which pass through fixed-size arrays.
+fn array_cycle() {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    static QUAD_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Collectable)]
+    struct Quad {
+        children: RefCell<[Option<Gc<Quad>>; 4]>,
+    }
+
+    impl Drop for Quad {
+        fn drop(&mut self) {
+            QUAD_COUNT.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    let new_multiref = || {
+        Gc::new(MultiRef {
+            counter: &COUNT,
+            pointers: RefCell::new(Vec::new()),
+        })
+    };
+
+    // a 3-cycle, whose only entry point is stored in an array
+    let gc1 = new_multiref();
+    let gc2 = new_multiref();
+    let gc3 = new_multiref();
+    gc1.pointers.borrow_mut().push(Gc::clone(&gc2));
+    gc2.pointers.borrow_mut().push(Gc::clone(&gc3));
+    gc3.pointers.borrow_mut().push(Gc::clone(&gc1));
+    let array: Gc<[Gc<MultiRef>; 1]> = Gc::new([gc1]);
+    drop(gc2);
+    drop(gc3);
+
+    collect();
+    assert_eq!(COUNT.load(Ordering::Relaxed), 0);
+    drop(array);
+    collect();
+    assert_eq!(COUNT.load(Ordering::Relaxed), 3);
+
+    // a node whose children all point back to it
+    let root = Gc::new(Quad {
+        children: RefCell::new([None, None, None, None]),
+    });
+    for child in root.children.borrow_mut().iter_mut() {
+        let quad = Gc::new(Quad {
+            children: RefCell::new([Some(Gc::clone(&root)), None, None, None]),
+        });
+        *child = Some(quad);
+    }
+    drop(root);
+    collect();
+    assert_eq!(QUAD_COUNT.load(Ordering::Relaxed), 5);
+}
+
+#[test]
+/// Test an adjacency list of nodes, where each node stores its outgoing edges in a `HashMap`.
+fn adjacency_list() {
+    type NodeId = u32;
+
+    static COUNTS: [AtomicUsize; 4] = [
+        AtomicUsize::new(0),
+        AtomicUsize::new(0),
+        AtomicUsize::new(0),
+        AtomicUsize::new(0),
+    ];
+
+    #[derive(Collectable)]
+    struct Node {
+        id: NodeId,
+        edges: RefCell<HashMap<NodeId, Gc<Node>>>,
+    }
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            COUNTS[self.id as usize].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn connect(from: &Gc<Node>, to: &Gc<Node>) {
+        from.edges.borrow_mut().insert(to.id, to.clone());
+    }
+
+    let graph: Gc<RefCell<HashMap<NodeId, Gc<Node>>>> = Gc::new(RefCell::new(HashMap::new()));
+    for id in 0..4 {
+        graph.borrow_mut().insert(
+            id,
+            Gc::new(Node {
+                id,
+                edges: RefCell::new(HashMap::new()),
+            }),
+        );
+    }
+
+    {
+        let nodes = graph.borrow();
+        // 0 -> 1 -> 2 -> 0, 2 -> 3 -> 3
+        connect(&nodes[&0], &nodes[&1]);
+        connect(&nodes[&1], &nodes[&2]);
+        connect(&nodes[&2], &nodes[&0]);
+        connect(&nodes[&2], &nodes[&3]);
+        connect(&nodes[&3], &nodes[&3]);
+
+        // reroute 1 -> 2 into 1 -> 3
+        nodes[&1].edges.borrow_mut().remove(&2);
+        connect(&nodes[&1], &nodes[&3]);
+
+        // collecting while a live node's edges are borrowed must not free anything
+        let borrow = nodes[&2].edges.borrow_mut();
+        collect();
+        drop(borrow);
+    }
+
+    collect();
+    for count in &COUNTS {
+        assert_eq!(count.load(Ordering::Relaxed), 0);
+    }
+
+    drop(graph);
+    collect();
+    for count in &COUNTS {
+        assert_eq!(count.load(Ordering::Relaxed), 1);
+    }
+}
+
+#[test]
+/// Test cycles which pass through `Option`, `Result`, and `Box` fields of a derived type.
+fn wrapper_cycle() {
+    type Link = Option<Gc<Wrapped>>;
+
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Collectable)]
+    struct Wrapped {
+        next: RefCell<Link>,
+        result: RefCell<Result<Gc<Wrapped>, Box<Link>>>,
+    }
+
+    impl Drop for Wrapped {
+        fn drop(&mut self) {
+            COUNT.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    let new_wrapped = || {
+        Gc::new(Wrapped {
+            next: RefCell::new(None),
+            result: RefCell::new(Err(Box::new(None))),
+        })
+    };
+
+    // a self-loop through an `Option`
+    let gc = new_wrapped();
+    *gc.next.borrow_mut() = Some(Gc::clone(&gc));
+    drop(gc);
+    collect();
+    assert_eq!(COUNT.load(Ordering::Relaxed), 1);
+
+    // a 2-cycle through the `Ok` variant of a `Result`
+    let gc1 = new_wrapped();
+    let gc2 = new_wrapped();
+    *gc1.result.borrow_mut() = Ok(Gc::clone(&gc2));
+    *gc2.result.borrow_mut() = Ok(Gc::clone(&gc1));
+    drop(gc1);
+    drop(gc2);
+    collect();
+    assert_eq!(COUNT.load(Ordering::Relaxed), 3);
+
+    // a self-loop through a `Box` in the `Err` variant of a `Result`
+    let gc = new_wrapped();
+    *gc.result.borrow_mut() = Err(Box::new(Some(Gc::clone(&gc))));
+    collect();
+    assert_eq!(COUNT.load(Ordering::Relaxed), 3);
+    drop(gc);
+    collect();
+    assert_eq!(COUNT.load(Ordering::Relaxed), 4);
+}
+
+#[test]
+/// Test that a derived type may contain a `Cell` of a `Copy` type.
+fn copy_cell() {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Collectable)]
+    struct Indexed {
+        index: Cell<usize>,
+        next: RefCell<Option<Gc<Indexed>>>,
+    }
+
+    impl Drop for Indexed {
+        fn drop(&mut self) {
+            COUNT.fetch_add(self.index.get(), Ordering::Relaxed);
+        }
+    }
+
+    let gc = Gc::new(Indexed {
+        index: Cell::new(1),
+        next: RefCell::new(None),
+    });
+    *gc.next.borrow_mut() = Some(Gc::clone(&gc));
+    gc.index.set(5);
+
+    collect();
+    assert_eq!(COUNT.load(Ordering::Relaxed), 0);
+    assert_eq!(gc.index.get(), 5);
+    drop(gc);
+    collect();
+    assert_eq!(COUNT.load(Ordering::Relaxed), 5);
+}
+
+#[test]
+/// Test that fields marked with `#[collectable(unsafe_skip)]` need not be `Collectable`.
+fn unsafe_skip() {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    /// A type which does not implement `Collectable`.
+    struct Handle(u32);
+
+    #[derive(Collectable)]
+    struct Named {
+        #[collectable(unsafe_skip)]
+        handle: Handle,
+        next: RefCell<Option<Gc<Named>>>,
+    }
+
+    #[derive(Collectable)]
+    struct Unnamed(#[collectable(unsafe_skip)] Handle, Gc<Named>);
+
+    #[derive(Collectable)]
+    #[allow(unused)]
+    enum Either {
+        Named {
+            #[collectable(unsafe_skip)]
+            handle: Handle,
+            gc: Gc<Named>,
+        },
+        Unnamed(#[collectable(unsafe_skip)] Handle, Gc<Named>),
+    }
+
+    impl Drop for Named {
+        fn drop(&mut self) {
+            COUNT.fetch_add(self.handle.0 as usize, Ordering::Relaxed);
+        }
+    }
+
+    let named = Gc::new(Named {
+        handle: Handle(1),
+        next: RefCell::new(None),
+    });
+    *named.next.borrow_mut() = Some(Gc::clone(&named));
+    let unnamed = Gc::new(Unnamed(Handle(0), Gc::clone(&named)));
+    let either = Gc::new(Either::Unnamed(Handle(0), Gc::clone(&named)));
+    let either2 = Gc::new(Either::Named {
+        handle: Handle(0),
+        gc: Gc::clone(&named),
+    });
+    drop(named);
+
+    collect();
+    assert_eq!(COUNT.load(Ordering::Relaxed), 0);
+    assert_eq!(unnamed.0 .0, 0);
+    drop((unnamed, either, either2));
+    collect();
+    assert_eq!(COUNT.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+/// Test that fields marked with `#[collectable(trace_with = "...")]` are visited by the given
+/// function.
+fn trace_with() {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    /// A container from a library which does not know about `dumpster`.
+    struct SmallVec<T> {
+        len: usize,
+        inline: [Option<T>; 2],
+    }
+
+    impl<T> SmallVec<T> {
+        fn push(&mut self, value: T) {
+            self.inline[self.len] = Some(value);
+            self.len += 1;
+        }
+
+        fn iter(&self) -> impl Iterator<Item = &T> {
+            self.inline.iter().flatten()
+        }
+    }
+
+    fn trace_small_vec<T: dumpster::Collectable, V: Visitor>(
+        small: &RefCell<SmallVec<T>>,
+        visitor: &mut V,
+    ) -> Result<(), ()> {
+        for value in small.try_borrow().map_err(|_| ())?.iter() {
+            value.accept(visitor)?;
+        }
+        Ok(())
+    }
+
+    #[derive(Collectable)]
+    struct Node {
+        #[collectable(trace_with = "trace_small_vec")]
+        edges: RefCell<SmallVec<Gc<Node>>>,
+    }
+
+    // the type of a field traced by a function is not required to be `Collectable`, so the bound
+    // must be given by hand
+    #[derive(Collectable)]
+    #[collectable(bound = "T: dumpster::Collectable")]
+    #[allow(unused)]
+    enum Edges<T> {
+        Named {
+            #[collectable(trace_with = "trace_small_vec")]
+            edges: RefCell<SmallVec<T>>,
+        },
+        Unnamed(#[collectable(trace_with = "trace_small_vec")] RefCell<SmallVec<T>>),
+    }
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            COUNT.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    let new_node = || {
+        Gc::new(Node {
+            edges: RefCell::new(SmallVec {
+                len: 0,
+                inline: [None, None],
+            }),
+        })
+    };
+    let a = new_node();
+    let b = new_node();
+    a.edges.borrow_mut().push(b.clone());
+    b.edges.borrow_mut().push(a.clone());
+    let edges = Gc::new(Edges::Unnamed(RefCell::new(SmallVec {
+        len: 1,
+        inline: [Some(b), None],
+    })));
+
+    drop(a);
+    collect();
+    assert_eq!(COUNT.load(Ordering::Relaxed), 0);
+    drop(edges);
+    collect();
+    assert_eq!(COUNT.load(Ordering::Relaxed), 2);
+}
+
+#[test]
+/// Test that the bounds of derived implementations for generic types are inferred correctly.
+fn generic_bounds() {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Collectable)]
+    struct Wrapper<T: dumpster::Collectable + 'static>(Gc<T>);
+
+    #[derive(Collectable)]
+    enum Either<L, R> {
+        Left(L),
+        Right(R),
+    }
+
+    /// A type which does not implement `Collectable`.
+    struct Meters;
+
+    #[derive(Collectable)]
+    #[collectable(bound = "")]
+    struct Tagged<U: 'static> {
+        next: RefCell<Option<Gc<Tagged<U>>>>,
+        unit: PhantomData<U>,
+    }
+
+    impl<U> Drop for Tagged<U> {
+        fn drop(&mut self) {
+            COUNT.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    // a cycle through both generic types
+    let tagged: Gc<Tagged<Meters>> = Gc::new(Tagged {
+        next: RefCell::new(None),
+        unit: PhantomData,
+    });
+    let either: Gc<Either<Wrapper<Tagged<Meters>>, u8>> =
+        Gc::new(Either::Left(Wrapper(Gc::clone(&tagged))));
+    let right: Gc<Either<Wrapper<Tagged<Meters>>, u8>> = Gc::new(Either::Right(1));
+    *tagged.next.borrow_mut() = Some(Gc::clone(&tagged));
+
+    drop(tagged);
+    collect();
+    assert_eq!(COUNT.load(Ordering::Relaxed), 0);
+    assert!(matches!(*right, Either::Right(1)));
+    drop(either);
+    collect();
+    assert_eq!(COUNT.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+/// Test deriving `Collectable` for unions.
+fn union_field() {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    struct Node {
+        next: RefCell<Option<Gc<Link>>>,
+    }
+
+    unsafe impl dumpster::Collectable for Node {
+        fn accept<V: dumpster::Visitor>(&self, visitor: &mut V) -> Result<(), ()> {
+            self.next.accept(visitor)
+        }
+    }
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            COUNT.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[derive(Collectable)]
+    #[collectable(unsafe_union_field = "node")]
+    union Link {
+        node: ManuallyDrop<Gc<Node>>,
+        #[allow(unused)]
+        raw: usize,
+    }
+
+    #[derive(Collectable)]
+    #[collectable(unsafe_skip)]
+    #[allow(unused)]
+    union Bits {
+        int: u32,
+        float: f32,
+    }
+
+    // a cycle which passes through the active field of a union
+    let node = Gc::new(Node {
+        next: RefCell::new(None),
+    });
+    *node.next.borrow_mut() = Some(Gc::new(Link {
+        node: ManuallyDrop::new(Gc::clone(&node)),
+    }));
+    let bits = Gc::new(Bits { int: 1 });
+
+    collect();
+    assert_eq!(COUNT.load(Ordering::Relaxed), 0);
+    drop(node);
+    collect();
+    assert_eq!(COUNT.load(Ordering::Relaxed), 1);
+    assert_eq!(unsafe { bits.int }, 1);
+}
+
+/// Mutually recursive types in different modules, where `b` also has its own items named
+/// `dumpster` and `Result` which the derived code must not pick up.
+mod a {
+    use std::{cell::RefCell, sync::atomic::Ordering};
+
+    use dumpster::unsync::Gc;
+    use dumpster_derive::Collectable;
+
+    use super::b;
+
+    #[derive(Collectable)]
+    pub struct Node {
+        pub edges: RefCell<Vec<Gc<b::Edge>>>,
+    }
+
+    impl Drop for Node {
+        fn drop(&mut self) {
+            super::MODULE_DROPS.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+mod b {
+    use std::sync::atomic::Ordering;
+
+    use ::dumpster::unsync::Gc;
+    use dumpster_derive::Collectable;
+
+    use super::a;
+
+    #[allow(unused)]
+    mod dumpster {}
+
+    #[allow(unused)]
+    type Result<T> = std::result::Result<T, String>;
+
+    #[derive(Collectable)]
+    pub struct Edge {
+        pub to: Gc<a::Node>,
+    }
+
+    impl Drop for Edge {
+        fn drop(&mut self) {
+            super::MODULE_DROPS.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// The number of `a::Node`s and `b::Edge`s which have been dropped.
+static MODULE_DROPS: AtomicUsize = AtomicUsize::new(0);
+
+#[test]
+/// Test a cycle through types deriving `Collectable` in different modules.
+fn mutual_modules() {
+    let node = Gc::new(a::Node {
+        edges: RefCell::new(Vec::new()),
+    });
+    let edge = Gc::new(b::Edge {
+        to: Gc::clone(&node),
+    });
+    node.edges.borrow_mut().push(edge);
+
+    collect();
+    assert_eq!(MODULE_DROPS.load(Ordering::Relaxed), 0);
+    drop(node);
+    collect();
+    assert_eq!(MODULE_DROPS.load(Ordering::Relaxed), 2);
+}
+
+#[test]
+/// Test that `#[derive(Acyclic)]` and `Gc::new_acyclic` only accept types without `Gc`s in them.
+fn acyclic_ui() {
+    let t = trybuild::TestCases::new();
+    t.pass("ui/acyclic_ok.rs");
+    t.compile_fail("ui/acyclic_gc.rs");
+    t.compile_fail("ui/acyclic_new.rs");
+    t.compile_fail("ui/acyclic_manual.rs");
+}
+
+#[test]
+/// Test that zero-sized marker fields need no `unsafe_skip` to derive `Collectable`.
+fn marker_ui() {
+    let t = trybuild::TestCases::new();
+    t.pass("ui/marker_ok.rs");
+}
+
+#[test]
+/// Test that closures, which could hide `Gc`s, are rejected as fields of collectable types.
+fn closure_ui() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("ui/closure_gc.rs");
+}
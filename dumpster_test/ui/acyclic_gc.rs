@@ -0,0 +1,10 @@
+use std::cell::RefCell;
+
+use dumpster::{unsync::Gc, Acyclic, Collectable};
+
+#[derive(Collectable, Acyclic)]
+struct Node {
+    next: RefCell<Option<Gc<Node>>>,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use dumpster::{unsync::Gc, Acyclic, Collectable};
+
+#[derive(Collectable)]
+struct Node {
+    next: Option<Gc<Node>>,
+}
+
+impl Acyclic for Node {}
+
+fn main() {}
@@ -0,0 +1,6 @@
+use dumpster::unsync::Gc;
+
+fn main() {
+    let inner = Gc::new(1u8);
+    let _outer = Gc::new_acyclic(vec![inner]);
+}
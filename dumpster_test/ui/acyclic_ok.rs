@@ -0,0 +1,34 @@
+use std::{cell::RefCell, collections::HashMap, marker::PhantomData};
+
+use dumpster::{unsync::Gc, Acyclic, Collectable};
+
+#[derive(Collectable, Acyclic)]
+struct Leaf {
+    name: String,
+    weights: RefCell<HashMap<u32, (f64, bool)>>,
+}
+
+#[derive(Collectable, Acyclic)]
+enum Tree<T> {
+    Leaf(T),
+    Branch(Vec<Tree<T>>),
+}
+
+#[derive(Collectable, Acyclic)]
+struct Tagged<'a, U> {
+    value: &'static str,
+    unit: PhantomData<&'a U>,
+}
+
+fn main() {
+    let leaf = Gc::new_acyclic(Leaf {
+        name: String::from("leaf"),
+        weights: RefCell::new(HashMap::new()),
+    });
+    let tree = Gc::new_acyclic(Tree::Branch(vec![Tree::Leaf(1u8)]));
+    let tagged = Gc::new_acyclic(Tagged::<u8> {
+        value: "three",
+        unit: PhantomData,
+    });
+    drop((leaf, tree, tagged));
+}
@@ -0,0 +1,6 @@
+use dumpster::unsync::Gc;
+
+fn main() {
+    let callback: Box<dyn Fn()> = Box::new(|| {});
+    let _ = Gc::new(callback);
+}
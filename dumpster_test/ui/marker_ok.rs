@@ -0,0 +1,23 @@
+use std::{convert::Infallible, marker::PhantomData};
+
+use dumpster::{unsync::Gc, Collectable};
+
+#[derive(Collectable)]
+struct Handle {
+    id: usize,
+    marker: PhantomData<*const u8>,
+    unit: (),
+    never: Option<Infallible>,
+    next: Option<Gc<Handle>>,
+}
+
+fn main() {
+    let handle = Gc::new(Handle {
+        id: 1,
+        marker: PhantomData,
+        unit: (),
+        never: None,
+        next: None,
+    });
+    assert_eq!(handle.id, 1);
+}